@@ -0,0 +1,418 @@
+//! Bandwidth shaping module
+//! One token bucket that every HTTP transfer (downloads, patch files, launcher updates) draws from
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::settings::AppSettings;
+use crate::transport::{Clock, SystemClock};
+
+// Global shaper, configured from AppSettings at startup and whenever the limit changes
+pub static BANDWIDTH_SHAPER: once_cell::sync::Lazy<Arc<BandwidthShaper>> =
+    once_cell::sync::Lazy::new(|| Arc::new(BandwidthShaper::new()));
+
+/// Sharing weight of a regular download
+pub const DOWNLOAD_WEIGHT: f64 = 1.0;
+/// Patch files are fetched while the user waits for the game to start
+pub const PATCH_WEIGHT: f64 = 4.0;
+/// Launcher updates are fetched while the user waits for the launcher to restart
+pub const UPDATE_WEIGHT: f64 = 4.0;
+
+/// Bucket capacity in seconds of the configured rate
+const BURST_SECONDS: f64 = 0.25;
+const MIN_BURST_BYTES: f64 = 64.0 * 1024.0;
+/// Upper bound on a single sleep so limit changes apply almost instantly
+const MAX_WAIT: Duration = Duration::from_millis(50);
+/// Sleep used when tokens are available but another transfer is next in line
+const TURN_WAIT: Duration = Duration::from_millis(2);
+
+/// One registered transfer. Transfers that should share a single allowance
+/// (e.g. the segments of one download) share one flow.
+struct Flow {
+    label: String,
+    weight: f64,
+    virtual_time: f64, // bytes granted divided by weight, used to pick who goes next
+    waiting: u32,
+    tokens: f64, // only used when every transfer gets the full limit
+    last_refill: Instant,
+    transferred: u64,
+}
+
+struct ShaperState {
+    rate: f64,    // bytes per second, 0 = unlimited
+    shared: bool, // true: the limit is split between transfers, false: every transfer gets the full limit
+    tokens: f64,
+    last_refill: Instant,
+    virtual_time: f64, // virtual time the request granted last started at
+    flows: HashMap<u64, Flow>,
+}
+
+impl ShaperState {
+    fn capacity(&self) -> f64 {
+        (self.rate * BURST_SECONDS).max(MIN_BURST_BYTES)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let capacity = self.capacity();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(capacity);
+        self.last_refill = now;
+    }
+
+    /// Smallest virtual time among transfers currently waiting for bandwidth
+    fn backlog_floor(&self) -> Option<f64> {
+        self.flows.values()
+            .filter(|flow| flow.waiting > 0)
+            .map(|flow| flow.virtual_time)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+pub struct BandwidthShaper {
+    state: Mutex<ShaperState>,
+    next_id: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+/// Status of one registered transfer
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferShareInfo {
+    pub label: String,
+    pub weight: f64,
+    pub transferred: u64,
+    pub waiting: bool,
+}
+
+/// Overall shaper status
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthStatus {
+    pub limit_bytes_per_sec: u64, // 0 = unlimited
+    pub shared: bool,
+    pub transfers: Vec<TransferShareInfo>,
+}
+
+/// Registration of a transfer with a shaper; unregisters itself when dropped
+pub struct TransferHandle {
+    id: u64,
+    shaper: Arc<BandwidthShaper>,
+}
+
+/// Tracks whether an acquire call is counted as waiting, so a dropped future doesn't stall others
+struct Waiter<'a> {
+    id: u64,
+    waiting: bool,
+    shaper: &'a BandwidthShaper,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.shaper.stop_waiting(self.id);
+        }
+    }
+}
+
+impl Default for BandwidthShaper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BandwidthShaper {
+    /// Shaper without a limit; `apply_settings` or `set_limit` configures it
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Shaper that refills and waits on the given clock
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        Self {
+            state: Mutex::new(ShaperState {
+                rate: 0.0,
+                shared: true,
+                tokens: 0.0,
+                last_refill: now,
+                virtual_time: 0.0,
+                flows: HashMap::new(),
+            }),
+            next_id: AtomicU64::new(1),
+            clock,
+        }
+    }
+
+    /// Apply the speed limit from settings (or the open speed window); running transfers
+    /// pick it up on their next chunk
+    pub fn apply_settings(&self, settings: &AppSettings, now: &chrono::DateTime<chrono::Local>) {
+        self.set_limit(settings.effective_speed_limit_mbps(now), settings.divide_speed_enabled);
+    }
+
+    /// Set the limit in MB/s (0 = unlimited). With `shared` the limit is split between
+    /// transfers by weight, otherwise every transfer may use the full limit.
+    pub fn set_limit(&self, speed_limit_mbps: f64, shared: bool) {
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let now = self.clock.now();
+        state.refill(now);
+        state.rate = speed_limit_mbps.max(0.0) * 1024.0 * 1024.0;
+        state.shared = shared;
+        let capacity = state.capacity();
+        state.tokens = state.tokens.min(capacity);
+        for flow in state.flows.values_mut() {
+            flow.tokens = flow.tokens.min(capacity);
+        }
+        log::info!("[Bandwidth] Limit set to {} MB/s ({})", speed_limit_mbps,
+            if shared { "shared between transfers" } else { "per transfer" });
+    }
+
+    /// Register a transfer; its share of the limit is proportional to `weight`
+    pub fn register(self: &Arc<Self>, label: &str, weight: f64) -> TransferHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        // Start level with the transfers already waiting so a newcomer can't claim a burst
+        let virtual_time = state.backlog_floor().unwrap_or(0.0);
+        let capacity = state.capacity();
+        state.flows.insert(id, Flow {
+            label: label.to_string(),
+            weight: weight.max(0.01),
+            virtual_time,
+            waiting: 0,
+            tokens: capacity,
+            last_refill: self.clock.now(),
+            transferred: 0,
+        });
+        TransferHandle { id, shaper: self.clone() }
+    }
+
+    /// Whether a speed limit is in effect
+    pub fn is_limited(&self) -> bool {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).rate > 0.0
+    }
+
+    pub fn get_status(&self) -> BandwidthStatus {
+        let state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        BandwidthStatus {
+            limit_bytes_per_sec: state.rate as u64,
+            shared: state.shared,
+            transfers: state.flows.values().map(|flow| TransferShareInfo {
+                label: flow.label.clone(),
+                weight: flow.weight,
+                transferred: flow.transferred,
+                waiting: flow.waiting > 0,
+            }).collect(),
+        }
+    }
+
+    fn unregister(&self, id: u64) {
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        state.flows.remove(&id);
+    }
+
+    fn set_weight(&self, id: u64, weight: f64) {
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(flow) = state.flows.get_mut(&id) {
+            flow.weight = weight.max(0.01);
+        }
+    }
+
+    fn stop_waiting(&self, id: u64) {
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(flow) = state.flows.get_mut(&id) {
+            flow.waiting = flow.waiting.saturating_sub(1);
+        }
+    }
+
+    /// Take `bytes` for the waiter's flow if allowed now, otherwise return how long to sleep
+    fn try_acquire(&self, waiter: &mut Waiter<'_>, bytes: f64) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let now = self.clock.now();
+        state.refill(now);
+
+        let rate = state.rate;
+        let capacity = state.capacity();
+        let shared = state.shared;
+        let served = state.virtual_time;
+
+        // A transfer that was unregistered is no longer limited
+        let flow = state.flows.get_mut(&waiter.id)?;
+
+        if rate <= 0.0 {
+            if waiter.waiting {
+                flow.waiting = flow.waiting.saturating_sub(1);
+                waiter.waiting = false;
+            }
+            flow.transferred += bytes as u64;
+            return None;
+        }
+
+        // Large chunks only need a full bucket, the remainder is paid off as debt
+        let needed = bytes.min(capacity);
+
+        if !shared {
+            let elapsed = now.duration_since(flow.last_refill).as_secs_f64();
+            flow.tokens = (flow.tokens + elapsed * rate).min(capacity);
+            flow.last_refill = now;
+            if flow.tokens >= needed {
+                flow.tokens -= bytes;
+                flow.transferred += bytes as u64;
+                return None;
+            }
+            let wait = Duration::from_secs_f64((needed - flow.tokens) / rate);
+            return Some(wait.clamp(Duration::from_millis(1), MAX_WAIT));
+        }
+
+        if !waiter.waiting {
+            // A flow that was idle resumes level with the request served last, not with the
+            // transfers still waiting, or every chunk would even out the weights
+            flow.virtual_time = flow.virtual_time.max(served);
+            flow.waiting += 1;
+            waiter.waiting = true;
+        }
+        let virtual_time = flow.virtual_time;
+
+        let next_in_line = state.backlog_floor()
+            .map(|min| virtual_time <= min)
+            .unwrap_or(true);
+
+        if next_in_line && state.tokens >= needed {
+            state.tokens -= bytes;
+            state.virtual_time = served.max(virtual_time);
+            if let Some(flow) = state.flows.get_mut(&waiter.id) {
+                flow.virtual_time += bytes / flow.weight;
+                flow.waiting = flow.waiting.saturating_sub(1);
+                flow.transferred += bytes as u64;
+            }
+            waiter.waiting = false;
+            return None;
+        }
+
+        if state.tokens >= needed {
+            return Some(TURN_WAIT);
+        }
+        let wait = Duration::from_secs_f64((needed - state.tokens) / rate);
+        Some(wait.clamp(Duration::from_millis(1), MAX_WAIT))
+    }
+}
+
+impl TransferHandle {
+    /// Wait until `bytes` may be transferred under the current limit
+    pub async fn acquire(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        let mut waiter = Waiter { id: self.id, waiting: false, shaper: &self.shaper };
+        while let Some(wait) = self.shaper.try_acquire(&mut waiter, bytes as f64) {
+            self.shaper.clock.sleep(wait).await;
+        }
+    }
+
+    /// Change this transfer's share relative to the others
+    pub fn set_weight(&self, weight: f64) {
+        self.shaper.set_weight(self.id, weight);
+    }
+}
+
+impl Drop for TransferHandle {
+    fn drop(&mut self) {
+        self.shaper.unregister(self.id);
+    }
+}
+
+/// Get the current limit and the transfers drawing from it
+#[tauri::command]
+pub fn get_bandwidth_status() -> Result<BandwidthStatus, String> {
+    Ok(BANDWIDTH_SHAPER.get_status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::fake::ManualClock;
+
+    const CHUNK: f64 = 16.0 * 1024.0;
+    const STEP: Duration = Duration::from_millis(10);
+
+    fn shaper(limit_mbps: f64) -> (Arc<ManualClock>, Arc<BandwidthShaper>) {
+        let clock = Arc::new(ManualClock::default());
+        let shaper = Arc::new(BandwidthShaper::with_clock(clock.clone()));
+        shaper.set_limit(limit_mbps, true);
+        (clock, shaper)
+    }
+
+    /// Keep a chunk waiting on every transfer for `duration`, returning the bytes each one got
+    fn saturate(clock: &ManualClock, shaper: &BandwidthShaper, transfers: &[&TransferHandle], duration: Duration) -> Vec<u64> {
+        let mut waiters: Vec<Waiter<'_>> = transfers.iter()
+            .map(|transfer| Waiter { id: transfer.id, waiting: false, shaper })
+            .collect();
+        let mut granted = vec![0u64; transfers.len()];
+        let steps = duration.as_millis() / STEP.as_millis();
+        for _ in 0..steps {
+            clock.advance(STEP);
+            // A transfer asks for its next chunk as soon as it gets one
+            for (waiter, granted) in waiters.iter_mut().zip(granted.iter_mut()) {
+                while shaper.try_acquire(waiter, CHUNK).is_none() {
+                    *granted += CHUNK as u64;
+                }
+            }
+        }
+        granted
+    }
+
+    fn ratio(granted: &[u64]) -> f64 {
+        granted[1] as f64 / granted[0] as f64
+    }
+
+    #[test]
+    fn shares_a_limit_in_proportion_to_weight() {
+        let (clock, shaper) = shaper(1.0);
+        let light = shaper.register("light", 1.0);
+        let heavy = shaper.register("heavy", 3.0);
+
+        let granted = saturate(&clock, &shaper, &[&light, &heavy], Duration::from_secs(10));
+        assert!((2.7..3.3).contains(&ratio(&granted)), "{:?}", granted);
+        // Together they stay within the limit plus one bucket
+        assert!(granted.iter().sum::<u64>() <= 10 * 1024 * 1024 + 256 * 1024, "{:?}", granted);
+    }
+
+    #[test]
+    fn a_weight_change_applies_to_a_running_transfer_at_once() {
+        let (clock, shaper) = shaper(1.0);
+        let download = shaper.register("download", DOWNLOAD_WEIGHT);
+        let promoted = shaper.register("promoted", DOWNLOAD_WEIGHT);
+
+        let granted = saturate(&clock, &shaper, &[&download, &promoted], Duration::from_secs(2));
+        assert!((0.9..1.1).contains(&ratio(&granted)), "{:?}", granted);
+
+        promoted.set_weight(4.0);
+        let granted = saturate(&clock, &shaper, &[&download, &promoted], Duration::from_secs(1));
+        assert!((3.5..4.5).contains(&ratio(&granted)), "{:?}", granted);
+    }
+
+    #[test]
+    fn a_limit_change_applies_without_waiting_for_the_current_refill() {
+        // A 64 KB chunk takes over half a second at 0.1 MB/s
+        let (clock, shaper) = shaper(0.1);
+        let transfer = shaper.register("download", DOWNLOAD_WEIGHT);
+        let mut waiter = Waiter { id: transfer.id, waiting: false, shaper: &shaper };
+        let wait = shaper.try_acquire(&mut waiter, 64.0 * 1024.0).unwrap();
+        assert!(wait <= MAX_WAIT);
+
+        // Raised, the chunk goes through by the time the transfer checks again
+        shaper.set_limit(100.0, true);
+        clock.advance(wait);
+        assert_eq!(shaper.try_acquire(&mut waiter, 64.0 * 1024.0), None);
+
+        // Lowered, the burst saved up at the old rate is cut to the new bucket straight away
+        clock.advance(Duration::from_secs(1));
+        shaper.set_limit(1.0, true);
+        let burst = (0..10).take_while(|_| shaper.try_acquire(&mut waiter, 64.0 * 1024.0).is_none()).count();
+        assert_eq!(burst, 4);
+
+        // Lifted, nothing waits at all
+        shaper.set_limit(0.0, true);
+        assert_eq!(shaper.try_acquire(&mut waiter, 64.0 * 1024.0), None);
+    }
+}
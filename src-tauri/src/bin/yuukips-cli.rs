@@ -0,0 +1,374 @@
+//! YuukiPS Launcher - headless command line interface
+//! Runs downloads, hashing and patching without the UI, for build boxes and SSH sessions.
+//! Results are printed to stdout as one JSON object; progress (with --progress) goes to stderr.
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use app_lib::{DownloadEngine, DownloadItem, DownloadOptions, DownloadStatus, FileLock, RequestHeaders};
+use serde_json::{json, Number, Value};
+
+/// The command ran and succeeded
+const EXIT_OK: u8 = 0;
+/// The command failed (network, file system, API error)
+const EXIT_FAILED: u8 = 1;
+/// Unknown command or missing/invalid arguments
+const EXIT_USAGE: u8 = 2;
+/// A download ended without completing (error, cancelled, paused or held for disk space)
+const EXIT_DOWNLOAD_FAILED: u8 = 3;
+/// The download was queued behind other downloads or outside a download window; nothing in this
+/// process would ever start it, so it is left queued for the launcher
+const EXIT_QUEUED: u8 = 4;
+/// Interrupted with Ctrl+C; the download was paused and can be resumed later
+const EXIT_INTERRUPTED: u8 = 130;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+const USAGE: &str = "\
+Usage: yuukips-cli <command> [arguments]
+
+Commands:
+  download <url> <file> [--name NAME] [--md5 HASH] [--sha256 HASH] [--mirror URL]... [--preallocate]
+           [--header 'NAME: VALUE']... [--user-agent UA] [--progress]
+      Start a download and wait until it finishes; --preallocate reserves the whole file up front
+  resume <download-id> [--progress]
+      Resume a paused or interrupted download and wait until it finishes
+  status <download-id>
+      Show one download
+  list [--status STATUS]
+      Show all downloads known to the launcher, optionally only those with STATUS
+  hash <file> [--algorithm md5|sha256]
+      Print the MD5 (default) or SHA-256 of a file
+  patch-plan <game-id> <version> <channel> <md5>
+      Fetch the patch plan for a game build
+  patch-apply <game-id> <version> <channel> <md5> <game-folder>
+      Apply the patch plan to a game folder
+  patch-restore <game-id> <version> <channel> <md5> <game-folder>
+      Restore the original files of a patched game folder
+  patch-recover
+      Restore the files of a patch session left unfinished by a crash
+
+download and resume share the launcher's download state, so they refuse to run while the
+launcher (or another download or resume) is open; status and list only read it.
+
+Exit codes: 0 success, 1 failure, 2 usage error, 3 download did not complete,
+4 download left queued for the launcher, 130 interrupted";
+
+/// Command line arguments split into positionals and `--flag [value]` options
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+/// Options that take a value; every other `--option` is a switch
+const VALUE_OPTIONS: &[&str] = &["name", "md5", "sha256", "mirror", "status", "algorithm", "header", "user-agent"];
+
+impl Args {
+    fn parse(mut raw: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        while let Some(arg) = raw.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                positional.push(arg);
+                continue;
+            };
+            let value = if VALUE_OPTIONS.contains(&name) {
+                Some(raw.next().ok_or_else(|| format!("Missing value for --{}", name))?)
+            } else {
+                None
+            };
+            options.push((name.to_string(), value));
+        }
+        Ok(Self { positional, options })
+    }
+
+    fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional.get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing argument <{}>", name))
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        self.options.iter()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.clone())
+    }
+
+    fn values(&self, name: &str) -> Vec<String> {
+        self.options.iter()
+            .filter(|(option, _)| option == name)
+            .filter_map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, value)| option == name && value.is_none())
+    }
+}
+
+/// Why a command did not succeed, mapped to an exit code
+enum Failure {
+    Usage(String),
+    Failed(String),
+    Download(Box<DownloadItem>),
+    Queued(Box<DownloadItem>),
+    Interrupted(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string()));
+}
+
+fn parse_number(value: &str, name: &str) -> Result<Number, Failure> {
+    serde_json::from_str::<Number>(value)
+        .map_err(|_| Failure::Usage(format!("<{}> must be a number, got '{}'", name, value)))
+}
+
+/// Game build arguments shared by the patch commands
+fn game_build(args: &Args) -> Result<(Number, String, Number, String), Failure> {
+    let game_id = parse_number(args.positional(1, "game-id").map_err(Failure::Usage)?, "game-id")?;
+    let version = args.positional(2, "version").map_err(Failure::Usage)?.to_string();
+    let channel = parse_number(args.positional(3, "channel").map_err(Failure::Usage)?, "channel")?;
+    let md5 = args.positional(4, "md5").map_err(Failure::Usage)?.to_string();
+    Ok((game_id, version, channel, md5))
+}
+
+/// The launcher's download engine, on the state it shares with the UI. The returned lock keeps
+/// the launcher and other CLI runs off that state until it is dropped.
+fn open_engine() -> Result<(DownloadEngine, FileLock), Failure> {
+    let data_dir = DownloadEngine::default_data_dir();
+    let lock = DownloadEngine::lock_data_dir(&data_dir).map_err(|e| Failure::Failed(format!(
+        "The launcher or another yuukips-cli is using the download state, close it first ({})", e
+    )))?;
+    Ok((DownloadEngine::new(data_dir), lock))
+}
+
+/// The launcher's downloads as last saved, without resuming or rewriting anything
+fn open_engine_read_only() -> DownloadEngine {
+    DownloadEngine::read_only(DownloadEngine::default_data_dir())
+}
+
+/// Poll a download until it stops running; Ctrl+C pauses it so it can be resumed later.
+/// A queued download gives up right away: the slots it waits for are held by the launcher's
+/// downloads, and the CLI starts no other downloads that could free one.
+async fn wait_for_download(engine: &DownloadEngine, download_id: &str, progress: bool) -> Result<DownloadItem, Failure> {
+    let mut last_report = std::time::Instant::now();
+    loop {
+        let download = engine.download(download_id)?
+            .ok_or_else(|| Failure::Failed(format!("Download {} disappeared", download_id)))?;
+
+        match download.status {
+            DownloadStatus::Completed => return Ok(download),
+            DownloadStatus::Error
+            | DownloadStatus::Cancelled
+            | DownloadStatus::Paused
+            | DownloadStatus::InsufficientSpace => return Err(Failure::Download(Box::new(download))),
+            DownloadStatus::Queued => return Err(Failure::Queued(Box::new(download))),
+            DownloadStatus::Downloading | DownloadStatus::Verifying => {}
+        }
+
+        if progress && last_report.elapsed() >= Duration::from_secs(1) {
+            eprintln!("{}", json!({
+                "id": download.id,
+                "status": download.status,
+                "downloadedSize": download.downloaded_size,
+                "totalSize": download.total_size,
+                "progress": download.progress,
+                "speed": download.speed,
+            }));
+            last_report = std::time::Instant::now();
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => {
+                let _ = engine.pause_download(download_id.to_string());
+                return Err(Failure::Interrupted(format!("Interrupted, download {} paused", download_id)));
+            }
+        }
+    }
+}
+
+/// `--header 'Name: value'` options as a header map
+fn request_headers(args: &Args) -> Result<RequestHeaders, Failure> {
+    let mut headers = RequestHeaders::default();
+    for header in args.values("header") {
+        let (name, value) = header.split_once(':')
+            .ok_or_else(|| Failure::Usage(format!("--header must look like 'Name: value', got '{}'", header)))?;
+        headers.0.insert(name.trim().to_string(), value.trim().to_string());
+    }
+    Ok(headers)
+}
+
+fn run_download(args: &Args) -> Result<Value, Failure> {
+    let url = args.positional(1, "url").map_err(Failure::Usage)?.to_string();
+    let file_path = args.positional(2, "file").map_err(Failure::Usage)?.to_string();
+    let options = DownloadOptions {
+        headers: request_headers(args)?,
+        user_agent: args.value("user-agent"),
+        mirrors: args.values("mirror"),
+        expected_md5: args.value("md5"),
+        expected_sha256: args.value("sha256"),
+        preallocate: args.flag("preallocate"),
+        ..Default::default()
+    };
+    let progress = args.flag("progress");
+
+    let (engine, _lock) = open_engine()?;
+    let result = tauri::async_runtime::block_on(async {
+        let download_id = engine.start_download(url, file_path, args.value("name"), options).await?;
+        let download = wait_for_download(&engine, &download_id, progress).await?;
+        Ok(json!(download))
+    });
+    // Keep the launcher's view of the downloads in sync with what happened here
+    let _ = engine.save_state();
+    result
+}
+
+fn run_resume(args: &Args) -> Result<Value, Failure> {
+    let download_id = args.positional(1, "download-id").map_err(Failure::Usage)?.to_string();
+    let progress = args.flag("progress");
+
+    let (engine, _lock) = open_engine()?;
+    let result = tauri::async_runtime::block_on(async {
+        engine.resume_download(download_id.clone()).await?;
+        let download = wait_for_download(&engine, &download_id, progress).await?;
+        Ok(json!(download))
+    });
+    let _ = engine.save_state();
+    result
+}
+
+fn run_status(args: &Args) -> Result<Value, Failure> {
+    let download_id = args.positional(1, "download-id").map_err(Failure::Usage)?;
+    let download = open_engine_read_only().download(download_id)?
+        .ok_or_else(|| Failure::Failed(format!("Download {} not found", download_id)))?;
+    Ok(json!(download))
+}
+
+fn run_list(args: &Args) -> Result<Value, Failure> {
+    let mut downloads = open_engine_read_only().downloads()?;
+    if let Some(status) = args.value("status") {
+        let status: DownloadStatus = serde_json::from_value(Value::String(status.clone()))
+            .map_err(|_| Failure::Usage(format!("Unknown download status: {}", status)))?;
+        downloads.retain(|download| download.status == status);
+    }
+    downloads.sort_by_key(|download| download.start_time);
+    Ok(json!(downloads))
+}
+
+fn run_hash(args: &Args) -> Result<Value, Failure> {
+    let file_path = args.positional(1, "file").map_err(Failure::Usage)?;
+    let path = Path::new(file_path);
+    if !path.is_file() {
+        return Err(Failure::Failed(format!("Not a file: {}", file_path)));
+    }
+
+    let algorithm = args.value("algorithm").unwrap_or_else(|| "md5".to_string()).to_lowercase();
+    let hash = tauri::async_runtime::block_on(async {
+        match algorithm.as_str() {
+            "md5" => app_lib::calculate_md5_streamed(path).await.map_err(Failure::Failed),
+            "sha256" => app_lib::calculate_sha256_streamed(path).await.map_err(Failure::Failed),
+            other => Err(Failure::Usage(format!("Unknown hash algorithm: {}", other))),
+        }
+    })?;
+    Ok(json!({ "file": file_path, "algorithm": algorithm, "hash": hash }))
+}
+
+// The patch functions create their own runtime, so they are called outside `block_on`
+fn run_patch_plan(args: &Args) -> Result<Value, Failure> {
+    let (game_id, version, channel, md5) = game_build(args)?;
+    let plan = app_lib::fetch_patch_info_command(game_id, version, channel, md5)?;
+    serde_json::from_str(&plan).map_err(|e| Failure::Failed(format!("Invalid patch plan: {}", e)))
+}
+
+fn run_patch_apply(args: &Args) -> Result<Value, Failure> {
+    let (game_id, version, channel, md5) = game_build(args)?;
+    let folder = args.positional(5, "game-folder").map_err(Failure::Usage)?.to_string();
+    let (message, plan, patched_files) = app_lib::check_and_apply_patches(game_id, version, channel, md5, folder)?;
+    Ok(json!({ "message": message, "plan": plan, "patchedFiles": patched_files }))
+}
+
+fn run_patch_restore(args: &Args) -> Result<Value, Failure> {
+    let (game_id, version, channel, md5) = game_build(args)?;
+    let folder = args.positional(5, "game-folder").map_err(Failure::Usage)?.to_string();
+    let message = app_lib::restore_game_files(game_id, version, channel, md5, folder)?;
+    Ok(json!({ "message": message }))
+}
+
+fn run_patch_recover() -> Result<Value, Failure> {
+    let session = app_lib::pending_patch_session();
+    let message = app_lib::recover_patch_session()?;
+    Ok(json!({ "message": message, "session": session }))
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return ExitCode::from(EXIT_OK);
+    }
+
+    let result = match args.positional.first().map(String::as_str) {
+        Some("download") => run_download(&args),
+        Some("resume") => run_resume(&args),
+        Some("status") => run_status(&args),
+        Some("list") => run_list(&args),
+        Some("hash") => run_hash(&args),
+        Some("patch-plan") => run_patch_plan(&args),
+        Some("patch-apply") => run_patch_apply(&args),
+        Some("patch-restore") => run_patch_restore(&args),
+        Some("patch-recover") => run_patch_recover(),
+        Some("help") => {
+            println!("{}", USAGE);
+            return ExitCode::from(EXIT_OK);
+        }
+        Some(other) => Err(Failure::Usage(format!("Unknown command: {}", other))),
+        None => Err(Failure::Usage("Missing command".to_string())),
+    };
+
+    match result {
+        Ok(value) => {
+            print_json(&json!({ "ok": true, "result": value }));
+            ExitCode::from(EXIT_OK)
+        }
+        Err(Failure::Usage(message)) => {
+            print_json(&json!({ "ok": false, "error": message }));
+            eprintln!("\n{}", USAGE);
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(Failure::Failed(message)) => {
+            print_json(&json!({ "ok": false, "error": message }));
+            ExitCode::from(EXIT_FAILED)
+        }
+        Err(Failure::Download(download)) => {
+            let error = download.error_message.clone()
+                .unwrap_or_else(|| format!("Download stopped with status {:?}", download.status));
+            print_json(&json!({ "ok": false, "error": error, "result": download }));
+            ExitCode::from(EXIT_DOWNLOAD_FAILED)
+        }
+        Err(Failure::Queued(download)) => {
+            let error = format!("Download {} is queued behind other downloads or outside the download window; the launcher will start it", download.id);
+            print_json(&json!({ "ok": false, "error": error, "result": download }));
+            ExitCode::from(EXIT_QUEUED)
+        }
+        Err(Failure::Interrupted(message)) => {
+            print_json(&json!({ "ok": false, "error": message }));
+            ExitCode::from(EXIT_INTERRUPTED)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Binary delta module
+//! Applies bsdiff deltas, which rebuild a new file from an old one using much less data than the new file itself
+
+use bzip2::read::BzDecoder;
+use std::io::Read;
+
+const BSDIFF43_MAGIC: &[u8] = b"ENDSLEY/BSDIFF43";
+
+/// Read a bsdiff integer: 8 bytes little-endian magnitude, sign in the top bit
+fn read_offset(reader: &mut impl Read) -> Result<i64, String> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)
+        .map_err(|e| format!("Truncated delta: {}", e))?;
+    let negative = bytes[7] & 0x80 != 0;
+    bytes[7] &= 0x7f;
+    let magnitude = i64::from_le_bytes(bytes);
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+fn read_length(reader: &mut impl Read, what: &str) -> Result<usize, String> {
+    let value = read_offset(reader)?;
+    usize::try_from(value).map_err(|_| format!("Invalid {} length in delta: {}", what, value))
+}
+
+fn read_block(reader: &mut impl Read, len: usize, what: &str) -> Result<Vec<u8>, String> {
+    let mut block = Vec::new();
+    reader.take(len as u64).read_to_end(&mut block)
+        .map_err(|e| format!("Failed to decompress delta: {}", e))?;
+    if block.len() < len {
+        return Err(format!("Truncated delta: {} block", what));
+    }
+    Ok(block)
+}
+
+/// Apply an `ENDSLEY/BSDIFF43` delta to `old`: the magic, the output size, then one bzip2 stream
+/// of control triples (diff length, extra length, seek), each followed by its diff and extra bytes
+pub fn apply_bsdiff_delta(old: &[u8], delta: &[u8]) -> Result<Vec<u8>, String> {
+    let mut header = delta.strip_prefix(BSDIFF43_MAGIC)
+        .ok_or("Not a bsdiff delta (missing ENDSLEY/BSDIFF43 header)")?;
+    let new_size = read_length(&mut header, "output")?;
+    let mut reader = BzDecoder::new(header);
+
+    // A size claim far beyond what the delta could expand to is not worth allocating for up front
+    let mut new = Vec::with_capacity(new_size.min(delta.len().saturating_mul(64)));
+    let mut old_pos: i64 = 0;
+
+    while new.len() < new_size {
+        let diff_len = read_length(&mut reader, "diff")?;
+        let extra_len = read_length(&mut reader, "extra")?;
+        let seek = read_offset(&mut reader)?;
+        if diff_len.checked_add(extra_len).map_or(true, |len| len > new_size - new.len()) {
+            return Err("Delta writes past the end of the output".to_string());
+        }
+
+        // Diff bytes are added to the old file's bytes; positions outside it count as zero
+        let diff = read_block(&mut reader, diff_len, "diff")?;
+        for (offset, byte) in diff.iter().enumerate() {
+            let old_byte = usize::try_from(old_pos.saturating_add(offset as i64)).ok()
+                .and_then(|index| old.get(index))
+                .copied()
+                .unwrap_or(0);
+            new.push(byte.wrapping_add(old_byte));
+        }
+        old_pos = old_pos.saturating_add(diff_len as i64);
+
+        // Extra bytes are copied as they are
+        new.extend(read_block(&mut reader, extra_len, "extra")?);
+        old_pos = old_pos.saturating_add(seek);
+    }
+
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bzip2::write::BzEncoder;
+    use bzip2::Compression;
+    use std::io::Write;
+
+    fn offset(value: i64) -> [u8; 8] {
+        let mut bytes = value.unsigned_abs().to_le_bytes();
+        if value < 0 {
+            bytes[7] |= 0x80;
+        }
+        bytes
+    }
+
+    /// Delta from bsdiff's control, diff and extra blocks
+    fn delta(new_size: usize, blocks: &[(&[u8], &[u8], i64)]) -> Vec<u8> {
+        let mut body = BzEncoder::new(Vec::new(), Compression::best());
+        for (diff, extra, seek) in blocks {
+            body.write_all(&offset(diff.len() as i64)).unwrap();
+            body.write_all(&offset(extra.len() as i64)).unwrap();
+            body.write_all(&offset(*seek)).unwrap();
+            body.write_all(diff).unwrap();
+            body.write_all(extra).unwrap();
+        }
+        let mut delta = BSDIFF43_MAGIC.to_vec();
+        delta.extend_from_slice(&offset(new_size as i64));
+        delta.extend(body.finish().unwrap());
+        delta
+    }
+
+    #[test]
+    fn rebuilds_the_new_file_from_the_old_one() {
+        let old = b"GameAssembly v1.0 build 0042 END";
+        let new = b"GameAssembly v1.1 build 0043 -- patched END";
+
+        // Copy the first 17 bytes with one changed digit, insert the new build tag,
+        // then skip ahead to "END" in the old file to reuse it
+        let mut diff = vec![0u8; 17];
+        diff[16] = 1; // '0' -> '1'
+        let delta = delta(new.len(), &[
+            (&diff, b" build 0043 -- patched ", 12),
+            (&[0, 0, 0], b"", 0),
+        ]);
+
+        assert_eq!(apply_bsdiff_delta(old, &delta).unwrap(), new);
+    }
+
+    #[test]
+    fn rejects_deltas_that_overrun_the_output() {
+        let delta = delta(4, &[(b"", b"too long", 0)]);
+        assert!(apply_bsdiff_delta(b"", &delta).is_err());
+    }
+}
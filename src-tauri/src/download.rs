@@ -6,22 +6,63 @@ use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Instant;
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use tauri::{command, Emitter, State};
 use uuid::Uuid;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::io::{AsyncWriteExt};
-use chrono::Utc;
-use crate::settings::SETTINGS;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use crate::headers::{header_list, redact_headers, resolve_request_headers, same_host, DownloadAuth, RequestHeaders};
+use crate::bandwidth::{BandwidthShaper, TransferHandle};
+use crate::history::{HistoryFilter, HistoryPage, HistoryStore};
+use crate::install::InstallJobStore;
+use crate::journal::Journal;
+use crate::mirrors::MirrorHealth;
+use crate::pipeline::PipelineStore;
+use crate::settings::{AppSettings, SharedSettings, SETTINGS};
 use crate::system::get_yuukips_data_path;
+use crate::throughput::ThroughputHistory;
+use crate::transport::{Clock, DownloadTransport, ReqwestTransport, SystemClock};
 
-static DOWNLOAD_MANAGER: once_cell::sync::Lazy<Arc<Mutex<DownloadManager>>> = 
-    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(DownloadManager::new())));
+/// One download manager with the network, clock and data directory it runs on.
+/// Cheap to clone; the launcher keeps one in Tauri state and hands clones to download tasks.
+#[derive(Clone)]
+pub struct DownloadEngine {
+    inner: Arc<EngineInner>,
+}
+
+struct EngineInner {
+    manager: Mutex<DownloadManager>,
+    active_requests: Mutex<HashSet<String>>, // downloads with an HTTP transfer in flight
+    transport: Arc<dyn DownloadTransport>,
+    clock: Arc<dyn Clock>,
+    settings: SharedSettings,
+    shaper: Arc<BandwidthShaper>,
+    mirror_health: MirrorHealth,
+    history: HistoryStore,
+    pipelines: PipelineStore,
+    install_jobs: InstallJobStore,
+}
+
+/// Free space kept untouched on the target volume when admitting downloads
+const DISK_SPACE_SAFETY_MARGIN: u64 = 100 * 1024 * 1024;
+
+/// Minimum time between two progress events for the same download
+const PROGRESS_EVENT_INTERVAL_MS: u128 = 250;
+
+/// How many times a download is fetched again after a checksum mismatch before it fails
+const MAX_CHECKSUM_RETRIES: u32 = 2;
+
+/// Minimum time between two journaled progress updates for the same download
+const JOURNAL_INTERVAL_MS: u128 = 1000;
+
+/// Journal entries after which the journal is compacted into a fresh snapshot
+const JOURNAL_COMPACT_ENTRIES: usize = 1000;
 
-static ACTIVE_DOWNLOAD_REQUESTS: once_cell::sync::Lazy<Arc<Mutex<HashSet<String>>>> = 
-    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+pub const DOWNLOAD_STATUS_EVENT: &str = "download-status";
+pub const DOWNLOAD_QUEUE_EVENT: &str = "download-queue";
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DownloadItem {
@@ -52,9 +93,123 @@ pub struct DownloadItem {
     pub user_paused: bool, // Track if pause was initiated by user
     #[serde(rename = "resumeSupported", default)]
     pub resume_supported: bool, // Track if server supports resumable downloads
+    #[serde(rename = "groupId", default)]
+    pub group_id: Option<String>, // Shared by all parts of one install job
+    #[serde(rename = "expectedSize", default)]
+    pub expected_size: Option<u64>, // Size announced by the caller, used for space checks before the server is probed
+    #[serde(default)]
+    pub mirrors: Vec<String>, // Alternative URLs for the same file, in preference order after `url`
+    #[serde(rename = "activeUrl", default)]
+    pub active_url: Option<String>, // Mirror the download is currently (or was last) fetched from
+    #[serde(rename = "expectedMd5", default)]
+    pub expected_md5: Option<String>, // Lowercase hex, checked once the transfer finishes
+    #[serde(rename = "expectedSha256", default)]
+    pub expected_sha256: Option<String>,
+    #[serde(rename = "verifyAttempts", default)]
+    pub verify_attempts: u32, // Checksum mismatches so far; each one downloads the file again
+    #[serde(default)]
+    pub priority: DownloadPriority,
+    #[serde(rename = "queuePosition", default)]
+    pub queue_position: i64, // Order within the priority, lower starts first
+    #[serde(default)]
+    pub preallocate: bool, // File is grown to its full size up front, so only downloaded_size tells progress
+    #[serde(default, skip_serializing)]
+    pub headers: RequestHeaders, // Sent with requests to the host of `url`, after the host rules; only the state file keeps them
+    #[serde(default, skip_serializing)]
+    pub auth: Option<DownloadAuth>,
+    #[serde(rename = "userAgent", default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+/// Optional settings accepted when a download is started
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DownloadOptions {
+    #[serde(rename = "groupId", default)]
+    pub group_id: Option<String>,
+    #[serde(rename = "expectedSize", default)]
+    pub expected_size: Option<u64>,
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    #[serde(rename = "expectedMd5", default)]
+    pub expected_md5: Option<String>,
+    #[serde(rename = "expectedSha256", default)]
+    pub expected_sha256: Option<String>,
+    #[serde(default)]
+    pub priority: DownloadPriority,
+    /// Reserve the whole file on disk as soon as its size is known
+    #[serde(default)]
+    pub preallocate: bool,
+    /// Extra request headers, such as a Referer or an API key
+    #[serde(default)]
+    pub headers: RequestHeaders,
+    #[serde(default)]
+    pub auth: Option<DownloadAuth>,
+    #[serde(rename = "userAgent", default)]
+    pub user_agent: Option<String>,
+}
+
+/// Queued downloads start in priority order, then by their position in the queue
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Pinned, // Small patch-related files that must not wait behind large archives
+}
+
+impl DownloadPriority {
+    /// Share of the bandwidth limit relative to other transfers
+    fn bandwidth_weight(self) -> f64 {
+        match self {
+            DownloadPriority::Low => crate::bandwidth::DOWNLOAD_WEIGHT / 2.0,
+            DownloadPriority::Normal => crate::bandwidth::DOWNLOAD_WEIGHT,
+            DownloadPriority::High => crate::bandwidth::DOWNLOAD_WEIGHT * 2.0,
+            DownloadPriority::Pinned => crate::bandwidth::PATCH_WEIGHT,
+        }
+    }
+}
+
+/// Payload of `download-progress`, throttled per download
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgressEvent {
+    pub sequence: u64,
+    pub timestamp: u64, // milliseconds since epoch
+    pub id: String,
+    pub downloaded_size: u64,
+    pub total_size: u64,
+    pub progress: f64,
+    pub speed: u64,
+    pub time_remaining: u64,
 }
 
+/// Payload of `download-status`, sent on every status transition
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadStatusEvent {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub id: String,
+    pub status: DownloadStatus,
+    pub previous_status: Option<DownloadStatus>,
+    pub error_message: Option<String>,
+    pub download: DownloadItem,
+}
 
+/// Payload of `download-queue`, a snapshot sent whenever downloads enter, leave or move through the queue
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadQueueEvent {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub reason: String,
+    pub downloading: Vec<String>,
+    pub queued: Vec<String>, // in the order they will be started
+    pub held: Vec<String>,   // waiting for disk space
+    pub total_downloads: usize,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DownloadStats {
@@ -74,6 +229,9 @@ pub enum DownloadStatus {
     Error,
     Cancelled,
     Queued,
+    #[serde(rename = "insufficient_space")]
+    InsufficientSpace, // Held until enough free space is available on the target volume
+    Verifying, // Transfer finished, the file is being checked against its expected hash
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -101,6 +259,11 @@ pub enum ActivityType {
     FileAdded,
     StatusChanged,
     UserInteraction,
+    DownloadScheduled,
+    ScheduleChanged,
+    MirrorSwitched,
+    ChecksumMismatch,
+    CompletionAction,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -109,6 +272,31 @@ pub struct DownloadState {
     pub download_directory: String,
     pub version: u32,
     pub timestamp: u64,
+    #[serde(default)]
+    pub partial_downloads: HashMap<String, PartialDownloadInfo>,
+    #[serde(default)]
+    pub credentials: HashMap<String, DownloadCredentials>, // Headers and auth by download ID, left out of DownloadItem's own serialization
+}
+
+/// Request headers and auth of one download, kept in the state file only
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadCredentials {
+    #[serde(default, skip_serializing_if = "RequestHeaders::is_empty")]
+    pub headers: RequestHeaders,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<DownloadAuth>,
+}
+
+/// Change to one download written ahead of the next snapshot, so a crash loses at most a second of progress
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StateJournalEntry {
+    generation: u32, // snapshot the change was made on top of
+    id: String,
+    status: DownloadStatus,
+    downloaded_size: u64,
+    total_size: u64,
+    #[serde(default)]
+    segments: Vec<u64>, // bytes downloaded per segment, for segmented downloads
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -119,7 +307,28 @@ pub struct PartialDownloadInfo {
     pub downloaded_size: u64,
     pub total_size: u64,
     pub last_modified: Option<String>,
-    pub etag: Option<String>
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub segments: Vec<DownloadSegment>,
+}
+
+/// Byte range handled by one connection of a segmented download
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadSegment {
+    pub index: u32,
+    pub start: u64,
+    pub end: u64, // inclusive
+    pub downloaded: u64,
+}
+
+impl DownloadSegment {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    fn is_complete(&self) -> bool {
+        self.downloaded >= self.len()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -135,62 +344,121 @@ struct DownloadManager {
     activities: Vec<ActivityEntry>,
     download_directory: PathBuf,
     cancellation_tokens: HashMap<String, Arc<AtomicBool>>,
+    transfers: HashMap<String, Weak<TransferHandle>>, // bandwidth registrations of running downloads
     partial_downloads: HashMap<String, PartialDownloadInfo>,
     auto_save_enabled: bool,
-    last_save_time: SystemTime,
-    state_version: u32,
+    last_save_time: Instant,
+    state_version: u32, // generation of the last snapshot; bumped by every save
+    journal: Journal<StateJournalEntry>, // progress and status changes since that snapshot
+    journal_entries: usize, // entries appended since that snapshot
+    last_journal_write: HashMap<String, std::time::Instant>,
+    last_progress_event: HashMap<String, std::time::Instant>,
+    schedule_waiting: HashSet<String>, // downloads already told they are waiting for a download window
+    throughput: ThroughputHistory, // saved alongside each state snapshot
+    data_dir: PathBuf, // state, journal and activities are kept here
+    clock: Arc<dyn Clock>,
+    settings: SharedSettings, // queue limit and download windows
+    engine: Weak<EngineInner>, // for spawning download tasks from within the manager
+    event_handle: Option<tauri::AppHandle>, // pushes download events to the frontend once set
+    event_sequence: AtomicU64, // shared by every event so listeners can order them and detect gaps
+}
+
+impl DownloadItem {
+    /// Sort key for the queue: higher priority first, then queue position, then age
+    fn queue_key(&self) -> (std::cmp::Reverse<DownloadPriority>, i64, u64) {
+        (std::cmp::Reverse(self.priority), self.queue_position, self.start_time)
+    }
+    
+    /// Hash the finished file must have, as (algorithm, lowercase hex); SHA-256 wins if both are set
+    pub(crate) fn expected_checksum(&self) -> Option<(&'static str, String)> {
+        self.expected_sha256.clone().map(|hash| ("SHA-256", hash))
+            .or_else(|| self.expected_md5.clone().map(|hash| ("MD5", hash)))
+    }
 }
 
 impl DownloadManager {
-    fn new() -> Self {
-        log::info!("Initializing DownloadManager");
+    fn new(data_dir: PathBuf, clock: Arc<dyn Clock>, settings: SharedSettings, engine: Weak<EngineInner>) -> Self {
+        log::info!("Initializing DownloadManager in {:?}", data_dir);
         let download_directory = dirs::download_dir()
             .unwrap_or_else(|| PathBuf::from("./downloads"));
         
-        let mut manager = Self {
+        Self {
             downloads: HashMap::new(),
             activities: Vec::new(),
             download_directory,
             cancellation_tokens: HashMap::new(),
+            transfers: HashMap::new(),
             partial_downloads: HashMap::new(),
             auto_save_enabled: true,
-            last_save_time: SystemTime::now(),
+            last_save_time: clock.now(),
             state_version: 1,
-        };
+            journal: Journal::new(data_dir.join("download_state.journal")),
+            journal_entries: 0,
+            last_journal_write: HashMap::new(),
+            last_progress_event: HashMap::new(),
+            schedule_waiting: HashSet::new(),
+            throughput: ThroughputHistory::default(),
+            data_dir,
+            clock,
+            settings,
+            engine,
+            event_handle: None,
+            event_sequence: AtomicU64::new(0),
+        }
+    }
+    
+    /// Load the persisted state and pick up downloads that were interrupted.
+    /// Runs once the engine exists, since resuming may spawn tasks on it.
+    fn restore(&mut self) {
+        self.throughput = ThroughputHistory::load(&self.get_throughput_file_path());
         
         // Load persisted state (includes activities and downloads)
-        match manager.load_state() {
+        match self.load_state() {
             Ok(_) => {
                 log::info!("Successfully loaded download state");
             },
             Err(e) => {
                 log::error!("Failed to load state: {}", e);
                 // Fallback to loading just activities for backward compatibility
-                if let Err(e) = manager.load_activities() {
+                if let Err(e) = self.load_activities() {
                     log::error!("Failed to load activities: {}", e);
                 }
             }
         }
         
         // Resume interrupted downloads
-        if let Err(e) = manager.resume_interrupted_downloads() {
+        if let Err(e) = self.resume_interrupted_downloads() {
             log::error!("Failed to resume interrupted downloads: {}", e);
         }
         
         // Ensure state file exists
-        if let Err(e) = manager.save_state() {
+        if let Err(e) = self.save_state() {
             log::error!("Failed to save initial state: {}", e);
         } else {
             log::info!("Initial state saved successfully");
         }
-        
-        manager
+    }
+    
+    /// The engine this manager belongs to, for spawning download tasks
+    fn engine(&self) -> Option<DownloadEngine> {
+        self.engine.upgrade().map(|inner| DownloadEngine { inner })
+    }
+    
+    /// How many downloads may run at once
+    fn max_downloads(&self) -> u32 {
+        self.settings.lock().map(|settings| settings.max_simultaneous_downloads).unwrap_or(3)
+    }
+    
+    /// Why queued downloads may not start right now, or None if they may
+    fn schedule_wait_reason(&self) -> Option<String> {
+        let settings = self.settings.lock().ok()?;
+        crate::schedule::schedule_wait_reason(&settings.download_schedule, &self.clock.local_now())
     }
     
     fn add_activity(&mut self, action_type: ActivityType, file_name: Option<String>, identifier: Option<String>, status: Option<String>, details: Option<String>) {
         // Check for duplicate activities within the last 5 seconds for the same identifier and action type
         if let Some(ref identifier_str) = identifier {
-            let now = Utc::now();
+            let now = self.clock.utc_now();
             let duplicate_found = self.activities.iter().rev().take(50).any(|activity| {
                 if activity.identifier.as_ref() == Some(identifier_str) && 
                    std::mem::discriminant(&activity.action_type) == std::mem::discriminant(&action_type) {
@@ -210,7 +478,7 @@ impl DownloadManager {
         
         let activity = ActivityEntry {
             id: Uuid::new_v4().to_string(),
-            timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            timestamp: self.clock.utc_now().format("%Y-%m-%d %H:%M:%S").to_string(),
             action_type,
             file_name,
             identifier,
@@ -226,14 +494,12 @@ impl DownloadManager {
         }
     }
     
-    fn get_activities_file_path() -> PathBuf {
-        let yuukips_dir = get_yuukips_data_path()
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(yuukips_dir).join("activities.json")
+    fn get_activities_file_path(&self) -> PathBuf {
+        self.data_dir.join("activities.json")
     }
     
     fn save_activities(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = Self::get_activities_file_path();
+        let file_path = self.get_activities_file_path();
         
         // Create directory if it doesn't exist
         if let Some(parent) = file_path.parent() {
@@ -241,12 +507,12 @@ impl DownloadManager {
         }
         
         let json = serde_json::to_string_pretty(&self.activities)?;
-        fs::write(file_path, json)?;
+        crate::utils::write_file_atomic(&file_path, json.as_bytes())?;
         Ok(())
     }
     
     fn load_activities(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = Self::get_activities_file_path();
+        let file_path = self.get_activities_file_path();
         
         if file_path.exists() {
             let json = fs::read_to_string(file_path)?;
@@ -264,43 +530,62 @@ impl DownloadManager {
     }
     
     // State persistence methods
-    fn get_state_file_path() -> PathBuf {
-        let yuukips_dir = get_yuukips_data_path()
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(yuukips_dir).join("download_state.json")
+    fn get_state_file_path(&self) -> PathBuf {
+        self.data_dir.join("download_state.json")
     }
     
-
+    fn get_throughput_file_path(&self) -> PathBuf {
+        self.data_dir.join("throughput_history.json")
+    }
     
-
+    /// Previous snapshot, used when the current one can't be read
+    fn get_state_backup_path(&self) -> PathBuf {
+        self.get_state_file_path().with_extension("json.bak")
+    }
     
     fn create_download_state(&self) -> Result<DownloadState, Box<dyn std::error::Error>> {
         let state = DownloadState {
             downloads: self.downloads.clone(),
             download_directory: self.download_directory.to_string_lossy().to_string(),
             version: self.state_version,
-            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            timestamp: self.clock.unix_time(),
+            partial_downloads: self.partial_downloads.clone(),
+            credentials: self.downloads.iter()
+                .filter(|(_, d)| !d.headers.is_empty() || d.auth.is_some())
+                .map(|(id, d)| (id.clone(), DownloadCredentials { headers: d.headers.clone(), auth: d.auth.clone() }))
+                .collect(),
         };
         
         Ok(state)
     }
     
     fn save_state(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // Each snapshot starts a new generation, which journal entries written after it refer to
+        self.state_version = self.state_version.wrapping_add(1);
         let state = self.create_download_state()?;
-        let file_path = Self::get_state_file_path();
+        let file_path = self.get_state_file_path();
         
         // Create directory if it doesn't exist
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent)?;
         }
         
-        // Write new state
+        // Write new state; the previous snapshot is kept as the fallback
         let json = serde_json::to_string_pretty(&state)?;
-        fs::write(&file_path, json)?;
+        crate::utils::write_file_atomic_with_backup(&file_path, json.as_bytes(), &self.get_state_backup_path())?;
+        
+        // Everything journaled so far is part of the snapshot now
+        if let Err(e) = self.journal.clear() {
+            log::warn!("Failed to clear state journal: {}", e);
+        }
+        self.journal_entries = 0;
+        
+        self.last_save_time = self.clock.now();
         
-        self.last_save_time = SystemTime::now();
+        if let Err(e) = self.throughput.save(&self.get_throughput_file_path()) {
+            log::warn!("{}", e);
+        }
         
-        // Update partial download info for active downloads
         // Update partial download info for all active downloads
         let active_downloads: Vec<_> = self.downloads.iter()
             .filter(|(_, download)| matches!(download.status, DownloadStatus::Downloading | DownloadStatus::Paused))
@@ -315,28 +600,151 @@ impl DownloadManager {
     }
     
     fn load_state(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let file_path = Self::get_state_file_path();
+        let file_path = self.get_state_file_path();
         
         log::info!("Attempting to load state from: {:?}", file_path);
         
-        // Try to load state file, create new if it fails
-        match self.try_load_state_file(&file_path) {
-            Ok(state) => {
-                log::info!("Successfully loaded state file");
-                // Apply loaded state
-                self.downloads = state.downloads;
-                self.download_directory = PathBuf::from(state.download_directory);
-                self.state_version = state.version;
-            },
+        // Try the state file, then the previous snapshot, and create new state if neither loads
+        let state = match self.try_load_state_file(&file_path) {
+            Ok(state) => Some(state),
             Err(e) => {
-                log::warn!("Failed to load state file: {}, creating new state", e);
-                // Keep current defaults, no need to change anything
+                log::warn!("Failed to load state file: {}, trying the previous snapshot", e);
+                if file_path.exists() {
+                    // Keep the unreadable file for inspection, out of the way of the next save
+                    let corrupt_path = file_path.with_extension("json.corrupt");
+                    if let Err(e) = fs::rename(&file_path, &corrupt_path) {
+                        log::warn!("Failed to move unreadable state file aside: {}", e);
+                    }
+                }
+                match self.try_load_state_file(&self.get_state_backup_path()) {
+                    Ok(state) => {
+                        log::warn!("Recovered download state from the previous snapshot");
+                        Some(state)
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to load previous snapshot: {}, creating new state", e);
+                        None
+                    }
+                }
             }
+        };
+        
+        if let Some(state) = state {
+            log::info!("Successfully loaded state file");
+            self.apply_state(state);
         }
         
         Ok(())
     }
     
+    /// Load the state file (or the previous snapshot) and replay the journal, without writing anything.
+    /// Auto-save is turned off so later changes in this process stay in memory.
+    fn load_read_only(&mut self) {
+        self.auto_save_enabled = false;
+        let state = self.try_load_state_file(&self.get_state_file_path())
+            .or_else(|_| self.try_load_state_file(&self.get_state_backup_path()));
+        match state {
+            Ok(state) => self.apply_state(state),
+            Err(e) => log::warn!("Failed to load download state: {}", e),
+        }
+    }
+    
+    /// Take over a loaded snapshot and the journal written after it
+    fn apply_state(&mut self, state: DownloadState) {
+        self.downloads = state.downloads;
+        for (id, credentials) in state.credentials {
+            if let Some(download) = self.downloads.get_mut(&id) {
+                download.headers = credentials.headers;
+                download.auth = credentials.auth;
+            }
+        }
+        self.download_directory = PathBuf::from(state.download_directory);
+        self.state_version = state.version;
+        self.partial_downloads = state.partial_downloads;
+        self.replay_journal();
+        self.assign_missing_queue_positions();
+    }
+    
+    /// Apply changes journaled after the loaded snapshot was written
+    fn replay_journal(&mut self) {
+        let entries: Vec<StateJournalEntry> = self.journal.read_all().into_iter()
+            .filter(|entry| entry.generation >= self.state_version)
+            .collect();
+        if entries.is_empty() {
+            return;
+        }
+        
+        log::info!("Replaying {} state journal entries", entries.len());
+        for entry in entries {
+            let Some(download) = self.downloads.get_mut(&entry.id) else {
+                continue;
+            };
+            download.status = entry.status;
+            download.downloaded_size = entry.downloaded_size;
+            download.total_size = entry.total_size;
+            download.progress = if entry.total_size > 0 {
+                (entry.downloaded_size as f64 / entry.total_size as f64) * 100.0
+            } else {
+                0.0
+            };
+            
+            if let Some(partial) = self.partial_downloads.get_mut(&entry.id) {
+                partial.downloaded_size = entry.downloaded_size;
+                partial.total_size = entry.total_size;
+                if partial.segments.len() == entry.segments.len() {
+                    for (segment, downloaded) in partial.segments.iter_mut().zip(&entry.segments) {
+                        segment.downloaded = *downloaded;
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Journal the current progress and status of a download, at most once per `JOURNAL_INTERVAL_MS` unless forced.
+    /// Forced entries (status changes, restarts, finished transfers) are synced to disk before returning.
+    fn journal_download(&mut self, id: &str, force: bool) {
+        let now = self.clock.now();
+        if !force {
+            if let Some(last) = self.last_journal_write.get(id) {
+                if now.duration_since(*last).as_millis() < JOURNAL_INTERVAL_MS {
+                    return;
+                }
+            }
+        }
+        
+        let Some(download) = self.downloads.get(id) else {
+            return;
+        };
+        let entry = StateJournalEntry {
+            generation: self.state_version,
+            id: id.to_string(),
+            status: download.status.clone(),
+            downloaded_size: download.downloaded_size,
+            total_size: download.total_size,
+            segments: self.partial_downloads.get(id)
+                .map(|partial| partial.segments.iter().map(|segment| segment.downloaded).collect())
+                .unwrap_or_default(),
+        };
+        self.last_journal_write.insert(id.to_string(), now);
+        
+        if let Err(e) = self.journal.append(&entry) {
+            log::warn!("Failed to journal download {}: {}", id, e);
+            return;
+        }
+        self.journal_entries += 1;
+        
+        if self.journal_entries >= JOURNAL_COMPACT_ENTRIES {
+            // The snapshot is synced and takes the journal's place
+            if let Err(e) = self.save_state() {
+                log::warn!("Failed to compact state journal: {}", e);
+            }
+        } else if force {
+            if let Err(e) = self.journal.sync() {
+                log::warn!("Failed to sync state journal: {}", e);
+            }
+        }
+    }
+    
     fn try_load_state_file(&self, file_path: &Path) -> Result<DownloadState, Box<dyn std::error::Error>> {
         if !file_path.exists() {
             return Err("State file does not exist".into());
@@ -354,9 +762,7 @@ impl DownloadManager {
         }
         
         // Auto-save every 30 seconds or when significant changes occur
-        let should_save = self.last_save_time.elapsed()
-            .map(|d| d.as_secs() >= 30)
-            .unwrap_or(true);
+        let should_save = self.clock.now().duration_since(self.last_save_time).as_secs() >= 30;
         
         if should_save {
             self.save_state()?;
@@ -368,19 +774,144 @@ impl DownloadManager {
     fn update_partial_download_info(&mut self, id: &str, downloaded: u64, total: u64) {
         if let Some(download) = self.downloads.get(id) {
             if matches!(download.status, DownloadStatus::Downloading | DownloadStatus::Paused) {
-                let partial_info = PartialDownloadInfo {
-                    id: id.to_string(),
-                    url: download.url.clone(),
-                    file_path: download.file_path.clone(),
-                    downloaded_size: downloaded,
-                    total_size: total,
-                    last_modified: None, // Will be populated during download
-                    etag: None, // Will be populated during download
-                };
+                // Keep validators and segment progress already recorded for this download
+                let partial_info = self.partial_downloads.entry(id.to_string())
+                    .or_insert_with(|| PartialDownloadInfo {
+                        id: id.to_string(),
+                        url: download.url.clone(),
+                        file_path: download.file_path.clone(),
+                        downloaded_size: 0,
+                        total_size: 0,
+                        last_modified: None, // Will be populated during download
+                        etag: None, // Will be populated during download
+                        segments: Vec::new(),
+                    });
                 
-                self.partial_downloads.insert(id.to_string(), partial_info);
+                partial_info.url = download.url.clone();
+                partial_info.file_path = download.file_path.clone();
+                partial_info.downloaded_size = downloaded;
+                partial_info.total_size = total;
+            }
+        }
+    }
+    
+    /// Store the server's validators for a partial download; existing ones are kept unless `replace`
+    fn record_validators(&mut self, id: &str, info: &crate::mirrors::RemoteFileInfo, replace: bool) {
+        if let Some(partial) = self.partial_downloads.get_mut(id) {
+            if replace || (partial.etag.is_none() && partial.last_modified.is_none()) {
+                partial.etag.clone_from(&info.etag);
+                partial.last_modified.clone_from(&info.last_modified);
+            }
+        }
+    }
+    
+    /// Why the server's file no longer matches the partial download, or None if it may be resumed
+    fn remote_file_change(&self, id: &str, info: &crate::mirrors::RemoteFileInfo) -> Option<String> {
+        let partial = self.partial_downloads.get(id)?;
+        let stored = crate::mirrors::RemoteFileInfo {
+            // A response without a length says nothing about the size
+            size: (partial.total_size > 0 && info.size.is_some()).then_some(partial.total_size),
+            etag: partial.etag.clone(),
+            last_modified: partial.last_modified.clone(),
+            accept_ranges: true,
+        };
+        stored.matches(info).err()
+    }
+    
+    /// Throw away a partial download whose remote file changed so it starts again from zero
+    fn restart_changed_download(&mut self, id: &str, reason: &str) {
+        let Some(download) = self.downloads.get(id) else {
+            return;
+        };
+        let file_name = download.file_name.clone();
+        
+        log::warn!("[Rust] Remote file changed for ID {} ({}), discarding {} bytes and restarting", id, reason, download.downloaded_size);
+        self.discard_downloaded_data(id);
+        
+        self.add_activity(
+            ActivityType::StatusChanged,
+            Some(file_name),
+            Some(id.to_string()),
+            Some("restarted".to_string()),
+            Some(format!("The file on the server changed ({}), so the download restarted from zero", reason))
+        );
+        
+        if let Err(e) = self.auto_save_state() {
+            log::error!("Failed to auto-save state: {}", e);
+        }
+    }
+    
+    /// Delete the file and segment plan of a download and reset its progress to zero
+    fn discard_downloaded_data(&mut self, id: &str) {
+        let Some(download) = self.downloads.get(id) else {
+            return;
+        };
+        let file_path = download.file_path.clone();
+        if let Err(e) = fs::remove_file(&file_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("[Rust] Failed to remove downloaded data {}: {}", file_path, e);
             }
         }
+        self.partial_downloads.remove(id);
+        self.update_download_progress(id, 0, 0, 0);
+    }
+    
+    fn needs_checksum_verification(&self, id: &str) -> bool {
+        self.downloads.get(id).is_some_and(|download| {
+            download.status != DownloadStatus::Verifying && download.expected_checksum().is_some()
+        })
+    }
+    
+    /// Move a finished transfer to Verifying and hash it in the background
+    fn begin_checksum_verification(&mut self, id: &str) {
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.speed = 0;
+            download.time_remaining = 0;
+        }
+        self.set_download_status(id, DownloadStatus::Verifying, None);
+        
+        // Hashing doesn't use the network, so the slot goes to the next queued download
+        self.start_next_queued_download();
+        if let Some(engine) = self.engine() {
+            engine.spawn_checksum_verification(id.to_string());
+        }
+    }
+    
+    /// Download the file again after a checksum mismatch, or fail once the retries are used up
+    fn retry_after_checksum_mismatch(&mut self, id: &str, algorithm: &str, expected: &str, actual: &str) {
+        let Some(download) = self.downloads.get_mut(id) else {
+            return;
+        };
+        download.verify_attempts += 1;
+        let attempts = download.verify_attempts;
+        let file_name = download.file_name.clone();
+        
+        if attempts > MAX_CHECKSUM_RETRIES {
+            log::error!("[Rust] {} mismatch for ID {} after {} attempts, giving up", algorithm, id, attempts);
+            self.set_download_status(id, DownloadStatus::Error, Some(format!(
+                "{} mismatch after {} attempts: expected {}, got {}", algorithm, attempts, expected, actual
+            )));
+            return;
+        }
+        
+        log::warn!("[Rust] {} mismatch for ID {} (expected {}, got {}), downloading again ({}/{})",
+            algorithm, id, expected, actual, attempts, MAX_CHECKSUM_RETRIES);
+        self.discard_downloaded_data(id);
+        self.add_activity(
+            ActivityType::ChecksumMismatch,
+            Some(file_name),
+            Some(id.to_string()),
+            Some("restarted".to_string()),
+            Some(format!("{} mismatch: expected {}, got {}. Downloading again (retry {}/{})",
+                algorithm, expected, actual, attempts, MAX_CHECKSUM_RETRIES))
+        );
+        
+        self.set_download_status_no_cleanup(id, DownloadStatus::Queued, None);
+        self.start_next_queued_download();
+        
+        if let Err(e) = self.auto_save_state() {
+            log::error!("Failed to auto-save state: {}", e);
+        }
     }
     
     /// Verify file integrity and detect corruption
@@ -401,8 +932,9 @@ impl DownloadManager {
         Ok(true)
     }
     
-    /// Detect and repair corrupted partial downloads
-    async fn repair_corrupted_download(file_path: &str, expected_size: u64) -> Result<u64, String> {
+    /// Detect and repair corrupted partial downloads, returning how many bytes can be resumed from.
+    /// A preallocated file is full size from the start, so its length only bounds the `recorded` progress.
+    async fn repair_corrupted_download(file_path: &str, expected_size: u64, recorded: Option<u64>) -> Result<u64, String> {
         let metadata = tokio::fs::metadata(file_path).await
             .map_err(|e| format!("Failed to get file metadata: {}", e))?;
         
@@ -421,11 +953,11 @@ impl DownloadManager {
             file.set_len(expected_size).await
                 .map_err(|e| format!("Failed to truncate file: {}", e))?;
             
-            return Ok(expected_size);
+            return Ok(recorded.map_or(expected_size, |recorded| recorded.min(expected_size)));
         }
         
         // If file is smaller or equal to expected size, return actual size
-        Ok(actual_size)
+        Ok(recorded.map_or(actual_size, |recorded| recorded.min(actual_size)))
     }
     
     fn resume_interrupted_downloads(&mut self) -> Result<Vec<String>, String> {
@@ -465,6 +997,16 @@ impl DownloadManager {
         log::info!("Found {} interrupted downloads and {} paused downloads", 
             interrupted_downloads.len(), paused_downloads.len());
         
+        // Downloads that were being hashed when the launcher closed are checked again
+        for (id, download) in &self.downloads {
+            if download.status == DownloadStatus::Verifying {
+                log::info!("Restarting checksum verification for: {}", download.file_name);
+                if let Some(engine) = self.engine() {
+                    engine.spawn_checksum_verification(id.clone());
+                }
+            }
+        }
+        
         let mut resumed_ids = Vec::new();
         
         // Handle interrupted downloads (set to paused and mark for auto-resume)
@@ -510,7 +1052,7 @@ impl DownloadManager {
         Ok(resumed_ids)
     }
 
-    fn add_download(&mut self, url: String, file_path: String, file_name: Option<String>) -> String {
+    fn add_download(&mut self, url: String, file_path: String, file_name: Option<String>, options: DownloadOptions) -> String {
         // Clean the URL by trimming whitespace and removing trailing commas/semicolons
         let cleaned_url = url.trim().trim_end_matches(',').trim_end_matches(';').to_string();
         log::info!("[Rust] Cleaned URL from '{}' to '{}'", url, cleaned_url);
@@ -519,7 +1061,7 @@ impl DownloadManager {
         for (existing_id, existing_download) in &self.downloads {
             if existing_download.url == cleaned_url && existing_download.file_path == file_path {
                 match existing_download.status {
-                    DownloadStatus::Downloading | DownloadStatus::Queued => {
+                    DownloadStatus::Downloading | DownloadStatus::Queued | DownloadStatus::InsufficientSpace => {
                         log::warn!("[Rust] Duplicate download detected for URL '{}' and path '{}', returning existing ID: {}", cleaned_url, file_path, existing_id);
                         return existing_id.clone();
                     }
@@ -548,8 +1090,13 @@ impl DownloadManager {
 
         // Check if we've reached the max simultaneous downloads limit
         let downloading_count = self.count_downloading_only();
-        let max_downloads = crate::settings::get_app_max_simultaneous_downloads().unwrap_or(3);
-        let initial_status = if downloading_count >= max_downloads {
+        let max_downloads = self.max_downloads();
+        // Everything already running or queued has first claim on the free space
+        let space_error = self.check_disk_space(&file_path, options.expected_size.unwrap_or(0), None, true).err();
+        let schedule_wait = self.schedule_wait_reason();
+        let initial_status = if space_error.is_some() {
+            DownloadStatus::InsufficientSpace
+        } else if schedule_wait.is_some() || downloading_count >= max_downloads {
             DownloadStatus::Queued
         } else {
             DownloadStatus::Downloading
@@ -567,14 +1114,24 @@ impl DownloadManager {
             time_remaining: 0,
             url: cleaned_url.clone(),
             file_path: file_path.clone(),
-            start_time: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            start_time: self.clock.unix_time(),
             end_time: None,
-            error_message: None,
+            error_message: space_error.clone(),
             user_paused: false,
             resume_supported: false, // Default to false, will be updated during download
+            group_id: options.group_id,
+            expected_size: options.expected_size,
+            mirrors: options.mirrors,
+            active_url: None,
+            expected_md5: options.expected_md5,
+            expected_sha256: options.expected_sha256,
+            verify_attempts: 0,
+            priority: options.priority,
+            queue_position: self.next_queue_position(),
+            preallocate: options.preallocate,
+            headers: options.headers,
+            auth: options.auth,
+            user_agent: options.user_agent,
         };
 
         // Add activity entry for file addition
@@ -595,6 +1152,23 @@ impl DownloadManager {
                 Some("downloading".to_string()),
                 Some(format!("Download started for file: {}", file_path))
             );
+        } else if let Some(reason) = space_error {
+            self.add_activity(
+                ActivityType::StatusChanged,
+                Some(actual_file_name),
+                Some(id.clone()),
+                Some("insufficient_space".to_string()),
+                Some(format!("Download held for {}: {}", file_path, reason))
+            );
+        } else if let Some(reason) = schedule_wait {
+            self.schedule_waiting.insert(id.clone());
+            self.add_activity(
+                ActivityType::DownloadScheduled,
+                Some(actual_file_name),
+                Some(id.clone()),
+                Some("queued".to_string()),
+                Some(format!("{}: {}", reason, file_path))
+            );
         } else {
             self.add_activity(
                 ActivityType::StatusChanged,
@@ -606,6 +1180,7 @@ impl DownloadManager {
         }
 
         self.downloads.insert(id.clone(), download);
+        self.emit_queue_event("added");
         
         // Auto-save state after adding download
         if let Err(e) = self.auto_save_state() {
@@ -617,6 +1192,12 @@ impl DownloadManager {
 
     fn update_download_progress(&mut self, id: &str, downloaded: u64, total: u64, speed: u64) {
         if let Some(download) = self.downloads.get_mut(id) {
+            // Only running transfers report a speed; the other updates are resets and resume bookkeeping
+            if speed > 0 {
+                let transferred = downloaded.saturating_sub(download.downloaded_size);
+                self.throughput.record(id, transferred, self.clock.unix_time());
+            }
+            
             download.downloaded_size = downloaded;
             download.total_size = total;
             download.progress = if total > 0 {
@@ -634,6 +1215,10 @@ impl DownloadManager {
             // Update partial download info
             self.update_partial_download_info(id, downloaded, total);
             
+            // Restarts from zero and finished transfers are journaled right away
+            self.journal_download(id, downloaded == 0 || (total > 0 && downloaded >= total));
+            self.emit_progress_event(id, total > 0 && downloaded >= total);
+            
             // Auto-save state periodically during progress updates
             if let Err(e) = self.auto_save_state() {
                 log::error!("Failed to auto-save state during progress update: {}", e);
@@ -642,8 +1227,15 @@ impl DownloadManager {
     }
 
     fn set_download_status(&mut self, id: &str, status: DownloadStatus, error_message: Option<String>) {
+        // A download with an expected checksum only completes once the verifier has passed it
+        if status == DownloadStatus::Completed && self.needs_checksum_verification(id) {
+            self.begin_checksum_verification(id);
+            return;
+        }
+        
+        let mut previous_status = None;
         if let Some(download) = self.downloads.get_mut(id) {
-            let _old_status = download.status.clone();
+            previous_status = Some(download.status.clone());
             download.status = status.clone();
             download.error_message = error_message.clone();
             
@@ -653,17 +1245,15 @@ impl DownloadManager {
         }
         
         // Now we can safely call add_activity without borrowing conflicts
-        if self.downloads.contains_key(id) {
-            let download = &self.downloads[id];
-            let file_name = download.file_name.clone();
-            let old_status = download.status.clone();
+        if let Some(old_status) = previous_status {
+            let file_name = self.downloads[id].file_name.clone();
             
             let activity_type = match status {
                 DownloadStatus::Completed => ActivityType::DownloadCompleted,
                 DownloadStatus::Error => ActivityType::DownloadError,
                 DownloadStatus::Cancelled => ActivityType::DownloadCancelled,
                 DownloadStatus::Paused => ActivityType::DownloadPaused,
-                DownloadStatus::Queued => ActivityType::StatusChanged,
+                DownloadStatus::Queued | DownloadStatus::InsufficientSpace | DownloadStatus::Verifying => ActivityType::StatusChanged,
                 DownloadStatus::Downloading => {
                     if matches!(old_status, DownloadStatus::Paused) {
                         ActivityType::DownloadResumed
@@ -680,6 +1270,8 @@ impl DownloadManager {
                 DownloadStatus::Paused => "paused".to_string(),
                 DownloadStatus::Downloading => "downloading".to_string(),
                 DownloadStatus::Queued => "queued".to_string(),
+                DownloadStatus::InsufficientSpace => "insufficient_space".to_string(),
+                DownloadStatus::Verifying => "verifying".to_string(),
             };
             
             let details = if let Some(ref err_msg) = error_message {
@@ -695,25 +1287,33 @@ impl DownloadManager {
                 Some(status_str),
                 details
             );
+            
+            self.journal_download(id, true);
+            self.emit_status_event(id, Some(old_status));
         }
         
         // Handle final state cleanup and queue management
+        let engine = self.engine();
         if let Some(download) = self.downloads.get_mut(id) {
             if matches!(status, DownloadStatus::Completed | DownloadStatus::Error | DownloadStatus::Cancelled) {
-                download.end_time = Some(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
-                );
+                download.end_time = Some(self.clock.unix_time());
+                
+                if let Some(engine) = engine {
+                    engine.history().record(download);
+                    if status == DownloadStatus::Completed {
+                        crate::pipeline::on_download_completed(engine, download.id.clone(), download.group_id.clone());
+                    }
+                }
                 
                 // Clean up cancellation token only for final states
                 self.cancellation_tokens.remove(id);
+                self.transfers.remove(id);
+                self.last_journal_write.remove(id);
                 
-
-                
-                // Remove from partial downloads when completed/cancelled/error
-                self.partial_downloads.remove(id);
+                // A failed download keeps its segment plan, so a restart can continue each segment
+                if status != DownloadStatus::Error {
+                    self.partial_downloads.remove(id);
+                }
                 
                 // Start next queued download if a download slot is now available
                 self.start_next_queued_download();
@@ -727,10 +1327,114 @@ impl DownloadManager {
     }
     
     fn set_download_status_no_cleanup(&mut self, id: &str, status: DownloadStatus, error_message: Option<String>) {
+        let mut previous_status = None;
         if let Some(download) = self.downloads.get_mut(id) {
+            previous_status = Some(download.status.clone());
             download.status = status;
             download.error_message = error_message;
         }
+        
+        if let Some(old_status) = previous_status {
+            self.journal_download(id, true);
+            self.emit_status_event(id, Some(old_status));
+        }
+    }
+    
+    fn next_event_sequence(&self) -> u64 {
+        self.event_sequence.fetch_add(1, Ordering::Relaxed) + 1
+    }
+    
+    fn emit_download_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app_handle) = &self.event_handle {
+            if let Err(e) = app_handle.emit(event, payload) {
+                log::warn!("[Rust] Failed to emit {} event: {}", event, e);
+            }
+        }
+    }
+    
+    /// Push a progress event for a download, at most once per `PROGRESS_EVENT_INTERVAL_MS` unless forced
+    fn emit_progress_event(&mut self, id: &str, force: bool) {
+        let now = self.clock.now();
+        if !force {
+            if let Some(last) = self.last_progress_event.get(id) {
+                if now.duration_since(*last).as_millis() < PROGRESS_EVENT_INTERVAL_MS {
+                    return;
+                }
+            }
+        }
+        
+        let Some(download) = self.downloads.get(id) else {
+            return;
+        };
+        self.last_progress_event.insert(id.to_string(), now);
+        
+        let event = DownloadProgressEvent {
+            sequence: self.next_event_sequence(),
+            timestamp: self.clock.utc_now().timestamp_millis() as u64,
+            id: id.to_string(),
+            downloaded_size: download.downloaded_size,
+            total_size: download.total_size,
+            progress: download.progress,
+            speed: download.speed,
+            time_remaining: download.time_remaining,
+        };
+        self.emit_download_event(DOWNLOAD_PROGRESS_EVENT, event);
+    }
+    
+    /// Push a status event, plus a queue snapshot when the transition affects the queue
+    fn emit_status_event(&mut self, id: &str, previous_status: Option<DownloadStatus>) {
+        let Some(download) = self.downloads.get(id) else {
+            return;
+        };
+        if previous_status.as_ref() == Some(&download.status) {
+            return;
+        }
+        
+        let event = DownloadStatusEvent {
+            sequence: self.next_event_sequence(),
+            timestamp: self.clock.utc_now().timestamp_millis() as u64,
+            id: id.to_string(),
+            status: download.status.clone(),
+            previous_status: previous_status.clone(),
+            error_message: download.error_message.clone(),
+            download: download.clone(),
+        };
+        self.emit_download_event(DOWNLOAD_STATUS_EVENT, event);
+        
+        let is_queue_status = |status: &DownloadStatus| matches!(
+            status,
+            DownloadStatus::Downloading | DownloadStatus::Queued | DownloadStatus::InsufficientSpace
+        );
+        if is_queue_status(&download.status) || previous_status.as_ref().map(is_queue_status).unwrap_or(false) {
+            self.emit_queue_event("status_changed");
+        }
+        
+        // The final progress event is forced, so the throttle entry is no longer needed
+        if !matches!(download.status, DownloadStatus::Downloading) {
+            self.last_progress_event.remove(id);
+        }
+    }
+    
+    /// Push a snapshot of running, queued and held downloads
+    fn emit_queue_event(&self, reason: &str) {
+        let ids_with_status = |status: DownloadStatus| {
+            let mut items: Vec<&DownloadItem> = self.downloads.values()
+                .filter(|d| d.status == status)
+                .collect();
+            items.sort_by_key(|d| d.queue_key());
+            items.into_iter().map(|d| d.id.clone()).collect::<Vec<String>>()
+        };
+        
+        let event = DownloadQueueEvent {
+            sequence: self.next_event_sequence(),
+            timestamp: self.clock.utc_now().timestamp_millis() as u64,
+            reason: reason.to_string(),
+            downloading: ids_with_status(DownloadStatus::Downloading),
+            queued: ids_with_status(DownloadStatus::Queued),
+            held: ids_with_status(DownloadStatus::InsufficientSpace),
+            total_downloads: self.downloads.len(),
+        };
+        self.emit_download_event(DOWNLOAD_QUEUE_EVENT, event);
     }
 
     fn get_stats(&self) -> DownloadStats {
@@ -772,66 +1476,313 @@ impl DownloadManager {
             .count() as u32
     }
     
-    fn start_next_queued_download(&mut self) {
-        // Check if we have capacity for more downloads
-        let mut downloading_count = self.count_downloading_only();
-        let max_downloads = crate::settings::get_app_max_simultaneous_downloads().unwrap_or(3);
+    /// Atomically claim a download for a new perform_download task by inserting its
+    /// cancellation token and setting it to Downloading. Returns false if the download
+    /// is not in the expected state or already has a running task.
+    fn claim_download(&mut self, download_id: &str, expected_status: &DownloadStatus) -> bool {
+        // Check download status before proceeding
+        if let Some(download) = self.downloads.get(download_id) {
+            if &download.status != expected_status {
+                log::warn!("[Rust] Download ID {} not in expected state (status: {:?}, expected: {:?}), aborting spawn", download_id, download.status, expected_status);
+                return false;
+            }
+        } else {
+            log::warn!("[Rust] Download ID {} not found in manager, aborting spawn", download_id);
+            return false;
+        }
         
-        // Start multiple downloads to fill available slots
-        while downloading_count < max_downloads {
-            // Find the oldest queued download
-            let next_queued = self.downloads.iter()
-                .filter(|(_, download)| matches!(download.status, DownloadStatus::Queued))
-                .min_by_key(|(_, download)| download.start_time)
-                .map(|(id, _)| id.clone());
-            
-            if let Some(download_id) = next_queued {
-                if let Some(download) = self.downloads.get(&download_id) {
-                    let url = download.url.clone();
-                    let file_path = download.file_path.clone();
-                    
-                    // Start the download using centralized concurrency control (expects Queued status)
-                    match spawn_perform_download_if_not_active(download_id.clone(), url, file_path, DownloadStatus::Queued) {
-                        Ok(true) => {
-                            log::info!("[Rust] Started queued download with ID: {}", download_id);
-                        },
-                        Ok(false) => {
-                            log::warn!("[Rust] Queued download already active for ID: {}", download_id);
-                            // Reset status back to queued since we couldn't start it
-                            self.set_download_status_no_cleanup(&download_id, DownloadStatus::Queued, None);
-                            continue; // Try next download
-                        },
-                        Err(e) => {
-                            log::error!("[Rust] Failed to spawn queued download for ID {}: {}", download_id, e);
-                            self.set_download_status(&download_id, DownloadStatus::Error, Some(e));
-                        }
-                    }
-                     
-                     downloading_count += 1; // Update our local count
-                } else {
-                    break; // Download not found, stop trying
-                }
-            } else {
-                break; // No more queued downloads
+        // Use HashMap's entry API for truly atomic insert-if-absent behavior
+        use std::collections::hash_map::Entry;
+        match self.cancellation_tokens.entry(download_id.to_string()) {
+            Entry::Occupied(_) => {
+                log::warn!("[Rust] Download ID {} already has an active perform_download instance, aborting duplicate spawn", download_id);
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Arc::new(AtomicBool::new(false)));
+                self.schedule_waiting.remove(download_id);
+                
+                // Set status to downloading atomically within the same lock
+                self.set_download_status_no_cleanup(download_id, DownloadStatus::Downloading, None);
+                
+                log::info!("[Rust] Claimed download ID {} with cancellation token, spawning task", download_id);
+                true
             }
         }
     }
     
-    fn enforce_download_limit(&mut self) {
-        let max_downloads = crate::settings::get_app_max_simultaneous_downloads().unwrap_or(3);
-        let downloading_only: Vec<_> = self.downloads.iter()
-            .filter(|(_, download)| matches!(download.status, DownloadStatus::Downloading))
-            .map(|(id, download)| (id.clone(), download.start_time))
-            .collect();
+    /// Bytes a download still has to write to disk
+    fn remaining_download_bytes(&self, download: &DownloadItem) -> u64 {
+        let expected_size = if download.total_size > 0 {
+            download.total_size
+        } else {
+            download.expected_size.unwrap_or(0)
+        };
+        // Preallocated segmented files already occupy their full size on disk
+        let size_on_disk = fs::metadata(&download.file_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        expected_size.saturating_sub(download.downloaded_size.max(size_on_disk))
+    }
+    
+    /// Bytes still promised to other downloads on the same volume as `file_path`
+    fn reserved_disk_space(&self, file_path: &str, exclude_id: Option<&str>, include_queued: bool) -> u64 {
+        let volume = crate::system::get_volume_id(Path::new(file_path));
+        self.downloads.values()
+            .filter(|d| Some(d.id.as_str()) != exclude_id)
+            .filter(|d| match d.status {
+                DownloadStatus::Downloading => true,
+                DownloadStatus::Queued => include_queued,
+                _ => false,
+            })
+            .filter(|d| crate::system::get_volume_id(Path::new(&d.file_path)) == volume)
+            .map(|d| self.remaining_download_bytes(d))
+            .sum()
+    }
+    
+    /// Check that `needed` more bytes fit at `file_path` next to what other downloads have been promised
+    fn check_disk_space(&self, file_path: &str, needed: u64, exclude_id: Option<&str>, include_queued: bool) -> Result<(), String> {
+        if needed == 0 {
+            return Ok(());
+        }
         
-        if downloading_only.len() > max_downloads as usize {
-            // Sort by start time to queue the newest downloads first
-            let mut sorted_downloads = downloading_only;
-            sorted_downloads.sort_by_key(|(_, start_time)| *start_time);
-            
-            // Queue the excess downloads (newest ones)
-            let excess_count = sorted_downloads.len() - max_downloads as usize;
-            for (download_id, _) in sorted_downloads.iter().rev().take(excess_count) {
+        let available = match crate::system::get_free_disk_space(Path::new(file_path)) {
+            Ok(available) => available,
+            Err(e) => {
+                // Don't hold downloads just because the volume can't be queried
+                log::warn!("[Rust] Could not query free space for {}: {}", file_path, e);
+                return Ok(());
+            }
+        };
+        let reserved = self.reserved_disk_space(file_path, exclude_id, include_queued);
+        
+        if available < needed + reserved + DISK_SPACE_SAFETY_MARGIN {
+            return Err(format!(
+                "Insufficient disk space: {} needed, {} reserved by other downloads, {} available",
+                crate::utils::format_file_size(needed),
+                crate::utils::format_file_size(reserved),
+                crate::utils::format_file_size(available)
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Park a download until enough disk space is available on its volume
+    fn hold_for_disk_space(&mut self, id: &str, reason: String) {
+        log::warn!("[Rust] Holding download {}: {}", id, reason);
+        self.cancellation_tokens.remove(id);
+        
+        let was_held = self.downloads.get(id)
+            .map(|d| d.status == DownloadStatus::InsufficientSpace)
+            .unwrap_or(true);
+        self.set_download_status_no_cleanup(id, DownloadStatus::InsufficientSpace, Some(reason.clone()));
+        
+        if !was_held {
+            let file_name = self.downloads.get(id).map(|d| d.file_name.clone());
+            self.add_activity(
+                ActivityType::StatusChanged,
+                file_name,
+                Some(id.to_string()),
+                Some("insufficient_space".to_string()),
+                Some(reason)
+            );
+        }
+        
+        if let Err(e) = self.auto_save_state() {
+            log::error!("Failed to auto-save state: {}", e);
+        }
+    }
+    
+    /// Ids of downloads whose status matches, in queue order
+    fn ordered_ids(&self, include: impl Fn(&DownloadStatus) -> bool) -> Vec<String> {
+        let mut items: Vec<&DownloadItem> = self.downloads.values()
+            .filter(|download| include(&download.status))
+            .collect();
+        items.sort_by_key(|download| download.queue_key());
+        items.into_iter().map(|download| download.id.clone()).collect()
+    }
+    
+    fn next_queue_position(&self) -> i64 {
+        self.downloads.values().map(|d| d.queue_position).max().unwrap_or(0) + 1
+    }
+    
+    /// Give downloads saved before queue positions existed a position after the others, oldest first
+    fn assign_missing_queue_positions(&mut self) {
+        let mut missing: Vec<(String, u64)> = self.downloads.values()
+            .filter(|d| d.queue_position == 0)
+            .map(|d| (d.id.clone(), d.start_time))
+            .collect();
+        missing.sort_by_key(|(_, start_time)| *start_time);
+        
+        let mut position = self.next_queue_position();
+        for (id, _) in missing {
+            if let Some(download) = self.downloads.get_mut(&id) {
+                download.queue_position = position;
+                position += 1;
+            }
+        }
+    }
+    
+    /// Swap a waiting download with its neighbour of the same priority; `up` moves it towards the front
+    fn move_in_queue(&mut self, id: &str, up: bool) -> Result<(), String> {
+        let download = self.downloads.get(id).ok_or_else(|| "Download not found".to_string())?;
+        if !is_reorderable(&download.status) {
+            return Err("Only queued or paused downloads can be reordered".to_string());
+        }
+        let priority = download.priority;
+        
+        let siblings: Vec<String> = self.ordered_ids(is_reorderable).into_iter()
+            .filter(|other| self.downloads[other].priority == priority)
+            .collect();
+        let index = siblings.iter().position(|other| other == id).unwrap_or(0);
+        let neighbour = if up {
+            index.checked_sub(1).and_then(|i| siblings.get(i))
+        } else {
+            siblings.get(index + 1)
+        };
+        let Some(neighbour) = neighbour.cloned() else {
+            return Ok(()); // Already at that end of its priority
+        };
+        
+        let own_position = self.downloads[id].queue_position;
+        let neighbour_position = self.downloads[&neighbour].queue_position;
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.queue_position = neighbour_position;
+        }
+        if let Some(download) = self.downloads.get_mut(&neighbour) {
+            download.queue_position = own_position;
+        }
+        // Equal positions (same-age legacy entries) would leave the order unchanged
+        if own_position == neighbour_position {
+            if let Some(download) = self.downloads.get_mut(id) {
+                download.queue_position += if up { -1 } else { 1 };
+            }
+        }
+        
+        self.finish_queue_change(id, if up { "moved up" } else { "moved down" });
+        Ok(())
+    }
+    
+    /// Put a waiting download ahead of everything else in the queue
+    fn move_to_front(&mut self, id: &str) -> Result<(), String> {
+        let download = self.downloads.get(id).ok_or_else(|| "Download not found".to_string())?;
+        if !is_reorderable(&download.status) {
+            return Err("Only queued or paused downloads can be reordered".to_string());
+        }
+        
+        let waiting: Vec<&DownloadItem> = self.downloads.values()
+            .filter(|d| is_reorderable(&d.status))
+            .collect();
+        let top_priority = waiting.iter().map(|d| d.priority).max().unwrap_or_default();
+        let front_position = waiting.iter().map(|d| d.queue_position).min().unwrap_or(0) - 1;
+        
+        if let Some(download) = self.downloads.get_mut(id) {
+            download.priority = download.priority.max(top_priority);
+            download.queue_position = front_position;
+        }
+        self.finish_queue_change(id, "moved to the front");
+        Ok(())
+    }
+    
+    fn set_priority(&mut self, id: &str, priority: DownloadPriority) -> Result<(), String> {
+        let download = self.downloads.get_mut(id).ok_or_else(|| "Download not found".to_string())?;
+        if download.priority == priority {
+            return Ok(());
+        }
+        download.priority = priority;
+        
+        // A running download gets its new bandwidth share right away
+        if let Some(transfer) = self.transfers.get(id).and_then(Weak::upgrade) {
+            transfer.set_weight(priority.bandwidth_weight());
+        }
+        self.finish_queue_change(id, &format!("priority set to {:?}", priority).to_lowercase());
+        Ok(())
+    }
+    
+    /// Announce and persist a change to the queue order
+    fn finish_queue_change(&mut self, id: &str, change: &str) {
+        log::info!("[Rust] Queue changed for ID {}: {}", id, change);
+        self.emit_queue_event("reordered");
+        if self.auto_save_enabled {
+            if let Err(e) = self.save_state() {
+                log::error!("Failed to save state after reordering: {}", e);
+            }
+        }
+    }
+    
+    fn start_next_queued_download(&mut self) {
+        // Check if we have capacity for more downloads
+        let mut downloading_count = self.count_downloading_only();
+        let max_downloads = self.max_downloads();
+        if downloading_count >= max_downloads {
+            return;
+        }
+        
+        // Queue order; downloads held for disk space get another chance alongside the queue
+        let candidates = self.ordered_ids(|status| matches!(status, DownloadStatus::Queued | DownloadStatus::InsufficientSpace));
+        
+        // Outside the scheduled download windows the queue stays put
+        if let Some(reason) = self.schedule_wait_reason() {
+            for download_id in candidates {
+                self.note_schedule_wait(&download_id, &reason);
+            }
+            return;
+        }
+        
+        // Start multiple downloads to fill available slots
+        for download_id in candidates {
+            if downloading_count >= max_downloads {
+                break;
+            }
+            
+            let (url, file_path, status, needed) = match self.downloads.get(&download_id) {
+                Some(download) => (
+                    download.url.clone(),
+                    download.file_path.clone(),
+                    download.status.clone(),
+                    self.remaining_download_bytes(download),
+                ),
+                None => continue,
+            };
+            
+            // Only active downloads count against the space; later queued ones wait their turn
+            if let Err(reason) = self.check_disk_space(&file_path, needed, Some(&download_id), false) {
+                self.hold_for_disk_space(&download_id, reason);
+                continue;
+            }
+            
+            // A download queued while running keeps its signalled token until its old task exits
+            if self.cancellation_tokens.get(&download_id).is_some_and(|token| token.load(Ordering::Relaxed)) {
+                self.cancellation_tokens.remove(&download_id);
+            }
+            
+            if !self.claim_download(&download_id, &status) {
+                log::warn!("[Rust] Queued download already active for ID: {}", download_id);
+                continue; // Try next download
+            }
+            if let Some(engine) = self.engine() {
+                engine.spawn_claimed_download(download_id.clone(), url, file_path);
+            }
+            log::info!("[Rust] Started queued download with ID: {}", download_id);
+            
+            downloading_count += 1; // Update our local count
+        }
+    }
+    
+    fn enforce_download_limit(&mut self) {
+        // Nothing may run outside the scheduled download windows
+        let schedule_wait = self.schedule_wait_reason();
+        let max_downloads = if schedule_wait.is_some() {
+            0
+        } else {
+            self.max_downloads()
+        };
+        let sorted_downloads = self.ordered_ids(|status| matches!(status, DownloadStatus::Downloading));
+        
+        if sorted_downloads.len() > max_downloads as usize {
+            // Queue the excess downloads, last in queue order first
+            let excess_count = sorted_downloads.len() - max_downloads as usize;
+            for download_id in sorted_downloads.iter().rev().take(excess_count) {
                 if let Some(download) = self.downloads.get(download_id) {
                     // Only queue downloads that are currently downloading (not paused by user)
                     if matches!(download.status, DownloadStatus::Downloading) {
@@ -843,29 +1794,772 @@ impl DownloadManager {
                         // Set status to queued
                         self.set_download_status_no_cleanup(download_id, DownloadStatus::Queued, None);
                         
-                        log::info!("[Rust] Download {} moved to queue due to reduced simultaneous download limit", download_id);
+                        if let Some(reason) = &schedule_wait {
+                            log::info!("[Rust] Download {} moved to queue because the download window closed", download_id);
+                            self.note_schedule_wait(download_id, reason);
+                        } else {
+                            log::info!("[Rust] Download {} moved to queue due to reduced simultaneous download limit", download_id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Record once per download that it is waiting for a download window
+    fn note_schedule_wait(&mut self, download_id: &str, reason: &str) {
+        if !self.schedule_waiting.insert(download_id.to_string()) {
+            return;
+        }
+        let file_name = self.downloads.get(download_id).map(|d| d.file_name.clone());
+        self.add_activity(
+            ActivityType::DownloadScheduled,
+            file_name,
+            Some(download_id.to_string()),
+            Some("queued".to_string()),
+            Some(reason.to_string())
+        );
+    }
+}
+
+impl DownloadEngine {
+    /// Engine on the real network and clock and the launcher's settings and speed limit, keeping its state in `data_dir`
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self::with_services(
+            data_dir,
+            Arc::new(ReqwestTransport::new()),
+            Arc::new(SystemClock),
+            SETTINGS.clone(),
+            crate::bandwidth::BANDWIDTH_SHAPER.clone(),
+        )
+    }
+    
+    /// Where the launcher keeps download state, falling back to the working directory
+    pub fn default_data_dir() -> PathBuf {
+        PathBuf::from(get_yuukips_data_path().unwrap_or_else(|_| ".".to_string()))
+    }
+    
+    /// Engine on the given transport and clock, such as a local stand-in server and a manual clock,
+    /// with default settings and a speed limiter of its own
+    pub fn with_transport(data_dir: PathBuf, transport: Arc<dyn DownloadTransport>, clock: Arc<dyn Clock>) -> Self {
+        Self::with_services(
+            data_dir,
+            transport,
+            clock.clone(),
+            Arc::new(Mutex::new(AppSettings::default())),
+            Arc::new(BandwidthShaper::with_clock(clock.clone())),
+        )
+    }
+    
+    /// Engine on the given transport, clock, settings and speed limiter. History, completion
+    /// pipelines and install jobs are loaded from `data_dir` next to the download state.
+    pub fn with_services(
+        data_dir: PathBuf,
+        transport: Arc<dyn DownloadTransport>,
+        clock: Arc<dyn Clock>,
+        settings: SharedSettings,
+        shaper: Arc<BandwidthShaper>,
+    ) -> Self {
+        let engine = Self::assemble(data_dir, transport, clock, settings, shaper);
+        match engine.lock() {
+            Ok(mut manager) => manager.restore(),
+            Err(e) => log::error!("{}", e),
+        }
+        engine
+    }
+    
+    /// Engine showing the downloads as the launcher last persisted them, for another process to look at.
+    /// Nothing is resumed and nothing is written back: the state file and journal stay the launcher's.
+    pub fn read_only(data_dir: PathBuf) -> Self {
+        let engine = Self::assemble(
+            data_dir,
+            Arc::new(ReqwestTransport::new()),
+            Arc::new(SystemClock),
+            SETTINGS.clone(),
+            crate::bandwidth::BANDWIDTH_SHAPER.clone(),
+        );
+        match engine.lock() {
+            Ok(mut manager) => manager.load_read_only(),
+            Err(e) => log::error!("{}", e),
+        }
+        engine
+    }
+    
+    /// Take the lock that keeps a second process from running downloads on the state in `data_dir`
+    pub fn lock_data_dir(data_dir: &Path) -> Result<crate::utils::FileLock, String> {
+        crate::utils::FileLock::try_acquire(&data_dir.join("download_state.lock"))
+    }
+    
+    fn assemble(
+        data_dir: PathBuf,
+        transport: Arc<dyn DownloadTransport>,
+        clock: Arc<dyn Clock>,
+        settings: SharedSettings,
+        shaper: Arc<BandwidthShaper>,
+    ) -> Self {
+        let mirror_health = MirrorHealth::new(clock.clone());
+        let inner = Arc::new_cyclic(|engine| EngineInner {
+            history: HistoryStore::load(&data_dir),
+            pipelines: PipelineStore::load(&data_dir),
+            install_jobs: InstallJobStore::load(&data_dir),
+            manager: Mutex::new(DownloadManager::new(data_dir, clock.clone(), settings.clone(), engine.clone())),
+            active_requests: Mutex::new(HashSet::new()),
+            transport,
+            clock,
+            settings,
+            shaper,
+            mirror_health,
+        });
+        Self { inner }
+    }
+    
+    fn lock(&self) -> Result<MutexGuard<'_, DownloadManager>, String> {
+        self.inner.manager.lock()
+            .map_err(|e| format!("Failed to lock download manager: {}", e))
+    }
+    
+    pub fn transport(&self) -> Arc<dyn DownloadTransport> {
+        self.inner.transport.clone()
+    }
+    
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.inner.clock.clone()
+    }
+    
+    /// Settings this engine runs on: queue limit, download windows, segments and header rules
+    pub fn settings(&self) -> &Mutex<AppSettings> {
+        &self.inner.settings
+    }
+    
+    /// Speed limiter the engine's transfers draw from
+    pub fn bandwidth_shaper(&self) -> &Arc<BandwidthShaper> {
+        &self.inner.shaper
+    }
+    
+    /// Apply the speed limit from `settings`, or the speed window open now on the engine's clock
+    pub fn apply_speed_limit(&self, settings: &AppSettings) {
+        self.inner.shaper.apply_settings(settings, &self.inner.clock.local_now());
+    }
+    
+    pub fn mirror_health(&self) -> &MirrorHealth {
+        &self.inner.mirror_health
+    }
+    
+    pub fn history(&self) -> &HistoryStore {
+        &self.inner.history
+    }
+    
+    pub fn pipelines(&self) -> &PipelineStore {
+        &self.inner.pipelines
+    }
+    
+    pub fn install_jobs(&self) -> &InstallJobStore {
+        &self.inner.install_jobs
+    }
+    
+    /// Connections per large download, from the settings
+    fn download_segments(&self) -> u32 {
+        self.settings().lock().map(|settings| settings.download_segments).unwrap_or(1)
+    }
+    
+    /// Push download progress, status and queue events to the frontend; called once from setup
+    pub fn set_event_handle(&self, app_handle: tauri::AppHandle) {
+        if let Ok(mut manager) = self.lock() {
+            manager.event_handle = Some(app_handle);
+        }
+    }
+    
+    /// Handle the frontend events go through, once setup has provided one
+    pub fn event_handle(&self) -> Option<tauri::AppHandle> {
+        self.lock().ok().and_then(|manager| manager.event_handle.clone())
+    }
+    
+    pub(crate) fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Ok(manager) = self.lock() {
+            manager.emit_download_event(event, payload);
+        }
+    }
+    
+    /// Add an entry to the activity log on behalf of another module
+    pub(crate) fn record_activity(&self, action_type: ActivityType, file_name: Option<String>, identifier: Option<String>, status: Option<String>, details: Option<String>) {
+        let Ok(mut manager) = self.lock() else {
+            return;
+        };
+        manager.add_activity(action_type, file_name, identifier, status, details);
+        if let Err(e) = manager.auto_save_state() {
+            log::error!("Failed to auto-save state: {}", e);
+        }
+    }
+    
+    pub fn save_state(&self) -> Result<(), String> {
+        self.lock()?.save_state()
+            .map_err(|e| format!("Failed to save state: {}", e))
+    }
+    
+    /// Downloads that have not completed or been cancelled, in the order they run
+    pub fn unfinished_downloads(&self) -> Result<Vec<DownloadItem>, String> {
+        let manager = self.lock()?;
+        Ok(manager.ordered_ids(|status| !matches!(status, DownloadStatus::Completed | DownloadStatus::Cancelled))
+            .iter()
+            .filter_map(|id| manager.downloads.get(id).cloned())
+            .collect())
+    }
+    
+    /// Every download in the list, in no particular order
+    pub fn downloads(&self) -> Result<Vec<DownloadItem>, String> {
+        Ok(self.lock()?.downloads.values().cloned().collect())
+    }
+    
+    pub fn download(&self, download_id: &str) -> Result<Option<DownloadItem>, String> {
+        Ok(self.lock()?.downloads.get(download_id).cloned())
+    }
+    
+    /// Trigger queue management when download limit settings change
+    /// This function is called from both the download manager and settings module
+    pub fn trigger_queue_management_on_settings_change(&self, max_downloads: u32, old_limit: u32) -> Result<(), String> {
+        let mut manager = self.lock()?;
+
+        if max_downloads < old_limit {
+            // If we reduced the limit, enforce the new limit by queuing excess downloads
+            manager.enforce_download_limit();
+        } else if max_downloads > old_limit {
+            // If we increased the limit, try to start queued downloads
+            manager.start_next_queued_download();
+        }
+
+        Ok(())
+    }
+    
+    /// Pause or resume the queue when a scheduled download window closes or opens.
+    /// `announce` logs the change as an activity; `next_change` is when the window flips again.
+    pub fn apply_download_window(&self, allowed: bool, announce: bool, next_change: Option<String>) -> Result<(), String> {
+        let mut manager = self.lock()?;
+
+        if announce {
+            let details = match (allowed, next_change) {
+                (true, Some(next)) => format!("Download window opened, queued downloads may run until {}", next),
+                (true, None) => "Download window opened, queued downloads may run".to_string(),
+                (false, Some(next)) => format!("Download window closed, downloads will wait until {}", next),
+                (false, None) => "Download window closed, downloads will wait for the next window".to_string(),
+            };
+            log::info!("[Rust] {}", details);
+            manager.add_activity(
+                ActivityType::ScheduleChanged,
+                None,
+                None,
+                Some(if allowed { "open" } else { "closed" }.to_string()),
+                Some(details)
+            );
+        }
+
+        if allowed {
+            manager.schedule_waiting.clear();
+            manager.start_next_queued_download();
+        } else {
+            manager.enforce_download_limit();
+            manager.start_next_queued_download();
+        }
+
+        Ok(())
+    }
+    
+    /// Remember which mirror a download moved to and tell the user why
+    pub fn record_mirror_switch(&self, download_id: &str, from_url: &str, to_url: &str, reason: &str) {
+        let Ok(mut manager) = self.lock() else {
+            return;
+        };
+        let Some(download) = manager.downloads.get_mut(download_id) else {
+            return;
+        };
+        download.active_url = Some(to_url.to_string());
+        let file_name = download.file_name.clone();
+
+        manager.add_activity(
+            ActivityType::MirrorSwitched,
+            Some(file_name),
+            Some(download_id.to_string()),
+            Some("downloading".to_string()),
+            Some(format!("Switched from {} to {}: {}", from_url, to_url, reason))
+        );
+
+        if let Err(e) = manager.auto_save_state() {
+            log::error!("Failed to auto-save state: {}", e);
+        }
+    }
+    
+    /// Headers for a request of this download to `url`, from the matching host rules and the download itself
+    pub(crate) fn request_headers(&self, download_id: &str, url: &str) -> Vec<(String, String)> {
+        // Mirrors on other hosts only get what the host rules give them
+        let options = self.lock().ok().and_then(|manager| {
+            manager.downloads.get(download_id)
+                .filter(|d| same_host(&d.url, url))
+                .map(|d| (d.headers.clone(), d.auth.clone(), d.user_agent.clone()))
+        });
+        let (headers, auth, user_agent) = options.unwrap_or_default();
+        let rules = self.settings().lock()
+            .map(|settings| settings.download_header_rules.clone())
+            .unwrap_or_default();
+        let resolved = resolve_request_headers(url, &rules, &headers, &auth, &user_agent);
+        if !resolved.is_empty() {
+            log::debug!("[Rust] Request headers for {}: {}", download_id, redact_headers(&resolved));
+        }
+        resolved
+    }
+    
+    /// Register a download with the bandwidth shaper, weighted by its priority.
+    /// The manager keeps a weak reference so a priority change can reweight the running transfer.
+    fn register_transfer(&self, download_id: &str) -> Arc<TransferHandle> {
+        let mut manager = self.lock();
+        let weight = manager.as_ref().ok()
+            .and_then(|manager| manager.downloads.get(download_id).map(|d| d.priority))
+            .unwrap_or_default()
+            .bandwidth_weight();
+        let transfer = Arc::new(self.bandwidth_shaper().register(download_id, weight));
+        if let Ok(manager) = manager.as_mut() {
+            manager.transfers.insert(download_id.to_string(), Arc::downgrade(&transfer));
+        }
+        transfer
+    }
+    
+    /// Hash a finished download and complete it, or fetch it again if the hash is wrong
+    fn spawn_checksum_verification(&self, download_id: String) {
+        let engine = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let target = engine.lock().ok().and_then(|manager| {
+                let download = manager.downloads.get(&download_id)?;
+                Some((download.file_path.clone(), download.expected_checksum()?))
+            });
+            let Some((file_path, (algorithm, expected))) = target else {
+                return;
+            };
+
+            log::info!("[Rust] Verifying {} of {} for ID: {}", algorithm, file_path, download_id);
+            let result = calculate_checksum(algorithm, Path::new(&file_path)).await;
+
+            let Ok(mut manager) = engine.lock() else {
+                return;
+            };
+            // Cancelled, removed or restarted while hashing
+            if manager.downloads.get(&download_id).map(|d| d.status.clone()) != Some(DownloadStatus::Verifying) {
+                log::info!("[Rust] Dropping checksum result for ID {}, download is no longer verifying", download_id);
+                return;
+            }
+
+            match result {
+                Ok(actual) if actual == expected => {
+                    log::info!("[Rust] {} verified for ID: {}", algorithm, download_id);
+                    manager.set_download_status(&download_id, DownloadStatus::Completed, None);
+                }
+                Ok(actual) => manager.retry_after_checksum_mismatch(&download_id, algorithm, &expected, &actual),
+                Err(e) => {
+                    log::error!("[Rust] Failed to verify {} for ID {}: {}", algorithm, download_id, e);
+                    manager.set_download_status(&download_id, DownloadStatus::Error, Some(format!("Failed to verify checksum: {}", e)));
+                }
+            }
+        });
+    }
+    
+    /// Add a download to the manager and spawn it unless it has to wait in the queue
+    pub async fn start_download(
+        &self,
+        url: String,
+        file_path: String,
+        file_name: Option<String>,
+        options: DownloadOptions,
+    ) -> Result<String, String> {
+        log::info!("[Rust] Starting new download: url={}, file_path={}, file_name={:?}", url, file_path, file_name);
+
+        let mut options = options;
+        options.expected_md5 = normalize_checksum(options.expected_md5, "MD5", 32)?;
+        options.expected_sha256 = normalize_checksum(options.expected_sha256, "SHA-256", 64)?;
+        crate::headers::validate_request_options(&options.headers, &options.auth, &options.user_agent)?;
+        if !options.headers.is_empty() || options.auth.is_some() || options.user_agent.is_some() {
+            log::info!("[Rust] Download request options: headers={:?}, auth={:?}, user_agent={:?}", options.headers, options.auth, options.user_agent);
+        }
+
+        let (download_id, should_start_immediately) = {
+            let mut manager = self.lock()
+                .map_err(|error_msg| {
+                    log::error!("[Rust] Error: {}", error_msg);
+                    error_msg
+                })?;
+
+            // Check if this is a duplicate before adding
+            let existing_download = manager.downloads.iter().find(|(_, download)| {
+                download.url == url && download.file_path == file_path &&
+                matches!(download.status, DownloadStatus::Downloading | DownloadStatus::Queued | DownloadStatus::InsufficientSpace)
+            });
+
+            if let Some((existing_id, _)) = existing_download {
+                log::info!("[Rust] Found existing active download with ID: {}, not spawning new task", existing_id);
+                return Ok(existing_id.clone());
+            }
+
+            let id = manager.add_download(url.clone(), file_path.clone(), file_name, options);
+            log::info!("[Rust] Download added to manager with ID: {}", id);
+
+            // Check if the download should start immediately or is queued
+            let should_start = if let Some(download) = manager.downloads.get(&id) {
+                matches!(download.status, DownloadStatus::Downloading)
+            } else {
+                false
+            };
+
+            (id, should_start)
+        };
+
+        // Start the download in a background task only if not queued
+        if should_start_immediately {
+            let download_id_clone = download_id.clone();
+            let url_clone = url.clone();
+            let file_path_clone = file_path.clone();
+
+            log::info!("[Rust] Spawning background task for download ID: {}", download_id);
+            match self.spawn_perform_download_if_not_active(download_id_clone.clone(), url_clone, file_path_clone, DownloadStatus::Downloading) {
+                Ok(spawned) => {
+                    if !spawned {
+                        log::info!("[Rust] Download already active for ID: {}", download_id_clone);
+                    }
+                },
+                Err(e) => {
+                    log::error!("[Rust] Failed to spawn download for ID {}: {}", download_id_clone, e);
+                    // Update status to error
+                    if let Ok(mut manager) = self.lock() {
+                        manager.set_download_status(&download_id_clone, DownloadStatus::Error, Some(e));
+                    }
+                }
+            }
+            log::info!("[Rust] Background task spawned successfully for download ID: {}, task handle created", download_id);
+        } else {
+            log::info!("[Rust] Download queued with ID: {} (max simultaneous downloads reached)", download_id);
+        }
+
+        log::info!("[Rust] Download initiation completed, returning ID: {}", download_id);
+        Ok(download_id)
+    }
+    
+    /// Pause a download
+    pub fn pause_download(&self, download_id: String) -> Result<(), String> {
+        let mut manager = self.lock()?;
+
+        if let Some(download) = manager.downloads.get(&download_id) {
+            if download.status != DownloadStatus::Downloading {
+                return Err("Download is not active".to_string());
+            }
+
+            // Signal cancellation to stop current download
+            if let Some(token) = manager.cancellation_tokens.get(&download_id) {
+                token.store(true, Ordering::Relaxed);
+            }
+
+            // Set user_paused to true for manual pause
+            if let Some(download) = manager.downloads.get_mut(&download_id) {
+                download.user_paused = true;
+            }
+
+            // Set status to paused without cleaning up cancellation token
+            manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Paused, None);
+
+            // Try to start next queued download since we freed up a slot
+            manager.start_next_queued_download();
+        }
+
+        Ok(())
+    }
+    
+    /// Resume a paused download, or retry one held for insufficient disk space
+    pub async fn resume_download(&self, download_id: String) -> Result<(), String> {
+        let download_info = {
+            let mut manager = self.lock()?;
+
+            if let Some(download) = manager.downloads.get(&download_id) {
+                if matches!(download.status, DownloadStatus::Paused | DownloadStatus::InsufficientSpace) {
+                    let url = download.url.clone();
+                    let file_path = download.file_path.clone();
+                    let previous_status = download.status.clone();
+                    let needed = manager.remaining_download_bytes(download);
+
+                    // A held download only leaves the hold once it fits next to the active ones
+                    if previous_status == DownloadStatus::InsufficientSpace {
+                        if let Err(reason) = manager.check_disk_space(&file_path, needed, Some(&download_id), false) {
+                            manager.hold_for_disk_space(&download_id, reason.clone());
+                            return Err(reason);
+                        }
+                    }
+
+                    // Outside the download windows a resumed download joins the queue
+                    if let Some(reason) = manager.schedule_wait_reason() {
+                        manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Queued, None);
+                        manager.note_schedule_wait(&download_id, &reason);
+                        return Ok(());
+                    }
+
+                    // Check if we have capacity to resume this download
+                    let downloading_count = manager.count_downloading_only();
+                    let max_downloads = manager.max_downloads();
+
+                    if downloading_count >= max_downloads {
+                        // No capacity, set to queued instead
+                        manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Queued, None);
+                        return Ok(());
+                    }
+
+                    // Reset user_paused flag when manually resuming
+                    if let Some(download_mut) = manager.downloads.get_mut(&download_id) {
+                        download_mut.user_paused = false;
+                    }
+
+                    // Remove old cancellation token (status will be set atomically by spawn function)
+                    manager.cancellation_tokens.remove(&download_id);
+
+                    Some((url, file_path, previous_status))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
+        // If we have download info, perform the download outside the lock
+        if let Some((url, file_path, previous_status)) = download_info {
+            // Use centralized concurrency control (expects the status it was resumed from)
+            match self.spawn_perform_download_if_not_active(download_id.clone(), url, file_path, previous_status) {
+                Ok(spawned) => {
+                    if !spawned {
+                        log::info!("[Rust] Download already active for resume ID: {}", download_id);
+                    }
+                },
+                Err(e) => {
+                    log::error!("[Rust] Failed to spawn resume download for ID {}: {}", download_id, e);
+                    // Update status to error
+                    if let Ok(mut manager) = self.lock() {
+                        manager.set_download_status(&download_id, DownloadStatus::Error, Some(e));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+    
+    /// Cancel a download
+    pub fn cancel_download(&self, download_id: String) -> Result<(), String> {
+        let mut manager = self.lock()?;
+
+        // Check if this was an active download before cancelling
+        let was_downloading = manager.downloads.get(&download_id)
+            .map(|d| d.status == DownloadStatus::Downloading)
+            .unwrap_or(false);
+
+        // Signal cancellation
+        if let Some(token) = manager.cancellation_tokens.get(&download_id) {
+            token.store(true, Ordering::Relaxed);
+        }
+
+        manager.set_download_status(&download_id, DownloadStatus::Cancelled, None);
+
+        // If we cancelled an active download, try to start next queued download
+        if was_downloading {
+            manager.start_next_queued_download();
+        }
+
+        Ok(())
+    }
+    
+    /// Cancel a download and delete the partially downloaded file
+    pub fn cancel_and_delete_download(&self, download_id: String) -> Result<(), String> {
+        let file_path = {
+            let mut manager = self.lock()?;
+
+            // Signal cancellation
+            if let Some(token) = manager.cancellation_tokens.get(&download_id) {
+                token.store(true, Ordering::Relaxed);
+            }
+
+            // Get file path and file name before removing from downloads
+            let (file_path, file_name) = manager.downloads.get(&download_id)
+                .map(|download| (download.file_path.clone(), download.file_name.clone()))
+                .unwrap_or_else(|| (String::new(), String::new()));
+
+            // Add activity for deletion before removing
+            if !file_name.is_empty() {
+                manager.add_activity(
+                    ActivityType::DownloadCancelled,
+                    Some(file_name),
+                    Some(download_id.clone()),
+                    Some("deleted".to_string()),
+                    Some("Download cancelled and file deleted".to_string())
+                );
+            }
+
+            // Remove from downloads and cancellation tokens
+             manager.downloads.remove(&download_id);
+             manager.cancellation_tokens.remove(&download_id);
+             manager.partial_downloads.remove(&download_id);
+             manager.last_progress_event.remove(&download_id);
+             manager.emit_queue_event("removed");
+
+             file_path
+        };
+
+        // Delete the file if it exists
+        if !file_path.is_empty() {
+            let path = Path::new(&file_path);
+            if path.exists() {
+                match fs::remove_file(path) {
+                    Ok(_) => log::info!("Successfully deleted file: {}", file_path),
+            Err(e) => log::error!("Failed to delete file {}: {}", file_path, e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+    
+    /// Restart a failed download
+    pub async fn restart_download(&self, download_id: String) -> Result<(), String> {
+        let download_info = {
+            let mut manager = self.lock()?;
+
+            if let Some(download) = manager.downloads.get(&download_id) {
+                if matches!(download.status, DownloadStatus::Error | DownloadStatus::Cancelled | DownloadStatus::Completed) {
+                    let total_size = download.total_size;
+                    let url = download.url.clone();
+                    let file_path = download.file_path.clone();
+                    let failed = download.status == DownloadStatus::Error;
+
+                    // Clean up any existing cancellation token (status will be set atomically by spawn function)
+                    manager.cancellation_tokens.remove(&download_id);
+
+                    // A failed segmented download continues from its synced segments; anything else begins from a fresh file
+                    let resumed_size = manager.partial_downloads.get(&download_id)
+                        .filter(|partial| failed && !partial.segments.is_empty())
+                        .map(|partial| partial.segments.iter().map(|segment| segment.downloaded).sum());
+                    if resumed_size.is_none() {
+                        manager.partial_downloads.remove(&download_id);
+                    }
+
+                    // Reset progress
+                    manager.update_download_progress(&download_id, resumed_size.unwrap_or(0), total_size, 0);
+                    if let Some(download) = manager.downloads.get_mut(&download_id) {
+                        download.verify_attempts = 0;
+                    }
+
+                    Some((url, file_path))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
+        // If we have download info, perform the download outside the lock
+        if let Some((url, file_path)) = download_info {
+            // Use centralized concurrency control (expects Error/Cancelled/Completed status)
+            match self.spawn_perform_download_if_not_active(download_id.clone(), url, file_path, DownloadStatus::Error) {
+                Ok(spawned) => {
+                    if !spawned {
+                        log::info!("[Rust] Download already active for restart ID: {}", download_id);
+                    }
+                },
+                Err(e) => {
+                    log::error!("[Rust] Failed to spawn restart download for ID {}: {}", download_id, e);
+                    // Update status to error
+                    if let Ok(mut manager) = self.lock() {
+                        manager.set_download_status(&download_id, DownloadStatus::Error, Some(e));
                     }
                 }
             }
         }
+
+        Ok(())
+    }
+    
+    /// Live downloads and history entries matching `filter`; the live entry wins when both have the same ID
+    fn find_downloads(&self, filter: &HistoryFilter) -> Result<HistoryPage, String> {
+        let mut items: Vec<DownloadItem> = {
+            let manager = self.lock()?;
+            manager.downloads.values().filter(|download| filter.matches(download)).cloned().collect()
+        };
+        let live_ids: HashSet<String> = items.iter().map(|download| download.id.clone()).collect();
+        items.extend(
+            self.inner.history.find(filter)?
+                .into_iter()
+                .filter(|entry| !live_ids.contains(&entry.id))
+        );
+        Ok(filter.paginate(items))
+    }
+    
+    /// Get all downloads that belong to the given group
+    pub fn get_group_downloads(&self, group_id: &str) -> Result<Vec<DownloadItem>, String> {
+        let manager = self.lock()?;
+
+        Ok(manager.downloads.values()
+            .filter(|d| d.group_id.as_deref() == Some(group_id))
+            .cloned()
+            .collect())
+    }
+    
+    /// Free space at `path` minus what running and queued downloads on that volume still need
+    pub fn get_unreserved_disk_space(&self, path: &str) -> Result<u64, String> {
+        let available = crate::system::get_free_disk_space(Path::new(path))?;
+        let manager = self.lock()?;
+        Ok(available.saturating_sub(manager.reserved_disk_space(path, None, true)))
+    }
+    
+    /// Claim a download and spawn its task, unless it is already running or no longer in `expected_status`
+    fn spawn_perform_download_if_not_active(&self, download_id: String, url: String, file_path: String, expected_status: DownloadStatus) -> Result<bool, String> {
+        let mut manager = self.lock()
+            .map_err(|error_msg| {
+                log::error!("[Rust] Error checking download status: {}", error_msg);
+                error_msg
+            })?;
+
+        if !manager.claim_download(&download_id, &expected_status) {
+            return Ok(false);
+        }
+
+        // Release the lock before spawning the task
+        drop(manager);
+
+        self.spawn_claimed_download(download_id, url, file_path);
+        Ok(true)
+    }
+    
+    /// Spawn the perform_download task for a download already claimed with `claim_download`
+    fn spawn_claimed_download(&self, download_id: String, url: String, file_path: String) {
+        let engine = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let token = engine.lock().ok()
+                .and_then(|manager| manager.cancellation_tokens.get(&download_id).cloned());
+            if let Err(e) = perform_download_internal(engine.clone(), download_id.clone(), url, file_path).await {
+                // A stopped task (paused, queued or cancelled) already has the status it should keep
+                if token.is_some_and(|token| token.load(Ordering::Relaxed)) {
+                    log::info!("[Rust] Download task stopped for ID {}: {}", download_id, e);
+                    return;
+                }
+                log::error!("[Rust] Download failed for ID {}: {}", download_id, e);
+                // Update status to error
+                if let Ok(mut manager) = engine.lock() {
+                    manager.set_download_status(&download_id, DownloadStatus::Error, Some(e));
+                }
+            }
+        });
     }
 }
 
 /// Save current download state manually
 #[command]
-pub fn save_download_state() -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    manager.save_state()
-        .map_err(|e| format!("Failed to save state: {}", e))
+pub fn save_download_state(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    engine.save_state()
 }
 
 /// Load download state manually
 #[command]
-pub fn load_download_state() -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn load_download_state(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.load_state()
         .map_err(|e| format!("Failed to load state: {}", e))
@@ -873,9 +2567,8 @@ pub fn load_download_state() -> Result<(), String> {
 
 /// Resume all interrupted downloads
 #[command]
-pub fn resume_interrupted_downloads() -> Result<Vec<String>, String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn resume_interrupted_downloads(engine: State<'_, DownloadEngine>) -> Result<Vec<String>, String> {
+    let mut manager = engine.lock()?;
     
     manager.resume_interrupted_downloads()
         .map_err(|e| format!("Failed to resume interrupted downloads: {}", e))
@@ -883,56 +2576,101 @@ pub fn resume_interrupted_downloads() -> Result<Vec<String>, String> {
 
 /// Get current state version
 #[command]
-pub fn get_state_version() -> Result<u32, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn get_state_version(engine: State<'_, DownloadEngine>) -> Result<u32, String> {
+    let manager = engine.lock()?;
     
     Ok(manager.state_version)
 }
 
 /// Enable or disable auto-save
 #[command]
-pub fn set_auto_save_enabled(enabled: bool) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn set_auto_save_enabled(engine: State<'_, DownloadEngine>, enabled: bool) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.auto_save_enabled = enabled;
     Ok(())
 }
 
-/// Get partial download information
+/// Get partial download information
+#[command]
+pub fn get_partial_downloads(engine: State<'_, DownloadEngine>) -> Result<std::collections::HashMap<String, PartialDownloadInfo>, String> {
+    let manager = engine.lock()?;
+    Ok(manager.partial_downloads.clone())
+}
+
+#[command]
+pub fn get_download_resume_support(engine: State<'_, DownloadEngine>, download_id: String) -> Result<bool, String> {
+    let manager = engine.lock()?;
+    
+    // Fallback to DownloadItem if PartialDownloadInfo is not available
+    if let Some(download) = manager.downloads.get(&download_id) {
+        return Ok(download.resume_supported);
+    }
+    
+    Err(format!("Download with ID {} not found", download_id))
+}
+
+/// Get waiting downloads (queued, held for space and paused) in the order they will start
+#[command]
+pub fn get_download_queue(engine: State<'_, DownloadEngine>) -> Result<Vec<DownloadItem>, String> {
+    let manager = engine.lock()?;
+    Ok(manager.ordered_ids(is_reorderable).iter()
+        .filter_map(|id| manager.downloads.get(id).cloned())
+        .collect())
+}
+
+/// Move a waiting download one place towards the front of its priority
+#[command]
+pub fn move_download_up(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    manager.move_in_queue(&download_id, true)
+}
+
+/// Move a waiting download one place towards the back of its priority
+#[command]
+pub fn move_download_down(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    manager.move_in_queue(&download_id, false)
+}
+
+/// Make a waiting download the next one to start
+#[command]
+pub fn move_download_to_front(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    manager.move_to_front(&download_id)
+}
+
 #[command]
-pub fn get_partial_downloads() -> Result<std::collections::HashMap<String, PartialDownloadInfo>, String> {
-    let manager = DOWNLOAD_MANAGER.lock().map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    Ok(manager.partial_downloads.clone())
+pub fn set_download_priority(engine: State<'_, DownloadEngine>, download_id: String, priority: DownloadPriority) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    manager.set_priority(&download_id, priority)
 }
 
+/// Pin a download ahead of every unpinned one, or return it to normal priority
 #[command]
-pub fn get_download_resume_support(download_id: String) -> Result<bool, String> {
-    let manager = DOWNLOAD_MANAGER.lock().map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    // Fallback to DownloadItem if PartialDownloadInfo is not available
-    if let Some(download) = manager.downloads.get(&download_id) {
-        return Ok(download.resume_supported);
-    }
-    
-    Err(format!("Download with ID {} not found", download_id))
+pub fn pin_download(engine: State<'_, DownloadEngine>, download_id: String, pinned: bool) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    let priority = if pinned { DownloadPriority::Pinned } else { DownloadPriority::Normal };
+    manager.set_priority(&download_id, priority)
 }
 
 /// Get current download speed limit in MB/s
 #[command]
-pub fn get_speed_limit() -> Result<f64, String> {
-    let settings = SETTINGS.lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
+pub fn get_speed_limit(engine: State<'_, DownloadEngine>) -> Result<f64, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
     Ok(settings.speed_limit_mbps)
 }
 
 /// Set download speed limit in MB/s (0 = unlimited)
 #[command]
-pub fn set_speed_limit(speed_limit_mbps: f64) -> Result<(), String> {
-    let mut settings = SETTINGS.lock().unwrap().clone();
+pub fn set_speed_limit(engine: State<'_, DownloadEngine>, speed_limit_mbps: f64) -> Result<(), String> {
+    let mut settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
     settings.speed_limit_mbps = speed_limit_mbps.max(0.0); // Ensure non-negative
     log::info!("[Rust] Speed limit set to {} MB/s", settings.speed_limit_mbps);
     
+    // Applies to running transfers immediately
+    engine.apply_speed_limit(&settings);
+    
     settings.save()
         .map_err(|e| format!("Failed to save settings after setting speed limit: {}", e))?;
     
@@ -940,17 +2678,19 @@ pub fn set_speed_limit(speed_limit_mbps: f64) -> Result<(), String> {
 }
 
 #[command]
-pub fn get_divide_speed_enabled() -> Result<bool, String> {
-    let settings = SETTINGS.lock().unwrap();
+pub fn get_divide_speed_enabled(engine: State<'_, DownloadEngine>) -> Result<bool, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
     Ok(settings.divide_speed_enabled)
 }
 
 #[command]
-pub fn set_divide_speed_enabled(enabled: bool) -> Result<(), String> {
-    let mut settings = SETTINGS.lock().unwrap().clone();
+pub fn set_divide_speed_enabled(engine: State<'_, DownloadEngine>, enabled: bool) -> Result<(), String> {
+    let mut settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
     settings.divide_speed_enabled = enabled;
     log::info!("[Rust] Divide speed setting set to {}", settings.divide_speed_enabled);
     
+    engine.apply_speed_limit(&settings);
+    
     settings.save()
         .map_err(|e| format!("Failed to save settings after setting divide speed: {}", e))?;
     
@@ -958,51 +2698,59 @@ pub fn set_divide_speed_enabled(enabled: bool) -> Result<(), String> {
 }
 
 #[command]
-pub fn get_max_simultaneous_downloads() -> Result<u32, String> {
-    let settings = SETTINGS.lock().unwrap();
+pub fn get_max_simultaneous_downloads(engine: State<'_, DownloadEngine>) -> Result<u32, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
     Ok(settings.max_simultaneous_downloads)
 }
 
 #[command]
-pub fn set_max_simultaneous_downloads(max_downloads: u32) -> Result<(), String> {
+pub fn set_max_simultaneous_downloads(engine: State<'_, DownloadEngine>, max_downloads: u32) -> Result<(), String> {
     // Validate the input (minimum 1, maximum 64 for reasonable limits)
     if max_downloads < 1 || max_downloads > 64 {
         return Err("Max simultaneous downloads must be between 1 and 64".to_string());
     }
     
-    let mut settings = SETTINGS.lock().unwrap().clone();
-    let old_limit = settings.max_simultaneous_downloads;
-    settings.max_simultaneous_downloads = max_downloads;
-    log::info!("[Rust] Max simultaneous downloads set to {}", settings.max_simultaneous_downloads);
-    
-    settings.save()
-        .map_err(|e| format!("Failed to save settings after setting max downloads: {}", e))?;
+    let old_limit = {
+        let mut settings = engine.settings().lock().map_err(|e| format!("Failed to lock settings: {}", e))?;
+        let old_limit = settings.max_simultaneous_downloads;
+        settings.max_simultaneous_downloads = max_downloads;
+        log::info!("[Rust] Max simultaneous downloads set to {}", settings.max_simultaneous_downloads);
+        
+        settings.save()
+            .map_err(|e| format!("Failed to save settings after setting max downloads: {}", e))?;
+        old_limit
+    };
     
-    trigger_queue_management_on_settings_change(max_downloads, old_limit)
+    engine.trigger_queue_management_on_settings_change(max_downloads, old_limit)
 }
 
-/// Trigger queue management when download limit settings change
-/// This function is called from both the download manager and settings module
-pub fn trigger_queue_management_on_settings_change(max_downloads: u32, old_limit: u32) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    if max_downloads < old_limit {
-        // If we reduced the limit, enforce the new limit by queuing excess downloads
-        manager.enforce_download_limit();
-    } else if max_downloads > old_limit {
-        // If we increased the limit, try to start queued downloads
-        manager.start_next_queued_download();
+/// Downloads that are waiting to run and can be moved around in the queue
+fn is_reorderable(status: &DownloadStatus) -> bool {
+    matches!(status, DownloadStatus::Queued | DownloadStatus::InsufficientSpace | DownloadStatus::Paused)
+}
+
+/// Lowercase an expected hash and check that it is `length` hex digits
+fn normalize_checksum(value: Option<String>, algorithm: &str, length: usize) -> Result<Option<String>, String> {
+    let Some(value) = value.map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    if value.len() != length || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid {} checksum: {}", algorithm, value));
+    }
+    Ok(Some(value))
+}
+
+pub(crate) async fn calculate_checksum(algorithm: &str, file_path: &Path) -> Result<String, String> {
+    match algorithm {
+        "SHA-256" => crate::utils::calculate_sha256_streamed(file_path).await,
+        _ => crate::utils::calculate_md5_streamed(file_path).await,
     }
-    
-    Ok(())
 }
 
 /// Get all activity entries
 #[command]
-pub fn get_activities() -> Result<Vec<ActivityEntry>, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn get_activities(engine: State<'_, DownloadEngine>) -> Result<Vec<ActivityEntry>, String> {
+    let manager = engine.lock()?;
     
     // Return activities in reverse chronological order (newest first)
     let mut activities = manager.activities.clone();
@@ -1012,9 +2760,8 @@ pub fn get_activities() -> Result<Vec<ActivityEntry>, String> {
 
 /// Clear all activity entries
 #[command]
-pub fn clear_activities() -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn clear_activities(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.clear_activities()
         .map_err(|e| format!("Failed to clear activities: {}", e))
@@ -1022,9 +2769,8 @@ pub fn clear_activities() -> Result<(), String> {
 
 /// Add a user interaction activity entry
 #[command]
-pub fn add_user_interaction_activity(action: String, details: Option<String>) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn add_user_interaction_activity(engine: State<'_, DownloadEngine>, action: String, details: Option<String>) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.add_activity(
         ActivityType::UserInteraction,
@@ -1040,318 +2786,66 @@ pub fn add_user_interaction_activity(action: String, details: Option<String>) ->
 /// Start a new download
 #[command]
 pub async fn start_download(
+    engine: State<'_, DownloadEngine>,
     url: String,
     file_path: String,
     file_name: Option<String>,
+    options: Option<DownloadOptions>,
 ) -> Result<String, String> {
-    log::info!("[Rust] Starting new download: url={}, file_path={}, file_name={:?}", url, file_path, file_name);
-    
-    let (download_id, should_start_immediately) = {
-        let mut manager = DOWNLOAD_MANAGER.lock()
-            .map_err(|e| {
-                let error_msg = format!("Failed to lock download manager: {}", e);
-                log::error!("[Rust] Error: {}", error_msg);
-                error_msg
-            })?;
-        
-        // Check if this is a duplicate before adding
-        let existing_download = manager.downloads.iter().find(|(_, download)| {
-            download.url == url && download.file_path == file_path &&
-            matches!(download.status, DownloadStatus::Downloading | DownloadStatus::Queued)
-        });
-        
-        if let Some((existing_id, _)) = existing_download {
-            log::info!("[Rust] Found existing active download with ID: {}, not spawning new task", existing_id);
-            return Ok(existing_id.clone());
-        }
-        
-        let id = manager.add_download(url.clone(), file_path.clone(), file_name);
-        log::info!("[Rust] Download added to manager with ID: {}", id);
-        
-        // Check if the download should start immediately or is queued
-        let should_start = if let Some(download) = manager.downloads.get(&id) {
-            matches!(download.status, DownloadStatus::Downloading)
-        } else {
-            false
-        };
-        
-        (id, should_start)
-    };
-
-    // Start the download in a background task only if not queued
-    if should_start_immediately {
-        let download_id_clone = download_id.clone();
-        let url_clone = url.clone();
-        let file_path_clone = file_path.clone();
-        
-        log::info!("[Rust] Spawning background task for download ID: {}", download_id);
-        match spawn_perform_download_if_not_active(download_id_clone.clone(), url_clone, file_path_clone, DownloadStatus::Downloading) {
-            Ok(spawned) => {
-                if !spawned {
-                    log::info!("[Rust] Download already active for ID: {}", download_id_clone);
-                }
-            },
-            Err(e) => {
-                log::error!("[Rust] Failed to spawn download for ID {}: {}", download_id_clone, e);
-                // Update status to error
-                if let Ok(mut manager) = DOWNLOAD_MANAGER.lock() {
-                    manager.set_download_status(&download_id_clone, DownloadStatus::Error, Some(e));
-                }
-            }
-        }
-        log::info!("[Rust] Background task spawned successfully for download ID: {}, task handle created", download_id);
-    } else {
-        log::info!("[Rust] Download queued with ID: {} (max simultaneous downloads reached)", download_id);
-    }
-
-    log::info!("[Rust] Download initiation completed, returning ID: {}", download_id);
-    Ok(download_id)
+    engine.start_download(url, file_path, file_name, options.unwrap_or_default()).await
 }
 
 /// Pause a download
 #[command]
-pub fn pause_download(download_id: String) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    if let Some(download) = manager.downloads.get(&download_id) {
-        if download.status != DownloadStatus::Downloading {
-            return Err("Download is not active".to_string());
-        }
-        
-        // Signal cancellation to stop current download
-        if let Some(token) = manager.cancellation_tokens.get(&download_id) {
-            token.store(true, Ordering::Relaxed);
-        }
-        
-        // Set user_paused to true for manual pause
-        if let Some(download) = manager.downloads.get_mut(&download_id) {
-            download.user_paused = true;
-        }
-        
-        // Set status to paused without cleaning up cancellation token
-        manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Paused, None);
-        
-        // Try to start next queued download since we freed up a slot
-        manager.start_next_queued_download();
-    }
-    
-    Ok(())
+pub fn pause_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    engine.pause_download(download_id)
 }
 
-/// Resume a paused download
+/// Resume a paused download, or retry one held for insufficient disk space
 #[command]
-pub async fn resume_download(download_id: String) -> Result<(), String> {
-    let download_info = {
-        let mut manager = DOWNLOAD_MANAGER.lock()
-            .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-        
-        if let Some(download) = manager.downloads.get(&download_id) {
-            if download.status == DownloadStatus::Paused {
-                // Check if we have capacity to resume this download
-                let downloading_count = manager.count_downloading_only();
-                let max_downloads = crate::settings::get_app_max_simultaneous_downloads().unwrap_or(3);
-                
-                if downloading_count >= max_downloads {
-                    // No capacity, set to queued instead
-                    manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Queued, None);
-                    return Ok(());
-                }
-                
-                let url = download.url.clone();
-                let file_path = download.file_path.clone();
-                
-                // Reset user_paused flag when manually resuming
-                if let Some(download_mut) = manager.downloads.get_mut(&download_id) {
-                    download_mut.user_paused = false;
-                }
-                
-                // Remove old cancellation token (status will be set atomically by spawn function)
-                manager.cancellation_tokens.remove(&download_id);
-                
-                Some((url, file_path))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    };
-    
-    // If we have download info, perform the download outside the lock
-    if let Some((url, file_path)) = download_info {
-        // Use centralized concurrency control (expects Paused status)
-        match spawn_perform_download_if_not_active(download_id.clone(), url, file_path, DownloadStatus::Paused) {
-            Ok(spawned) => {
-                if !spawned {
-                    log::info!("[Rust] Download already active for resume ID: {}", download_id);
-                }
-            },
-            Err(e) => {
-                log::error!("[Rust] Failed to spawn resume download for ID {}: {}", download_id, e);
-                // Update status to error
-                if let Ok(mut manager) = DOWNLOAD_MANAGER.lock() {
-                    manager.set_download_status(&download_id, DownloadStatus::Error, Some(e));
-                }
-            }
-        }
-    }
-    
-    Ok(())
+pub async fn resume_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    engine.resume_download(download_id).await
 }
 
 /// Cancel a download
 #[command]
-pub fn cancel_download(download_id: String) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    // Check if this was an active download before cancelling
-    let was_downloading = manager.downloads.get(&download_id)
-        .map(|d| d.status == DownloadStatus::Downloading)
-        .unwrap_or(false);
-    
-    // Signal cancellation
-    if let Some(token) = manager.cancellation_tokens.get(&download_id) {
-        token.store(true, Ordering::Relaxed);
-    }
-    
-    manager.set_download_status(&download_id, DownloadStatus::Cancelled, None);
-    
-    // If we cancelled an active download, try to start next queued download
-    if was_downloading {
-        manager.start_next_queued_download();
-    }
-    
-    Ok(())
+pub fn cancel_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    engine.cancel_download(download_id)
 }
 
 /// Cancel a download and delete the partially downloaded file
 #[command]
-pub fn cancel_and_delete_download(download_id: String) -> Result<(), String> {
-    let file_path = {
-        let mut manager = DOWNLOAD_MANAGER.lock()
-            .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-        
-        // Signal cancellation
-        if let Some(token) = manager.cancellation_tokens.get(&download_id) {
-            token.store(true, Ordering::Relaxed);
-        }
-        
-        // Get file path and file name before removing from downloads
-        let (file_path, file_name) = manager.downloads.get(&download_id)
-            .map(|download| (download.file_path.clone(), download.file_name.clone()))
-            .unwrap_or_else(|| (String::new(), String::new()));
-        
-        // Add activity for deletion before removing
-        if !file_name.is_empty() {
-            manager.add_activity(
-                ActivityType::DownloadCancelled,
-                Some(file_name),
-                Some(download_id.clone()),
-                Some("deleted".to_string()),
-                Some("Download cancelled and file deleted".to_string())
-            );
-        }
-        
-        // Remove from downloads and cancellation tokens
-         manager.downloads.remove(&download_id);
-         manager.cancellation_tokens.remove(&download_id);
-         
-         file_path
-    };
-    
-    // Delete the file if it exists
-    if !file_path.is_empty() {
-        let path = Path::new(&file_path);
-        if path.exists() {
-            match fs::remove_file(path) {
-                Ok(_) => log::info!("Successfully deleted file: {}", file_path),
-        Err(e) => log::error!("Failed to delete file {}: {}", file_path, e),
-            }
-        }
-    }
-    
-    Ok(())
+pub fn cancel_and_delete_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    engine.cancel_and_delete_download(download_id)
 }
 
 /// Restart a failed download
 #[command]
-pub async fn restart_download(download_id: String) -> Result<(), String> {
-    let download_info = {
-        let mut manager = DOWNLOAD_MANAGER.lock()
-            .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-        
-        if let Some(download) = manager.downloads.get(&download_id) {
-            if matches!(download.status, DownloadStatus::Error | DownloadStatus::Cancelled | DownloadStatus::Completed) {
-                let total_size = download.total_size;
-                let url = download.url.clone();
-                let file_path = download.file_path.clone();
-                
-                // Clean up any existing cancellation token (status will be set atomically by spawn function)
-                manager.cancellation_tokens.remove(&download_id);
-                
-                // Reset progress
-                manager.update_download_progress(&download_id, 0, total_size, 0);
-                
-                Some((url, file_path))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    };
-    
-    // If we have download info, perform the download outside the lock
-    if let Some((url, file_path)) = download_info {
-        // Use centralized concurrency control (expects Error/Cancelled/Completed status)
-        match spawn_perform_download_if_not_active(download_id.clone(), url, file_path, DownloadStatus::Error) {
-            Ok(spawned) => {
-                if !spawned {
-                    log::info!("[Rust] Download already active for restart ID: {}", download_id);
-                }
-            },
-            Err(e) => {
-                log::error!("[Rust] Failed to spawn restart download for ID {}: {}", download_id, e);
-                // Update status to error
-                if let Ok(mut manager) = DOWNLOAD_MANAGER.lock() {
-                    manager.set_download_status(&download_id, DownloadStatus::Error, Some(e));
-                }
-            }
-        }
-    }
-    
-    Ok(())
+pub async fn restart_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    engine.restart_download(download_id).await
 }
 
 /// Get all active downloads
 #[command]
-pub fn get_active_downloads() -> Result<Vec<DownloadItem>, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    Ok(manager.downloads.values().cloned().collect())
+pub fn get_active_downloads(engine: State<'_, DownloadEngine>) -> Result<Vec<DownloadItem>, String> {
+    engine.downloads()
 }
 
 /// Get download status for a specific download
 #[command]
-pub fn get_download_status(download_id: String) -> Result<Option<DownloadItem>, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    Ok(manager.downloads.get(&download_id).cloned())
+pub fn get_download_status(engine: State<'_, DownloadEngine>, download_id: String) -> Result<Option<DownloadItem>, String> {
+    engine.download(&download_id)
 }
 
 /// Clear completed downloads
 #[command]
-pub fn clear_completed_downloads() -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn clear_completed_downloads(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.downloads.retain(|_, download| {
         !matches!(download.status, DownloadStatus::Completed)
     });
+    manager.emit_queue_event("cleared");
     
     // Save state immediately after clearing completed downloads
     manager.save_state()
@@ -1360,15 +2854,79 @@ pub fn clear_completed_downloads() -> Result<(), String> {
     Ok(())
 }
 
+/// Search current and finished downloads by file name or URL
+#[command]
+pub fn search_downloads(engine: State<'_, DownloadEngine>, query: String, filter: Option<HistoryFilter>) -> Result<Vec<DownloadItem>, String> {
+    let filter = HistoryFilter { query: Some(query), ..filter.unwrap_or_default() };
+    Ok(engine.find_downloads(&filter)?.items)
+}
+
+/// Get current and finished downloads with the given status
+#[command]
+pub fn get_downloads_by_status(engine: State<'_, DownloadEngine>, status: String, filter: Option<HistoryFilter>) -> Result<Vec<DownloadItem>, String> {
+    let status: DownloadStatus = serde_json::from_value(serde_json::Value::String(status.clone()))
+        .map_err(|_| format!("Unknown download status: {}", status))?;
+    let filter = HistoryFilter { statuses: vec![status], ..filter.unwrap_or_default() };
+    Ok(engine.find_downloads(&filter)?.items)
+}
+
+/// Remove a download from the list and the history, leaving its file on disk
+#[command]
+pub fn remove_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    let removed_live = {
+        let mut manager = engine.lock()?;
+        
+        match manager.downloads.get(&download_id).map(|d| d.status.clone()) {
+            Some(DownloadStatus::Downloading | DownloadStatus::Verifying) => {
+                return Err("Pause or cancel the download before removing it".to_string());
+            }
+            Some(_) => {
+                manager.downloads.remove(&download_id);
+                manager.partial_downloads.remove(&download_id);
+                manager.cancellation_tokens.remove(&download_id);
+                manager.schedule_waiting.remove(&download_id);
+                manager.emit_queue_event("removed");
+                manager.save_state()
+                    .map_err(|e| format!("Failed to save state after removing download: {}", e))?;
+                true
+            }
+            None => false,
+        }
+    };
+    
+    let removed_history = engine.history().remove(&download_id)?;
+    if removed_live || removed_history {
+        log::info!("[Rust] Removed download {} from the list", download_id);
+        Ok(())
+    } else {
+        Err("Download not found".to_string())
+    }
+}
+
 /// Get download statistics
 #[command]
-pub fn get_download_stats() -> Result<DownloadStats, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn get_download_stats(engine: State<'_, DownloadEngine>) -> Result<DownloadStats, String> {
+    let manager = engine.lock()?;
     
     Ok(manager.get_stats())
 }
 
+/// Bytes per second over time, overall and per download (or only `download_id`), with hourly and daily totals
+#[command]
+pub fn get_throughput_history(engine: State<'_, DownloadEngine>, download_id: Option<String>) -> Result<ThroughputHistory, String> {
+    let mut manager = engine.lock()?;
+    let now = manager.clock.unix_time();
+    Ok(manager.throughput.snapshot(now, download_id.as_deref()))
+}
+
+/// Forget the recorded throughput
+#[command]
+pub fn clear_throughput_history(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    let mut manager = engine.lock()?;
+    manager.throughput.clear();
+    manager.throughput.save(&manager.get_throughput_file_path())
+}
+
 /// Open download location in file explorer
 #[command]
 pub fn open_download_location(file_path: String) -> Result<(), String> {
@@ -1405,9 +2963,8 @@ pub fn open_download_location(file_path: String) -> Result<(), String> {
 
 /// Set download directory
 #[command]
-pub fn set_download_directory(directory: String) -> Result<(), String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn set_download_directory(engine: State<'_, DownloadEngine>, directory: String) -> Result<(), String> {
+    let mut manager = engine.lock()?;
     
     manager.download_directory = PathBuf::from(directory);
     Ok(())
@@ -1415,42 +2972,41 @@ pub fn set_download_directory(directory: String) -> Result<(), String> {
 
 /// Get download directory
 #[command]
-pub fn get_download_directory() -> Result<String, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+pub fn get_download_directory(engine: State<'_, DownloadEngine>) -> Result<String, String> {
+    let manager = engine.lock()?;
     
     Ok(manager.download_directory.to_string_lossy().to_string())
 }
 
 /// Bulk operations
 #[command]
-pub fn bulk_pause_downloads(download_ids: Vec<String>) -> Result<(), String> {
+pub fn bulk_pause_downloads(engine: State<'_, DownloadEngine>, download_ids: Vec<String>) -> Result<(), String> {
     for id in download_ids {
-        pause_download(id)?;
+        engine.pause_download(id)?;
     }
     Ok(())
 }
 
 #[command]
-pub async fn bulk_resume_downloads(download_ids: Vec<String>) -> Result<(), String> {
+pub async fn bulk_resume_downloads(engine: State<'_, DownloadEngine>, download_ids: Vec<String>) -> Result<(), String> {
     for id in download_ids {
-        resume_download(id).await?;
+        engine.resume_download(id).await?;
     }
     Ok(())
 }
 
 #[command]
-pub fn bulk_cancel_downloads(download_ids: Vec<String>) -> Result<(), String> {
+pub fn bulk_cancel_downloads(engine: State<'_, DownloadEngine>, download_ids: Vec<String>) -> Result<(), String> {
     for id in download_ids {
-        cancel_download(id)?;
+        engine.cancel_download(id)?;
     }
     Ok(())
 }
 
 #[command]
-pub fn bulk_cancel_and_delete_downloads(download_ids: Vec<String>) -> Result<(), String> {
+pub fn bulk_cancel_and_delete_downloads(engine: State<'_, DownloadEngine>, download_ids: Vec<String>) -> Result<(), String> {
     for id in download_ids {
-        cancel_and_delete_download(id)?;
+        engine.cancel_and_delete_download(id)?;
     }
     Ok(())
 }
@@ -1583,27 +3139,19 @@ pub async fn check_file_existence_info(file_path: String, url: String) -> Result
 
 #[command]
 pub fn get_available_disk_space(path: String) -> Result<u64, String> {
-    use std::fs;
-    
-    // This is a simplified implementation
-    // In a real implementation, you'd use platform-specific APIs
-    match fs::metadata(&path) {
-        Ok(_) => Ok(1024 * 1024 * 1024 * 10), // Return 10GB as placeholder
-        Err(e) => Err(format!("Failed to get disk space: {}", e)),
-    }
+    crate::system::get_free_disk_space(Path::new(&path))
 }
 
 /// Verify and repair a corrupted download file
 #[command]
-pub async fn verify_and_repair_download(download_id: String) -> Result<String, String> {
-    let manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
-    
-    let download = manager.downloads.get(&download_id)
-        .ok_or_else(|| "Download not found".to_string())?
-        .clone();
-    
-    drop(manager);
+pub async fn verify_and_repair_download(engine: State<'_, DownloadEngine>, download_id: String) -> Result<String, String> {
+    let download = {
+        let manager = engine.lock()?;
+        
+        manager.downloads.get(&download_id)
+            .ok_or_else(|| "Download not found".to_string())?
+            .clone()
+    };
     
     let file_path = &download.file_path;
     let expected_size = download.total_size;
@@ -1620,15 +3168,22 @@ pub async fn verify_and_repair_download(download_id: String) -> Result<String, S
     // Verify integrity
     match DownloadManager::verify_file_integrity(file_path, expected_size).await {
         Ok(true) => {
-            Ok("File integrity verified - no corruption detected".to_string())
+            let Some((algorithm, expected)) = download.expected_checksum() else {
+                return Ok("File integrity verified - no corruption detected".to_string());
+            };
+            let actual = calculate_checksum(algorithm, Path::new(file_path)).await?;
+            if actual != expected {
+                return Err(format!("{} mismatch: expected {}, got {}", algorithm, expected, actual));
+            }
+            Ok(format!("File integrity verified - {} matches", algorithm))
         }
         Ok(false) => {
             // Attempt repair
-            match DownloadManager::repair_corrupted_download(file_path, expected_size).await {
+            let recorded = download.preallocate.then_some(download.downloaded_size);
+            match DownloadManager::repair_corrupted_download(file_path, expected_size, recorded).await {
                 Ok(repaired_size) => {
                     // Update download progress
-                    let mut manager = DOWNLOAD_MANAGER.lock()
-                        .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                    let mut manager = engine.lock()?;
                     manager.update_download_progress(&download_id, repaired_size, expected_size, 0);
                     
                     Ok(format!("File repaired successfully - size corrected to {} bytes", repaired_size))
@@ -1644,19 +3199,18 @@ pub async fn verify_and_repair_download(download_id: String) -> Result<String, S
     }
 }
 
-
-
 #[command]
-pub async fn check_and_fix_stalled_downloads() -> Result<Vec<String>, String> {
+pub async fn check_and_fix_stalled_downloads(engine: State<'_, DownloadEngine>) -> Result<Vec<String>, String> {
     let mut fixed_downloads = Vec::new();
     
     // Get all downloading status downloads
     let downloads_to_check: Vec<(String, String, u64, u64)> = {
-        let manager = DOWNLOAD_MANAGER.lock()
-            .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+        let manager = engine.lock()?;
         
+        // Segmented and preallocated downloads create the whole file up front, so the size on disk says nothing about progress
         manager.downloads.iter()
-            .filter(|(_, download)| download.status == DownloadStatus::Downloading)
+            .filter(|(_, download)| download.status == DownloadStatus::Downloading && !download.preallocate)
+            .filter(|(id, _)| manager.partial_downloads.get(*id).map_or(true, |p| p.segments.is_empty()))
             .map(|(id, download)| (id.clone(), download.file_path.clone(), download.downloaded_size, download.total_size))
             .collect()
     };
@@ -1670,8 +3224,7 @@ pub async fn check_and_fix_stalled_downloads() -> Result<Vec<String>, String> {
             if total_size > 0 && actual_file_size >= total_size {
                 log::info!("[Rust] Found stalled but complete download: {} (file size: {}, expected: {})", download_id, actual_file_size, total_size);
                 
-                let mut manager = DOWNLOAD_MANAGER.lock()
-                    .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                let mut manager = engine.lock()?;
                 
                 // Update the downloaded size to match actual file size
                 manager.update_download_progress(&download_id, actual_file_size, total_size, 0);
@@ -1690,8 +3243,7 @@ pub async fn check_and_fix_stalled_downloads() -> Result<Vec<String>, String> {
             else if actual_file_size > downloaded_size + 1024 { // At least 1KB difference
                 log::info!("[Rust] Updating progress for download: {} (file size: {}, recorded: {})", download_id, actual_file_size, downloaded_size);
                 
-                let mut manager = DOWNLOAD_MANAGER.lock()
-                    .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                let mut manager = engine.lock()?;
                 
                 manager.update_download_progress(&download_id, actual_file_size, total_size, 0);
                 
@@ -1703,74 +3255,19 @@ pub async fn check_and_fix_stalled_downloads() -> Result<Vec<String>, String> {
         }
     }
     
-    Ok(fixed_downloads)
-}
-
-/// Core download function using reqwest with robust error handling
-// Centralized function to spawn perform_download with concurrency control
-fn spawn_perform_download_if_not_active(download_id: String, url: String, file_path: String, expected_status: DownloadStatus) -> Result<bool, String> {
-    let mut manager = DOWNLOAD_MANAGER.lock()
-        .map_err(|e| {
-            let error_msg = format!("Failed to lock download manager: {}", e);
-            log::error!("[Rust] Error checking download status: {}", error_msg);
-            error_msg
-        })?;
-    
-    // Check download status before proceeding
-    if let Some(download) = manager.downloads.get(&download_id) {
-        if download.status != expected_status {
-            log::warn!("[Rust] Download ID {} not in expected state (status: {:?}, expected: {:?}), aborting spawn", download_id, download.status, expected_status);
-            return Ok(false);
-        }
-    } else {
-        log::warn!("[Rust] Download ID {} not found in manager, aborting spawn", download_id);
-        return Ok(false);
+    // Downloads held for disk space may fit again now that files were cleaned up
+    {
+        let mut manager = engine.lock()?;
+        manager.start_next_queued_download();
     }
     
-    // Use HashMap's entry API for truly atomic insert-if-absent behavior
-    use std::collections::hash_map::Entry;
-    match manager.cancellation_tokens.entry(download_id.clone()) {
-        Entry::Occupied(_) => {
-            log::warn!("[Rust] Download ID {} already has an active perform_download instance, aborting duplicate spawn", download_id);
-            return Ok(false);
-        }
-        Entry::Vacant(entry) => {
-            // Atomically claim this download by inserting the cancellation token AND setting status
-            let cancellation_token = Arc::new(AtomicBool::new(false));
-            entry.insert(cancellation_token.clone());
-            
-            // Set status to downloading atomically within the same lock
-            manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Downloading, None);
-            
-            log::info!("[Rust] Claimed download ID {} with cancellation token, spawning task", download_id);
-            
-            // Release the lock before spawning the task
-            drop(manager);
-            
-            // Spawn the actual download task
-            let download_id_clone = download_id.clone();
-            let url_clone = url.clone();
-            let file_path_clone = file_path.clone();
-            
-            tauri::async_runtime::spawn(async move {
-                if let Err(e) = perform_download_internal(download_id_clone.clone(), url_clone, file_path_clone).await {
-                    log::error!("[Rust] Download failed for ID {}: {}", download_id_clone, e);
-                    // Update status to error
-                    if let Ok(mut manager) = DOWNLOAD_MANAGER.lock() {
-                        manager.set_download_status(&download_id_clone, DownloadStatus::Error, Some(e));
-                    }
-                }
-            });
-            
-            return Ok(true);
-        }
-    }
+    Ok(fixed_downloads)
 }
 
-async fn perform_download_internal(download_id: String, url: String, file_path: String) -> Result<(), String> {
+async fn perform_download_internal(engine: DownloadEngine, download_id: String, url: String, file_path: String) -> Result<(), String> {
     // Early cancellation check to prevent duplicate execution
     {
-        let manager = DOWNLOAD_MANAGER.lock().unwrap();
+        let manager = engine.lock().unwrap();
         if let Some(token) = manager.cancellation_tokens.get(&download_id) {
             if token.load(Ordering::Relaxed) {
                 log::warn!("[Rust] Download ID {} was cancelled before starting, aborting", download_id);
@@ -1797,67 +3294,112 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
     
     // Get the cancellation token
     let cancellation_token = {
-        let manager = DOWNLOAD_MANAGER.lock().unwrap();
+        let manager = engine.lock().unwrap();
         manager.cancellation_tokens.get(&download_id).unwrap().clone()
     };
 
+    // Every URL this file can be fetched from; a partial file stays with the mirror that wrote it
+    let mirrors = {
+        let mut manager = engine.lock().unwrap();
+        let mut urls = vec![url.clone()];
+        let mut resume_from = None;
+        if let Some(download) = manager.downloads.get(&download_id) {
+            urls.extend(download.mirrors.iter().cloned());
+            if download.downloaded_size > 0 {
+                resume_from = Some(download.active_url.clone().unwrap_or_else(|| url.clone()));
+            }
+        }
+        let mirrors = Arc::new(crate::mirrors::MirrorSet::new(engine.clone(), &download_id, urls, resume_from.as_deref()));
+        // Validators saved with the partial file, so a mirror switch can be checked even if the probe fails
+        if let Some(partial) = manager.partial_downloads.get(&download_id).filter(|p| p.total_size > 0) {
+            mirrors.set_reference(crate::mirrors::RemoteFileInfo {
+                size: Some(partial.total_size),
+                etag: partial.etag.clone(),
+                last_modified: partial.last_modified.clone(),
+                accept_ranges: true,
+            });
+        }
+        if let Some(download) = manager.downloads.get_mut(&download_id) {
+            download.active_url = Some(mirrors.current());
+        }
+        mirrors
+    };
+    let mut probe_url = mirrors.current();
+
     // Check resume capability and get file info with HEAD request
     log::info!("[Rust] Checking resume capability and file info for ID: {}", download_id);
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .build()
-        .unwrap_or_else(|_| reqwest::Client::new());
+    let transport = engine.transport();
+    let clock = engine.clock();
     
     let mut total_size = 0u64;
     let mut resume_supported = false;
     let max_retries = 3;
     let mut head_request_successful = false;
+    let mut remote_info = None;
     
-    for attempt in 1..=max_retries {
-        log::info!("[Rust] HEAD request attempt {} of {} for ID: {}", attempt, max_retries, download_id);
+    loop {
+        for attempt in 1..=max_retries {
+            log::info!("[Rust] HEAD request attempt {} of {} for ID: {}", attempt, max_retries, download_id);
         
-        match client.head(&url).send().await {
-            Ok(resp) => {
-                total_size = resp.content_length().unwrap_or(0);
-                log::info!("[Rust] HEAD status: {} for ID: {}", resp.status(), download_id);
+            let custom_headers = engine.request_headers(&download_id, &probe_url);
+            match transport.head(&probe_url, &header_list(&custom_headers, Vec::new())).await {
+                Ok(resp) => {
+                    total_size = resp.content_length().unwrap_or(0);
+                    log::info!("[Rust] HEAD status: {} for ID: {}", resp.status(), download_id);
+                    let info = crate::mirrors::RemoteFileInfo::from_headers(resp.headers(), resp.content_length());
+                    mirrors.set_reference(info.clone());
+                    remote_info = Some(info);
                 
-                // Check Accept-Ranges header
-                if let Some(accept_ranges) = resp.headers().get("Accept-Ranges") {
-                    if let Ok(ranges_str) = accept_ranges.to_str() {
-                        log::info!("[Rust] Accept-Ranges: {} for ID: {}", ranges_str, download_id);
-                        if ranges_str.to_lowercase().contains("bytes") {
-                            resume_supported = true;
+                    // Check Accept-Ranges header
+                    if let Some(accept_ranges) = resp.headers().get("Accept-Ranges") {
+                        if let Ok(ranges_str) = accept_ranges.to_str() {
+                            log::info!("[Rust] Accept-Ranges: {} for ID: {}", ranges_str, download_id);
+                            if ranges_str.to_lowercase().contains("bytes") {
+                                resume_supported = true;
+                            }
                         }
+                    } else {
+                        log::info!("[Rust] Accept-Ranges: <missing> for ID: {}", download_id);
                     }
-                } else {
-                    log::info!("[Rust] Accept-Ranges: <missing> for ID: {}", download_id);
-                }
                 
-                log::info!("[Rust] File size determined via HEAD request for ID {}: {} bytes", download_id, total_size);
-                head_request_successful = true;
-                break;
+                    log::info!("[Rust] File size determined via HEAD request for ID {}: {} bytes", download_id, total_size);
+                    head_request_successful = true;
+                    break;
+                }
+                Err(e) => {
+                    log::info!("[Rust] HEAD request failed on attempt {} for ID {}: {}", attempt, download_id, e);
+                    engine.mirror_health().record_failure(&probe_url);
+                    if attempt == max_retries {
+                        log::info!("[Rust] HEAD request failed after {} attempts for ID {}. Will proceed with download and determine size during transfer.", max_retries, download_id);
+                    } else {
+                        log::info!("[Rust] Retrying HEAD request in 2 seconds...");
+                        clock.sleep(std::time::Duration::from_secs(2)).await;
+                    }
+                }
             }
-            Err(e) => {
-                log::info!("[Rust] HEAD request failed on attempt {} for ID {}: {}", attempt, download_id, e);
-                if attempt == max_retries {
-                    log::info!("[Rust] HEAD request failed after {} attempts for ID {}. Will proceed with download and determine size during transfer.", max_retries, download_id);
-                } else {
-                    log::info!("[Rust] Retrying HEAD request in 2 seconds...");
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+    
+        // An unreachable mirror is swapped for the next one before giving up on the probe
+        if !head_request_successful && mirrors.has_alternatives() {
+            let downloaded = engine.lock().unwrap().downloads.get(&download_id)
+                .map(|d| d.downloaded_size)
+                .unwrap_or(0);
+            if let Some(next_url) = mirrors.fail_over(&probe_url, downloaded > 0, "mirror did not answer HEAD requests").await {
+                if next_url != probe_url {
+                    probe_url = next_url;
+                    continue;
                 }
             }
         }
+        break;
     }
     
     // If HEAD request succeeded but Accept-Ranges was not "bytes", test with a range request
     if head_request_successful && !resume_supported {
         log::info!("[Rust] Testing range request support for ID: {}", download_id);
         
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(reqwest::header::RANGE, reqwest::header::HeaderValue::from_static("bytes=0-0"));
-        
-        match client.get(&url).headers(headers).send().await {
+        let custom_headers = engine.request_headers(&download_id, &probe_url);
+        match transport.get(&probe_url, &header_list(&custom_headers, vec![("Range", "bytes=0-0".to_string())])).await {
             Ok(range_resp) => {
                 log::info!("[Rust] Range status: {} for ID: {}", range_resp.status(), download_id);
                 if range_resp.status() == 206 {
@@ -1881,15 +3423,58 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
     
     // Update total size and resume capability, but preserve existing downloaded_size for resumed downloads
     {
-        let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
-        let current_downloaded = manager.downloads.get(&download_id)
+        let mut manager = engine.lock().unwrap();
+        let mut current_downloaded = manager.downloads.get(&download_id)
             .map(|d| d.downloaded_size)
             .unwrap_or(0);
-        manager.update_download_progress(&download_id, current_downloaded, total_size, 0);
-        manager.update_partial_download_info(&download_id, current_downloaded, total_size);
-        log::info!("[Rust] Updated download progress for ID {}: {}/{} bytes, resume_supported: {}", download_id, current_downloaded, total_size, resume_supported);
+        
+        // Bytes on disk from a file the server has since replaced can't be resumed
+        let has_partial_data = current_downloaded > 0 || Path::new(&file_path).exists();
+        let remote_change = remote_info.as_ref()
+            .filter(|_| has_partial_data)
+            .and_then(|info| manager.remote_file_change(&download_id, info));
+        if let Some(reason) = remote_change {
+            manager.restart_changed_download(&download_id, &reason);
+            current_downloaded = 0;
+        }
+        
+        if let Some(download) = manager.downloads.get_mut(&download_id) {
+            download.resume_supported = resume_supported;
+        }
+        manager.update_download_progress(&download_id, current_downloaded, total_size, 0);
+        manager.update_partial_download_info(&download_id, current_downloaded, total_size);
+        if let Some(info) = &remote_info {
+            manager.record_validators(&download_id, info, true);
+        }
+        log::info!("[Rust] Updated download progress for ID {}: {}/{} bytes, resume_supported: {}", download_id, current_downloaded, total_size, resume_supported);
+    }
+
+    // Now that the real size is known, hold the download rather than run out of space halfway
+    {
+        let mut manager = engine.lock().unwrap();
+        let needed = manager.downloads.get(&download_id)
+            .map(|d| manager.remaining_download_bytes(d))
+            .unwrap_or(0);
+        if let Err(reason) = manager.check_disk_space(&file_path, needed, Some(&download_id), false) {
+            manager.hold_for_disk_space(&download_id, reason);
+            manager.start_next_queued_download();
+            return Ok(());
+        }
     }
 
+    // Large files from servers that honour ranges are fetched over several connections.
+    // An existing segment plan for the same size is always resumed in segmented mode,
+    // and bytes left by a single-stream attempt become the start of a new plan.
+    let segmented_size = {
+        let manager = engine.lock().unwrap();
+        let has_segment_plan = manager.partial_downloads.get(&download_id)
+            .map(|p| !p.segments.is_empty() && p.total_size == total_size)
+            .unwrap_or(false);
+        let use_segmented = resume_supported && total_size > 0 &&
+            (has_segment_plan || segment_count_for_size(total_size, engine.download_segments()) > 1);
+        use_segmented.then_some(total_size)
+    };
+
     // Create parent directories if they don't exist
     if let Some(parent) = Path::new(&file_path).parent() {
         log::info!("[Rust] Creating parent directories for ID {}: {:?}", download_id, parent);
@@ -1903,43 +3488,47 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
     }
 
      // Use custom implementation for download
+     let task_engine = engine.clone();
      let download_id_clone2 = download_id.clone();
-     let url_clone = url.clone();
+     let mirrors_clone = mirrors.clone();
      let file_path_clone = file_path.clone();
      let cancellation_token_clone2 = cancellation_token.clone();
      
      let custom_result = tauri::async_runtime::spawn(async move {
-         // Create robust HTTP client with longer timeouts for large file downloads
-         let client = reqwest::Client::builder()
-             //.timeout(std::time::Duration::from_secs(300)) // 5 minutes overall timeout
-             //.connect_timeout(std::time::Duration::from_secs(30)) // 30 seconds to establish connection
-             //.tcp_keepalive(std::time::Duration::from_secs(60)) // Keep connections alive
-             //.pool_idle_timeout(std::time::Duration::from_secs(90)) // Connection pool timeout
-             //.pool_max_idle_per_host(4) // Allow multiple connections per host
-             .user_agent("YuukiPS-Launcher/1.0 (Download Manager)")
-             .build()
-             .unwrap_or_else(|_| reqwest::Client::new());
+         let engine = task_engine;
+         if let Some(segmented_total) = segmented_size {
+             return perform_segmented_download(engine, download_id_clone2, mirrors_clone, file_path_clone, segmented_total, cancellation_token_clone2).await;
+         }
+         
+         // Draw from the shared bandwidth limit for the whole lifetime of this download
+         let transfer = engine.register_transfer(&download_id_clone2);
+         
+         let transport = engine.transport();
+         let clock = engine.clock();
             
         let mut downloaded = 0u64;
         let mut total_size = 0u64;
-        let mut last_update = std::time::Instant::now();
+        let mut last_update = clock.now();
         let mut last_downloaded = 0u64;
         let mut consecutive_errors = 0u32;
         let max_consecutive_errors = 5;
         let max_total_retries = 15; // Increased retry limit for unstable connections
         let mut total_retries = 0u32;
-        let mut last_progress_time = std::time::Instant::now();
+        let mut last_progress_time = clock.now();
         let progress_timeout = std::time::Duration::from_secs(180); // 3 minutes for progress timeout
         let mut base_delay = std::time::Duration::from_secs(1); // Base delay for exponential backoff
         let mut success_count = 0u32; // Track successful chunk reads for connection stability
+        let mut request_registered = false;
+        let mut throughput_window = crate::mirrors::ThroughputWindow::starting_at(clock.now());
         
         // Check resume capability first before handling existing files
-        let resume_supported = {
-            let manager = DOWNLOAD_MANAGER.lock().unwrap();
+        let (resume_supported, preallocate, recorded_size) = {
+            let manager = engine.lock().unwrap();
             manager.downloads.get(&download_id_clone2)
-                .map(|d| d.resume_supported)
-                .unwrap_or(false)
+                .map(|d| (d.resume_supported, d.preallocate, d.downloaded_size))
+                .unwrap_or((false, false, 0))
         };
+        let recorded = preallocate.then_some(recorded_size);
         
         // Check if file already exists (for resume functionality)
         if let Ok(metadata) = std::fs::metadata(&file_path_clone) {
@@ -1949,7 +3538,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
             if resume_supported {
                 // If we know the total size, verify the existing file isn't corrupted
                 if total_size > 0 {
-                    match DownloadManager::repair_corrupted_download(&file_path_clone, total_size).await {
+                    match DownloadManager::repair_corrupted_download(&file_path_clone, total_size, recorded).await {
                         Ok(repaired_size) => {
                             downloaded = repaired_size;
                             if repaired_size != existing_size {
@@ -1962,7 +3551,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                         }
                     }
                 } else {
-                    downloaded = existing_size;
+                    downloaded = recorded.map_or(existing_size, |recorded| recorded.min(existing_size));
                 }
             } else {
                 log::info!("[Rust] Resume not supported, removing existing file for fresh download for ID {}", download_id_clone2);
@@ -1977,7 +3566,9 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
         }
         
         // Get file size with HEAD request
-        match client.head(&url_clone).send().await {
+        let head_url = mirrors_clone.current();
+        let custom_headers = engine.request_headers(&download_id_clone2, &head_url);
+        match transport.head(&head_url, &header_list(&custom_headers, Vec::new())).await {
             Ok(response) => {
                 if let Some(content_length) = response.content_length() {
                     total_size = content_length;
@@ -1996,8 +3587,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
              if cancellation_token_clone2.load(Ordering::Relaxed) {
                  // Check if this was a user-initiated pause
                  let is_user_paused = {
-                     let manager = DOWNLOAD_MANAGER.lock()
-                         .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                     let manager = engine.lock()?;
                      manager.downloads.get(&download_id_clone2)
                          .map(|d| d.user_paused)
                          .unwrap_or(false)
@@ -2014,8 +3604,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
 
              // Check if download should be paused or cancelled
              let (should_continue, is_completed) = {
-                 let manager = DOWNLOAD_MANAGER.lock()
-                     .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                 let manager = engine.lock()?;
                  if let Some(download) = manager.downloads.get(&download_id_clone2) {
                      let is_completed = matches!(download.status, DownloadStatus::Completed) || 
                                        (total_size > 0 && download.downloaded_size >= total_size);
@@ -2038,7 +3627,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                  
                  // Mark download as completed and exit
                  {
-                     let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                     let mut manager = engine.lock().unwrap();
                      manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
                  }
                  
@@ -2053,7 +3642,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
             
             // Check for progress timeout only if download is not complete
              // For unknown size downloads (total_size == 0), still check timeout
-            if (total_size == 0 || downloaded < total_size) && last_progress_time.elapsed() > progress_timeout {
+            if (total_size == 0 || downloaded < total_size) && clock.now().duration_since(last_progress_time) > progress_timeout {
                 // Special case: if we know the total size and have downloaded enough, mark as completed
                 if total_size > 0 && downloaded >= total_size {
                     log::info!("[Rust] Download appears stalled but file is complete for ID {}: {}/{} bytes", download_id_clone2, downloaded, total_size);
@@ -2067,7 +3656,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
              
              // Additional duplicate check before making HTTP request
              {
-                 let manager = DOWNLOAD_MANAGER.lock().unwrap();
+                 let manager = engine.lock().unwrap();
                  if let Some(token) = manager.cancellation_tokens.get(&download_id_clone2) {
                      if token.load(Ordering::Relaxed) {
                          log::warn!("[Rust] Download ID {} was cancelled during execution, aborting HTTP request", download_id_clone2);
@@ -2087,28 +3676,36 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                  }
              }
              
-             // Global duplicate check before HTTP request
-             {
-                 let mut active_requests = ACTIVE_DOWNLOAD_REQUESTS.lock().unwrap();
+             // Global duplicate check before the first HTTP request; retries of this task are not duplicates
+             if !request_registered {
+                 let mut active_requests = engine.inner.active_requests.lock().unwrap();
                  if active_requests.contains(&download_id_clone2) {
                      log::warn!("[Rust] Duplicate HTTP request detected for ID {}, aborting", download_id_clone2);
                      return Err("Duplicate HTTP request detected".to_string());
                  }
                  active_requests.insert(download_id_clone2.clone());
+                 request_registered = true;
              }
              
              // Create request with range headers only for resumable downloads
-              let mut request = client.get(&url_clone);
+              let request_url = mirrors_clone.current();
+              let mut request_headers = Vec::new();
               
-              if downloaded > 0 && resume_supported {
+              let range_requested = downloaded > 0 && resume_supported;
+              if range_requested {
                   let range_header = format!("bytes={}-", downloaded);
-                  request = request.header("Range", &range_header);
+                  request_headers.push(("Range", range_header.clone()));
+                  // The server sends the whole file instead of the range if it changed since
+                  if let Some(validator) = mirrors_clone.if_range() {
+                      request_headers.push(("If-Range", validator));
+                  }
                   log::info!("[Rust] Using range request for resume: {} for ID {}", range_header, download_id_clone2);
               } else {
                   log::info!("[Rust] Starting fresh download for ID {}", download_id_clone2);
               }
             
-            match request.send().await {
+            let custom_headers = engine.request_headers(&download_id_clone2, &request_url);
+            match transport.get(&request_url, &header_list(&custom_headers, request_headers)).await {
                 Ok(mut response) => {
                      if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
                          // Special handling for HTTP 416 Range Not Satisfiable
@@ -2132,16 +3729,44 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                          consecutive_errors += 1;
                          log::error!("[Rust] HTTP error {} for ID {}: attempt {}", response.status(), download_id_clone2, consecutive_errors);
                          
+                         // A mirror that keeps failing is replaced before the retries run out
+                         if mirrors_clone.report_error(&request_url) {
+                             let reason = format!("HTTP {}", response.status());
+                             if mirrors_clone.fail_over(&request_url, downloaded > 0, &reason).await.is_some() {
+                                 consecutive_errors = 0;
+                                 continue;
+                             }
+                         }
+                         
                          if consecutive_errors >= max_consecutive_errors {
                              return Err(format!("HTTP error after {} attempts: {}", max_consecutive_errors, response.status()));
                          }
                          
-                         tokio::time::sleep(std::time::Duration::from_millis(1000 * consecutive_errors as u64)).await;
+                         clock.sleep(std::time::Duration::from_millis(1000 * consecutive_errors as u64)).await;
                          continue;
                      }
                      
                      // Reset error counter on successful response
                      consecutive_errors = 0;
+                     mirrors_clone.report_success(&request_url);
+                     
+                     // A full response to a range request means If-Range failed: start over with this body
+                     if range_requested && response.status() == reqwest::StatusCode::OK {
+                         let mut manager = engine.lock().unwrap();
+                         manager.restart_changed_download(&download_id_clone2, "validators did not match on resume");
+                         mirrors_clone.set_reference(crate::mirrors::RemoteFileInfo::from_headers(response.headers(), response.content_length()));
+                         downloaded = 0;
+                         last_downloaded = 0;
+                         total_size = 0;
+                     }
+                     
+                     // Keep the validators of the first response so later resumes can be checked
+                     {
+                         let info = crate::mirrors::RemoteFileInfo::from_headers(response.headers(), None);
+                         let mut manager = engine.lock().unwrap();
+                         manager.update_partial_download_info(&download_id_clone2, downloaded, total_size);
+                         manager.record_validators(&download_id_clone2, &info, false);
+                     }
                      
                      // Handle total size for both fresh downloads and resumes
                      if total_size == 0 {
@@ -2180,12 +3805,12 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                  log::warn!("[Rust] Downloaded size ({}) exceeds reported total size ({}) for ID {}. File may already be complete or server reported inconsistent sizes.", downloaded, total_size, download_id_clone2);
                                  
                                  // Mark download as completed if file appears to be complete
-                                 let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                                 let mut manager = engine.lock().unwrap();
                                  manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
                                  return Ok(());
                              }
                              
-                             let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                             let mut manager = engine.lock().unwrap();
                              manager.update_download_progress(&download_id_clone2, downloaded, total_size, 0);
                          }
                      }
@@ -2193,19 +3818,19 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                     // Open/create file for writing
                      let mut file = if downloaded == 0 {
                          // For fresh downloads, always create new file
-                         tokio::fs::File::create(&file_path_clone).await
-                             .map_err(|e| format!("Failed to create file: {}", e))?
+                         create_output_file(&file_path_clone, preallocate).await?
                      } else {
                          // For resumable downloads with range support
-                         // Verify file integrity before resuming
+                         // Verify file integrity before resuming; a preallocated file should already be full size
                          if total_size > 0 {
-                             match DownloadManager::verify_file_integrity(&file_path_clone, downloaded).await {
+                             let expected_len = if preallocate { total_size } else { downloaded };
+                             match DownloadManager::verify_file_integrity(&file_path_clone, expected_len).await {
                                  Ok(true) => {
                                      log::info!("[Rust] File integrity verified for resume: {} bytes", downloaded);
                                  }
                                  Ok(false) => {
                                      log::warn!("[Rust] File integrity check failed, attempting repair for ID: {}", download_id_clone2);
-                                     match DownloadManager::repair_corrupted_download(&file_path_clone, total_size).await {
+                                     match DownloadManager::repair_corrupted_download(&file_path_clone, total_size, preallocate.then_some(downloaded)).await {
                                          Ok(repaired_size) => {
                                              downloaded = repaired_size;
                                              log::info!("[Rust] File repaired to {} bytes for ID: {}", repaired_size, download_id_clone2);
@@ -2224,30 +3849,37 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                          }
                          
                          if downloaded > 0 {
-                             tokio::fs::OpenOptions::new()
+                             let mut file = tokio::fs::OpenOptions::new()
                                  .write(true)
-                                 .append(true)
                                  .open(&file_path_clone)
                                  .await
-                                 .map_err(|e| format!("Failed to open file for append: {}", e))?
+                                 .map_err(|e| format!("Failed to open file for resume: {}", e))?;
+                             // Not opened for append: a preallocated file already extends past the resume point
+                             file.seek(std::io::SeekFrom::Start(downloaded)).await
+                                 .map_err(|e| format!("Failed to seek to resume offset: {}", e))?;
+                             file
                          } else {
-                             tokio::fs::File::create(&file_path_clone).await
-                                 .map_err(|e| format!("Failed to create file: {}", e))?
+                             create_output_file(&file_path_clone, preallocate).await?
                          }
                      };
-                    
-                    // Initialize rate limiting variables
-                     let mut rate_limiter_start = std::time::Instant::now();
-                     let mut bytes_downloaded_in_window = 0u64;
                      
+                     // Reserve the rest of the file before writing, so a full disk shows up now and not near the end
+                     if preallocate && total_size > 0 {
+                         if let Err(reason) = preallocate_file(&file, total_size).await {
+                             let mut manager = engine.lock().unwrap();
+                             manager.hold_for_disk_space(&download_id_clone2, reason);
+                             manager.start_next_queued_download();
+                             return Ok(());
+                         }
+                     }
+                    
                     // Download chunks
                      while let Some(chunk_result) = response.chunk().await.transpose() {
                          // Check for cancellation
                          if cancellation_token_clone2.load(Ordering::Relaxed) {
                              // Check if this was a user-initiated pause
                              let is_user_paused = {
-                                 let manager = DOWNLOAD_MANAGER.lock()
-                                     .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                                 let manager = engine.lock()?;
                                  manager.downloads.get(&download_id_clone2)
                                      .map(|d| d.user_paused)
                                      .unwrap_or(false)
@@ -2264,8 +3896,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                          
                          // Check if download should continue
                          let should_continue = {
-                             let manager = DOWNLOAD_MANAGER.lock()
-                                 .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                             let manager = engine.lock()?;
                              manager.downloads.get(&download_id_clone2)
                                  .map(|d| matches!(d.status, DownloadStatus::Downloading))
                                  .unwrap_or(false)
@@ -2304,17 +3935,17 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                         // Download is already complete, no more data needed
                                         log::info!("[Rust] Download already at expected size, no more data needed for ID: {}", download_id_clone2);
                                         
-                                        // Mark download as completed and exit
-                                        {
-                                            let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
-                                            manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
-                                        }
-                                        
                                         file.flush().await
                                             .map_err(|e| format!("Failed to flush file: {}", e))?;
                                         file.sync_all().await
                                             .map_err(|e| format!("Failed to sync file: {}", e))?;
                                         
+                                        // Mark download as completed once its data is on disk, and exit
+                                        {
+                                            let mut manager = engine.lock().unwrap();
+                                            manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
+                                        }
+                                        
                                         log::info!("[Rust] Download completed successfully for ID: {}", download_id_clone2);
                                         return Ok(());
                                     }
@@ -2327,30 +3958,26 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                     
                                     log::info!("[Rust] Wrote final {} bytes, download completed for ID: {}", truncated_chunk.len(), download_id_clone2);
                                     
-                                    // Mark download as completed and exit
-                                    {
-                                        let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
-                                        manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
-                                    }
-                                    
                                     file.flush().await
                                         .map_err(|e| format!("Failed to flush file: {}", e))?;
                                     file.sync_all().await
                                         .map_err(|e| format!("Failed to sync file: {}", e))?;
                                     
+                                    // Mark download as completed once its data is on disk, and exit
+                                    {
+                                        let mut manager = engine.lock().unwrap();
+                                        manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
+                                    }
+                                    
                                     log::info!("[Rust] Download completed successfully for ID: {}", download_id_clone2);
                                     return Ok(());
                                 }
                                 
-                                last_progress_time = std::time::Instant::now();
+                                last_progress_time = clock.now();
                                 
                                 use tokio::io::AsyncWriteExt;
                                 file.write_all(&chunk).await
                                     .map_err(|e| format!("Failed to write to file: {}", e))?;
-                                
-                                // Ensure data is written to disk immediately to prevent corruption
-                                file.sync_data().await
-                                    .map_err(|e| format!("Failed to sync file data: {}", e))?;
 
                                 downloaded += chunk.len() as u64;
                                 
@@ -2365,64 +3992,23 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                     log::debug!("[Rust] Connection stabilized, reset base delay for ID: {}", download_id_clone2);
                                 }
 
-                                // Apply speed limiting if enabled using a sliding window approach
-                                 let (speed_limit, divide_speed_enabled) = {
-                                     let settings = SETTINGS.lock().unwrap();
-                                     (settings.speed_limit_mbps, settings.divide_speed_enabled)
-                                 };
-                                
-                                let effective_speed_limit = if divide_speed_enabled && speed_limit > 0.0 {
-                                    // Count active downloads and divide speed limit among them
-                                    let active_downloads_count = {
-                                        let manager = DOWNLOAD_MANAGER.lock().unwrap();
-                                        manager.downloads.values()
-                                            .filter(|d| matches!(d.status, DownloadStatus::Downloading))
-                                            .count()
-                                    };
-                                    
-                                    if active_downloads_count > 0 {
-                                        speed_limit / active_downloads_count as f64
-                                    } else {
-                                        speed_limit
-                                    }
-                                } else {
-                                    speed_limit
-                                };
-                                
-                                if effective_speed_limit > 0.0 {
-                                    // Convert speed limit from MB/s to bytes/s
-                                    let speed_limit_bytes_per_sec = effective_speed_limit * 1024.0 * 1024.0;
-                                    
-                                    // Add current chunk to the rate limiting window
-                                    bytes_downloaded_in_window += chunk.len() as u64;
-                                    
-                                    // Calculate elapsed time in the current window
-                                    let elapsed = rate_limiter_start.elapsed();
-                                    let elapsed_secs = elapsed.as_secs_f64();
-                                    
-                                    // If we have data for at least 100ms, check if we need to throttle
-                                    if elapsed_secs >= 0.1 {
-                                        let current_rate = bytes_downloaded_in_window as f64 / elapsed_secs;
-                                        
-                                        // If we're exceeding the speed limit, calculate how long to sleep
-                                        if current_rate > speed_limit_bytes_per_sec {
-                                            let excess_bytes = bytes_downloaded_in_window as f64 - (speed_limit_bytes_per_sec * elapsed_secs);
-                                            let sleep_time_secs = excess_bytes / speed_limit_bytes_per_sec;
-                                            let sleep_time_ms = (sleep_time_secs * 1000.0).max(1.0) as u64;
-                                            
-                                            if sleep_time_ms > 0 {
-                                                tokio::time::sleep(std::time::Duration::from_millis(sleep_time_ms)).await;
-                                            }
+                                // Wait for this chunk's share of the global speed limit
+                                transfer.acquire(chunk.len()).await;
+
+                                // Move to another mirror if this one slowed to a crawl; the next request resumes there
+                                if let Some(window_speed) = throughput_window.add(chunk.len() as u64, clock.now()) {
+                                    if total_size > 0 && resume_supported && mirrors_clone.report_throughput(&request_url, window_speed) {
+                                        let reason = format!("throughput dropped to {}/s", crate::utils::format_file_size(window_speed as u64));
+                                        if mirrors_clone.fail_over(&request_url, downloaded > 0, &reason).await.is_some() {
+                                            file.flush().await
+                                                .map_err(|e| format!("Failed to flush file: {}", e))?;
+                                            break;
                                         }
-                                        
-                                        // Reset the rate limiting window
-                                        rate_limiter_start = std::time::Instant::now();
-                                        bytes_downloaded_in_window = 0;
                                     }
                                 }
 
                                 // Update progress every 500ms
-                                let now = std::time::Instant::now();
+                                let now = clock.now();
                                 if now.duration_since(last_update).as_millis() >= 500 {
                                     let elapsed_secs = now.duration_since(last_update).as_secs_f64();
                                     let speed = if elapsed_secs > 0.0 && downloaded >= last_downloaded {
@@ -2431,7 +4017,11 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                         0
                                     };
                                     
-                                    let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                                    // Sync before recording progress, so a resume never trusts bytes that aren't on disk
+                                    file.sync_data().await
+                                        .map_err(|e| format!("Failed to sync file data: {}", e))?;
+                                    
+                                    let mut manager = engine.lock().unwrap();
                                      manager.update_download_progress(&download_id_clone2, downloaded, total_size, speed);
                                      
                                      last_update = now;
@@ -2457,7 +4047,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                      
                                      // Update download status to Completed
                                      {
-                                         let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                                         let mut manager = engine.lock().unwrap();
                                          manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
                                          log::info!("[Rust] Download status set to Completed in download loop for ID: {}", download_id_clone2);
                                          // save state
@@ -2474,7 +4064,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                 
                                 // Check if resume is supported
                                 let resume_supported = {
-                                    let manager = DOWNLOAD_MANAGER.lock().unwrap();
+                                    let manager = engine.lock().unwrap();
                                     manager.downloads.get(&download_id_clone2)
                                         .map(|d| d.resume_supported)
                                         .unwrap_or(false)
@@ -2492,6 +4082,14 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                     log::error!("[Rust] Failed to flush file after chunk error: {}", flush_err);
                                 }
                                 
+                                // Keep the bytes already written and continue from another mirror
+                                if mirrors_clone.report_error(&request_url) &&
+                                    mirrors_clone.fail_over(&request_url, downloaded > 0, &format!("read error: {}", e)).await.is_some() {
+                                    consecutive_errors = 0;
+                                    last_downloaded = downloaded;
+                                    break;
+                                }
+                                
                                 if consecutive_errors >= max_consecutive_errors {
                                     return Err(format!("Download failed after {} consecutive chunk errors: {}", max_consecutive_errors, e));
                                 }
@@ -2526,7 +4124,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                                                base_delay.as_millis(), download_id_clone2);
                                 }
                                 
-                                tokio::time::sleep(std::time::Duration::from_millis(final_delay_ms)).await;
+                                clock.sleep(std::time::Duration::from_millis(final_delay_ms)).await;
                                 
                                 // Break from chunk loop to retry request
                                 break;
@@ -2538,17 +4136,17 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                     if total_size > 0 && downloaded >= total_size {
                         log::info!("[Rust] Download completed after chunk processing: {} bytes for ID: {}", downloaded, download_id_clone2);
                         
-                        // Mark download as completed and exit
-                        {
-                            let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
-                            manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
-                        }
-                        
                         file.flush().await
                             .map_err(|e| format!("Failed to flush file: {}", e))?;
                         file.sync_all().await
                             .map_err(|e| format!("Failed to sync file: {}", e))?;
                         
+                        // Mark download as completed once its data is on disk, and exit
+                        {
+                            let mut manager = engine.lock().unwrap();
+                            manager.set_download_status(&download_id_clone2, DownloadStatus::Completed, None);
+                        }
+                        
                         log::info!("[Rust] Download completed successfully for ID: {}", download_id_clone2);
                         return Ok(());
                     }
@@ -2575,6 +4173,12 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                     total_retries += 1;
                     log::error!("[Rust] Request error {} for ID {} (total retries: {}): {}", consecutive_errors, download_id_clone2, total_retries, e);
                    
+                   if mirrors_clone.report_error(&request_url) &&
+                       mirrors_clone.fail_over(&request_url, downloaded > 0, &format!("request error: {}", e)).await.is_some() {
+                       consecutive_errors = 0;
+                       continue;
+                   }
+                   
                    if consecutive_errors >= max_consecutive_errors {
                        return Err(format!("Download failed after {} consecutive request errors: {}", max_consecutive_errors, e));
                    }
@@ -2585,7 +4189,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                    
                    // Check resume support and decide whether to restart or continue
                    let resume_supported = {
-                       let manager = DOWNLOAD_MANAGER.lock().unwrap();
+                       let manager = engine.lock().unwrap();
                        manager.downloads.get(&download_id_clone2)
                            .map(|info| info.resume_supported)
                            .unwrap_or(true) // Default to true if no info available
@@ -2606,7 +4210,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                        
                        // Update download manager with reset progress
                        {
-                           let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                           let mut manager = engine.lock().unwrap();
                            manager.update_download_progress(&download_id_clone2, 0, total_size, 0);
                            manager.update_partial_download_info(&download_id_clone2, 0, total_size);
                        }
@@ -2635,7 +4239,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                        log::info!("[Rust] Increased base delay to {:.1}s due to persistent errors", base_delay.as_secs_f32());
                    }
                    
-                   tokio::time::sleep(final_delay).await;
+                   clock.sleep(final_delay).await;
                }            
             }
         }
@@ -2643,7 +4247,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
 
      // Clean up cancellation token
      {
-         let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+         let mut manager = engine.lock().unwrap();
          manager.cancellation_tokens.remove(&download_id);
      }
 
@@ -2651,8 +4255,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
         Ok(Ok(())) => {
             // Check final status
             let final_status = {
-                let manager = DOWNLOAD_MANAGER.lock()
-                    .map_err(|e| format!("Failed to lock download manager: {}", e))?;
+                let manager = engine.lock()?;
                 manager.downloads.get(&download_id)
                     .map(|d| d.status.clone())
                     .unwrap_or(DownloadStatus::Error)
@@ -2660,7 +4263,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
 
             // Check if download was actually completed by comparing downloaded vs total size
             let (downloaded_size, total_size) = {
-                let manager = DOWNLOAD_MANAGER.lock().unwrap();
+                let manager = engine.lock().unwrap();
                 if let Some(download) = manager.downloads.get(&download_id) {
                     (download.downloaded_size, download.total_size)
                 } else {
@@ -2673,7 +4276,7 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                     if downloaded_size >= total_size && total_size > 0 {
                         // Download actually completed successfully
                         log::info!("[Rust] Download completed successfully for ID: {}", download_id);
-                        let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                        let mut manager = engine.lock().unwrap();
                         manager.set_download_status(&download_id, DownloadStatus::Completed, None);
                         log::info!("[Rust] Download status set to Completed for ID: {}", download_id);
                     } else {
@@ -2684,6 +4287,9 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
                 DownloadStatus::Completed => {
                     log::info!("[Rust] Download completed successfully for ID: {}", download_id);
                 }
+                DownloadStatus::Verifying => {
+                    log::info!("[Rust] Download transferred, verifying checksum for ID: {}", download_id);
+                }
                 DownloadStatus::Paused => {
                     // Respect user's pause action - don't auto-complete paused downloads
                     log::info!("[Rust] Download was paused by user for ID: {}", download_id);
@@ -2696,28 +4302,40 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
             
             // Clean up cancellation token and active request tracking on successful completion
             {
-                let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                let mut manager = engine.lock().unwrap();
                 manager.cancellation_tokens.remove(&download_id);
                 log::info!("[Rust] Cleaned up cancellation token for completed download ID: {}", download_id);
             }
             {
-                let mut active_requests = ACTIVE_DOWNLOAD_REQUESTS.lock().unwrap();
+                let mut active_requests = engine.inner.active_requests.lock().unwrap();
                 active_requests.remove(&download_id);
                 log::info!("[Rust] Cleaned up active request tracking for completed download ID: {}", download_id);
             }
             
             Ok(())
         }
+        Ok(Err(e)) if e.starts_with(REMOTE_FILE_CHANGED) => {
+            // Segments can't restart in place, so the whole download goes back to the queue from zero
+            {
+                let mut manager = engine.lock().unwrap();
+                manager.cancellation_tokens.remove(&download_id);
+                manager.restart_changed_download(&download_id, "validators did not match on resume");
+                manager.set_download_status_no_cleanup(&download_id, DownloadStatus::Queued, None);
+                manager.start_next_queued_download();
+            }
+            engine.inner.active_requests.lock().unwrap().remove(&download_id);
+            Ok(())
+        }
         Ok(Err(e)) => {
             // Clean up cancellation token and active request tracking on error
             {
-                let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                let mut manager = engine.lock().unwrap();
                 manager.cancellation_tokens.remove(&download_id);
                 manager.set_download_status(&download_id, DownloadStatus::Error, Some(e.clone()));
                 log::info!("[Rust] Cleaned up cancellation token for failed download ID: {}", download_id);
             }
             {
-                let mut active_requests = ACTIVE_DOWNLOAD_REQUESTS.lock().unwrap();
+                let mut active_requests = engine.inner.active_requests.lock().unwrap();
                 active_requests.remove(&download_id);
                 log::info!("[Rust] Cleaned up active request tracking for failed download ID: {}", download_id);
             }
@@ -2727,17 +4345,641 @@ async fn perform_download_internal(download_id: String, url: String, file_path:
             let error_msg = format!("Download task failed: {}", e);
             // Clean up cancellation token and active request tracking on task failure
             {
-                let mut manager = DOWNLOAD_MANAGER.lock().unwrap();
+                let mut manager = engine.lock().unwrap();
                 manager.cancellation_tokens.remove(&download_id);
                 manager.set_download_status(&download_id, DownloadStatus::Error, Some(error_msg.clone()));
                 log::info!("[Rust] Cleaned up cancellation token for task failed download ID: {}", download_id);
             }
             {
-                let mut active_requests = ACTIVE_DOWNLOAD_REQUESTS.lock().unwrap();
+                let mut active_requests = engine.inner.active_requests.lock().unwrap();
                 active_requests.remove(&download_id);
                 log::info!("[Rust] Cleaned up active request tracking for task failed download ID: {}", download_id);
             }
             Err(error_msg)
         }
     }
-}
\ No newline at end of file
+}
+/// Files below this size are always fetched over a single connection
+const SEGMENTED_MIN_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// Smallest byte range worth a dedicated connection
+const MIN_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_SEGMENT_CONSECUTIVE_ERRORS: u32 = 5;
+/// Bytes a segment connection writes between two syncs; only synced bytes are recorded as resumable
+const SEGMENT_SYNC_BYTES: u64 = 8 * 1024 * 1024;
+/// Error prefix for a resume that found a different file on the server
+const REMOTE_FILE_CHANGED: &str = "REMOTE_FILE_CHANGED";
+
+/// Number of connections to use for a file of the given size
+fn segment_count_for_size(total_size: u64, configured: u32) -> u32 {
+    if total_size < SEGMENTED_MIN_FILE_SIZE {
+        return 1;
+    }
+    
+    let configured = configured.max(1);
+    let by_size = (total_size / MIN_SEGMENT_SIZE).max(1);
+    configured.min(by_size.min(u32::MAX as u64) as u32)
+}
+
+/// Split a file into contiguous, inclusive byte ranges of roughly equal size
+fn plan_segments(total_size: u64, segment_count: u32) -> Vec<DownloadSegment> {
+    let count = segment_count.max(1) as u64;
+    let segment_size = total_size / count;
+    
+    (0..count).map(|index| {
+        let start = index * segment_size;
+        let end = if index == count - 1 { total_size - 1 } else { start + segment_size - 1 };
+        DownloadSegment {
+            index: index as u32,
+            start,
+            end,
+            downloaded: 0,
+        }
+    }).collect()
+}
+
+/// Open a file for a download starting from byte 0. A preallocated file keeps its length,
+/// since the bytes past the write position are overwritten before the download completes.
+async fn create_output_file(file_path: &str, preallocate: bool) -> Result<tokio::fs::File, String> {
+    if !preallocate {
+        return tokio::fs::File::create(file_path).await
+            .map_err(|e| format!("Failed to create file: {}", e));
+    }
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(file_path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))
+}
+
+/// Grow a download's file to `total_size`, or shrink it if a previous attempt left it longer.
+/// Some file systems only record the new length (a sparse file); the queue's disk space check still covers those.
+async fn preallocate_file(file: &tokio::fs::File, total_size: u64) -> Result<(), String> {
+    let current_len = file.metadata().await
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        .len();
+    if current_len == total_size {
+        return Ok(());
+    }
+    
+    if let Err(e) = file.set_len(total_size).await {
+        // Give back whatever part of the allocation succeeded
+        let _ = file.set_len(current_len).await;
+        return Err(format!("Failed to reserve {} on disk: {}", crate::utils::format_file_size(total_size), e));
+    }
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(metadata) = file.metadata().await {
+            if metadata.blocks() * 512 < total_size {
+                log::debug!("[Rust] File system created a sparse file of {} bytes", total_size);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Download one file over several ranged connections, writing every segment into a single preallocated file.
+/// Synced segment progress is mirrored into `PartialDownloadInfo` so an interrupted download resumes per segment.
+async fn perform_segmented_download(
+    engine: DownloadEngine,
+    download_id: String,
+    mirrors: Arc<crate::mirrors::MirrorSet>,
+    file_path: String,
+    total_size: u64,
+    cancellation_token: Arc<AtomicBool>,
+) -> Result<(), String> {
+    use std::sync::atomic::{AtomicU64, AtomicUsize};
+    
+    // Reuse the persisted plan only if the preallocated file is still intact
+    let (existing_plan, downloaded_size) = {
+        let manager = engine.lock()?;
+        let plan = manager.partial_downloads.get(&download_id)
+            .filter(|p| p.total_size == total_size && !p.segments.is_empty())
+            .map(|p| p.segments.clone());
+        let downloaded_size = manager.downloads.get(&download_id).map(|d| d.downloaded_size).unwrap_or(0);
+        (plan, downloaded_size)
+    };
+    let existing_len = tokio::fs::metadata(&file_path).await.map(|m| m.len()).ok();
+    
+    let segments = match existing_plan {
+        Some(segments) if existing_len == Some(total_size) => {
+            let done: u64 = segments.iter().map(|s| s.downloaded).sum();
+            log::info!("[Rust] Resuming segmented download for ID {}: {} segments, {}/{} bytes", download_id, segments.len(), done, total_size);
+            segments
+        }
+        plan => {
+            // Without a plan, the bytes on disk came from a single-stream attempt and run from the start of the file
+            let on_disk = if plan.is_none() { downloaded_size.min(existing_len.unwrap_or(0)) } else { 0 };
+            let mut segments = plan_segments(total_size, segment_count_for_size(total_size, engine.download_segments()));
+            for segment in &mut segments {
+                segment.downloaded = on_disk.saturating_sub(segment.start).min(segment.len());
+            }
+            log::info!("[Rust] Starting segmented download for ID {}: {} segments over {} bytes, {} already on disk", download_id, segments.len(), total_size, on_disk);
+            
+            // Never truncated: the bytes counted above stay where they are
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&file_path)
+                .await
+                .map_err(|e| format!("Failed to create file: {}", e))?;
+            file.set_len(total_size).await
+                .map_err(|e| format!("Failed to preallocate file: {}", e))?;
+            segments
+        }
+    };
+    
+    // Record the plan before any bytes are written
+    {
+        let mut manager = engine.lock()?;
+        let done: u64 = segments.iter().map(|s| s.downloaded).sum();
+        manager.update_download_progress(&download_id, done, total_size, 0);
+        if let Some(partial) = manager.partial_downloads.get_mut(&download_id) {
+            partial.segments = segments.clone();
+        }
+    }
+    
+    let clock = engine.clock();
+    
+    let progress: Arc<Vec<AtomicU64>> = Arc::new(segments.iter().map(|s| AtomicU64::new(s.downloaded)).collect());
+    // Bytes of each segment known to be on disk, which is what a resume may rely on
+    let durable: Arc<Vec<AtomicU64>> = Arc::new(segments.iter().map(|s| AtomicU64::new(s.downloaded)).collect());
+    let abort_flag = Arc::new(AtomicBool::new(false));
+    let pending: Vec<usize> = segments.iter().enumerate()
+        .filter(|(_, segment)| !segment.is_complete())
+        .map(|(slot, _)| slot)
+        .collect();
+    let remaining_workers = Arc::new(AtomicUsize::new(pending.len()));
+    // All segments share one allowance so splitting a download doesn't multiply its share
+    let transfer = engine.register_transfer(&download_id);
+    
+    let mut handles = Vec::new();
+    for slot in pending {
+        let engine = engine.clone();
+        let mirrors = mirrors.clone();
+        let file_path = file_path.clone();
+        let segment = segments[slot].clone();
+        let progress = progress.clone();
+        let durable = durable.clone();
+        let cancellation_token = cancellation_token.clone();
+        let abort_flag = abort_flag.clone();
+        let remaining_workers = remaining_workers.clone();
+        let download_id = download_id.clone();
+        let transfer = transfer.clone();
+        
+        handles.push(tauri::async_runtime::spawn(async move {
+            let result = download_segment(&engine, &mirrors, &file_path, &segment, &progress[slot], &durable[slot], &cancellation_token, &abort_flag, &transfer).await;
+            if let Err(ref e) = result {
+                log::error!("[Rust] Segment {} failed for ID {}: {}", segment.index, download_id, e);
+                abort_flag.store(true, Ordering::Relaxed);
+            }
+            remaining_workers.fetch_sub(1, Ordering::Relaxed);
+            result
+        }));
+    }
+    
+    // Publish aggregate progress while the workers run
+    let mut last_update = clock.now();
+    let mut last_downloaded: u64 = progress.iter().map(|p| p.load(Ordering::Relaxed)).sum();
+    let mut throughput_window = crate::mirrors::ThroughputWindow::starting_at(last_update);
+    loop {
+        let finished = remaining_workers.load(Ordering::Relaxed) == 0;
+        let downloaded: u64 = progress.iter().map(|p| p.load(Ordering::Relaxed)).sum();
+        let now = clock.now();
+        
+        // Segments notice the new mirror on their next chunk and reconnect there
+        if let Some(window_speed) = throughput_window.add(downloaded.saturating_sub(last_downloaded), now) {
+            let current_url = mirrors.current();
+            if !finished && mirrors.report_throughput(&current_url, window_speed) {
+                let reason = format!("throughput dropped to {}/s", crate::utils::format_file_size(window_speed as u64));
+                mirrors.fail_over(&current_url, true, &reason).await;
+            }
+        }
+        
+        let elapsed_secs = now.duration_since(last_update).as_secs_f64();
+        let speed = if elapsed_secs > 0.0 && downloaded >= last_downloaded {
+            ((downloaded - last_downloaded) as f64 / elapsed_secs) as u64
+        } else {
+            0
+        };
+        
+        {
+            let mut manager = engine.lock().unwrap();
+            if let Some(partial) = manager.partial_downloads.get_mut(&download_id) {
+                for (segment, done) in partial.segments.iter_mut().zip(durable.iter()) {
+                    segment.downloaded = done.load(Ordering::Relaxed);
+                }
+            }
+            manager.update_download_progress(&download_id, downloaded, total_size, speed);
+        }
+        
+        last_update = now;
+        last_downloaded = downloaded;
+        
+        if finished {
+            break;
+        }
+        clock.sleep(std::time::Duration::from_millis(500)).await;
+    }
+    
+    let mut first_error = None;
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => { first_error.get_or_insert(e); }
+            Err(e) => { first_error.get_or_insert_with(|| format!("Segment task failed: {}", e)); }
+        }
+    }
+    
+    if cancellation_token.load(Ordering::Relaxed) {
+        let mut manager = engine.lock()?;
+        let is_user_paused = manager.downloads.get(&download_id)
+            .map(|d| d.user_paused)
+            .unwrap_or(false);
+        
+        // Persist segment progress so the next resume picks up where each connection stopped
+        if let Err(e) = manager.save_state() {
+            log::error!("[Rust] Failed to save segment progress for ID {}: {}", download_id, e);
+        }
+        
+        if is_user_paused {
+            log::info!("[Rust] Segmented download paused by user for ID: {}", download_id);
+            return Ok(());
+        }
+        log::info!("[Rust] Segmented download cancellation detected for ID: {}", download_id);
+        return Err("Download was cancelled".to_string());
+    }
+    
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    
+    let downloaded: u64 = progress.iter().map(|p| p.load(Ordering::Relaxed)).sum();
+    if downloaded != total_size {
+        return Err(format!("Segmented download incomplete: expected {} bytes, got {} bytes", total_size, downloaded));
+    }
+    
+    let file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(&file_path)
+        .await
+        .map_err(|e| format!("Failed to open file for sync: {}", e))?;
+    file.sync_all().await
+        .map_err(|e| format!("Failed to sync file: {}", e))?;
+    
+    {
+        let mut manager = engine.lock().unwrap();
+        manager.set_download_status(&download_id, DownloadStatus::Completed, None);
+        log::info!("[Rust] Segmented download completed for ID: {}", download_id);
+        let _ = manager.save_state();
+    }
+    
+    Ok(())
+}
+
+/// Sync a segment's writes and record everything written so far as durable
+async fn sync_segment(
+    file: &tokio::fs::File,
+    segment: &DownloadSegment,
+    progress: &std::sync::atomic::AtomicU64,
+    durable: &std::sync::atomic::AtomicU64,
+) -> Result<(), String> {
+    let written = progress.load(Ordering::Relaxed);
+    file.sync_data().await
+        .map_err(|e| format!("Failed to sync segment {}: {}", segment.index, e))?;
+    durable.store(written, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Fetch a single segment with its own retry loop, writing at the segment's offset in the shared file.
+/// Writes are synced every `SEGMENT_SYNC_BYTES` and whenever the connection ends.
+#[allow(clippy::too_many_arguments)]
+async fn download_segment(
+    engine: &DownloadEngine,
+    mirrors: &crate::mirrors::MirrorSet,
+    file_path: &str,
+    segment: &DownloadSegment,
+    progress: &std::sync::atomic::AtomicU64,
+    durable: &std::sync::atomic::AtomicU64,
+    cancellation_token: &AtomicBool,
+    abort_flag: &AtomicBool,
+    transfer: &crate::bandwidth::TransferHandle,
+) -> Result<(), String> {
+    let mut consecutive_errors = 0u32;
+    
+    loop {
+        if cancellation_token.load(Ordering::Relaxed) || abort_flag.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        
+        let done = progress.load(Ordering::Relaxed);
+        if done >= segment.len() {
+            return Ok(());
+        }
+        
+        let offset = segment.start + done;
+        let range_header = format!("bytes={}-{}", offset, segment.end);
+        let url = mirrors.current();
+        log::debug!("[Rust] Segment {} requesting {}", segment.index, range_header);
+        
+        let mut request_headers = vec![("Range", range_header)];
+        let if_range = mirrors.if_range();
+        if let Some(validator) = &if_range {
+            request_headers.push(("If-Range", validator.clone()));
+        }
+        
+        let custom_headers = engine.request_headers(mirrors.download_id(), &url);
+        let attempt_error = match engine.transport().get(&url, &header_list(&custom_headers, request_headers)).await {
+            Ok(mut response) => {
+                if response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                    if if_range.is_some() {
+                        return Err(format!("{}: full response to segment {} range request", REMOTE_FILE_CHANGED, segment.index));
+                    }
+                    // A full response would overwrite other segments, so this server cannot be split
+                    return Err(format!("Server ignored range request for segment {} (status {})", segment.index, response.status()));
+                }
+                
+                if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                    Some(format!("HTTP error {} for segment {}", response.status(), segment.index))
+                } else {
+                    mirrors.report_success(&url);
+                    let mut file = tokio::fs::OpenOptions::new()
+                        .write(true)
+                        .open(file_path)
+                        .await
+                        .map_err(|e| format!("Failed to open file for segment {}: {}", segment.index, e))?;
+                    file.seek(std::io::SeekFrom::Start(offset)).await
+                        .map_err(|e| format!("Failed to seek to segment {} offset: {}", segment.index, e))?;
+                    
+                    // Ends with `None` when the segment is done or stopped; the outer loop sorts out which
+                    let mut unsynced = 0u64;
+                    let chunk_error = loop {
+                        if cancellation_token.load(Ordering::Relaxed) || abort_flag.load(Ordering::Relaxed) {
+                            break None;
+                        }
+                        
+                        // The download moved to another mirror; reconnect there from the current offset
+                        if mirrors.current() != url {
+                            break None;
+                        }
+                        
+                        match response.chunk().await {
+                            Ok(Some(chunk)) => {
+                                let remaining = segment.len() - progress.load(Ordering::Relaxed);
+                                let data = &chunk[..chunk.len().min(remaining as usize)];
+                                
+                                file.write_all(data).await
+                                    .map_err(|e| format!("Failed to write segment {}: {}", segment.index, e))?;
+                                
+                                progress.fetch_add(data.len() as u64, Ordering::Relaxed);
+                                consecutive_errors = 0;
+                                
+                                if progress.load(Ordering::Relaxed) >= segment.len() {
+                                    break None;
+                                }
+                                
+                                unsynced += data.len() as u64;
+                                if unsynced >= SEGMENT_SYNC_BYTES {
+                                    sync_segment(&file, segment, progress, durable).await?;
+                                    unsynced = 0;
+                                }
+                                
+                                // Wait for this chunk's share of the global speed limit
+                                transfer.acquire(data.len()).await;
+                            }
+                            Ok(None) => {
+                                break Some(format!("Connection closed early for segment {}", segment.index));
+                            }
+                            Err(e) => {
+                                break Some(format!("Chunk read error for segment {}: {}", segment.index, e));
+                            }
+                        }
+                    };
+                    
+                    sync_segment(&file, segment, progress, durable).await?;
+                    chunk_error
+                }
+            }
+            Err(e) => Some(format!("Request error for segment {}: {}", segment.index, e)),
+        };
+        
+        if let Some(e) = attempt_error {
+            consecutive_errors += 1;
+            
+            // Try another mirror before spending the remaining retries on this one.
+            // The preallocated file already fixes the size, so the new mirror is always validated.
+            if mirrors.report_error(&url) && mirrors.fail_over(&url, true, &e).await.is_some() {
+                consecutive_errors = 0;
+                continue;
+            }
+            
+            if consecutive_errors >= MAX_SEGMENT_CONSECUTIVE_ERRORS {
+                return Err(format!("Segment {} failed after {} consecutive errors: {}", segment.index, consecutive_errors, e));
+            }
+            
+            let delay = std::time::Duration::from_millis((1000 * 2u64.pow(consecutive_errors - 1)).min(30000));
+            log::warn!("[Rust] {} - retrying in {:.1}s (attempt {}/{})", e, delay.as_secs_f32(), consecutive_errors, MAX_SEGMENT_CONSECUTIVE_ERRORS);
+            engine.clock().sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::fake::{FakeTransport, ManualClock};
+    use std::time::Duration;
+
+    /// Engine on a fake server and a manual clock, in a fresh directory that holds both its state and the files
+    fn test_engine(max_downloads: u32) -> (DownloadEngine, Arc<FakeTransport>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("yuukips-download-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let transport = Arc::new(FakeTransport::default());
+        let engine = DownloadEngine::with_transport(dir.clone(), transport.clone(), Arc::new(ManualClock::default()));
+        engine.settings().lock().unwrap().max_simultaneous_downloads = max_downloads;
+        (engine, transport, dir)
+    }
+
+    fn file_body(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    async fn start(engine: &DownloadEngine, url: &str, path: &Path) -> String {
+        engine.start_download(url.to_string(), path.to_string_lossy().to_string(), None, DownloadOptions::default())
+            .await
+            .unwrap()
+    }
+
+    fn status(engine: &DownloadEngine, id: &str) -> DownloadStatus {
+        engine.download(id).unwrap().unwrap().status
+    }
+
+    async fn wait_for_status(engine: &DownloadEngine, id: &str, expected: DownloadStatus) {
+        for _ in 0..2500 {
+            if status(engine, id) == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        panic!("download {} stayed {:?}, expected {:?}", id, status(engine, id), expected);
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_bytes_on_disk_after_a_broken_connection() {
+        let (engine, transport, dir) = test_engine(3);
+        let url = "https://fake.test/game.zip";
+        let body = file_body(200_000);
+        transport.serve(url, body.clone());
+        transport.fail_once_after(url, 100_000);
+
+        let path = dir.join("files").join("game.zip");
+        let id = start(&engine, url, &path).await;
+        wait_for_status(&engine, &id, DownloadStatus::Completed).await;
+
+        assert_eq!(transport.ranges(url), vec![None, Some("bytes=100000-".to_string())]);
+        assert_eq!(fs::read(&path).unwrap(), body);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_single_stream_partial_when_resuming_over_several_segments() {
+        let (engine, transport, dir) = test_engine(3);
+        engine.settings().lock().unwrap().download_segments = 1;
+        let url = "https://fake.test/large.pak";
+        let body = file_body(SEGMENTED_MIN_FILE_SIZE as usize);
+        transport.serve(url, body.clone());
+        transport.hold(url);
+
+        // Pause the single-stream download before it receives anything, and let its task end
+        let path = dir.join("large.pak");
+        let id = start(&engine, url, &path).await;
+        engine.pause_download(id.clone()).unwrap();
+        transport.release(url);
+        for _ in 0..2500 {
+            if !engine.inner.active_requests.lock().unwrap().contains(&id) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        // Bytes from an earlier single-stream attempt, then more connections in the settings
+        let partial = 5 * 1024 * 1024;
+        fs::write(&path, &body[..partial]).unwrap();
+        engine.lock().unwrap().update_download_progress(&id, partial as u64, body.len() as u64, 0);
+        engine.settings().lock().unwrap().download_segments = 4;
+
+        engine.resume_download(id.clone()).await.unwrap();
+        wait_for_status(&engine, &id, DownloadStatus::Completed).await;
+
+        let ranges = transport.ranges(url);
+        assert!(ranges.contains(&Some(format!("bytes={}-{}", partial, MIN_SEGMENT_SIZE - 1))));
+        assert!(!ranges.iter().flatten().any(|range| range.starts_with("bytes=0-")));
+        assert!(fs::read(&path).unwrap() == body);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn queued_downloads_start_by_priority_then_queue_order() {
+        let (engine, transport, dir) = test_engine(1);
+        let urls = ["https://fake.test/a.bin", "https://fake.test/b.bin", "https://fake.test/c.bin"];
+        for url in urls {
+            transport.serve(url, file_body(64 * 1024));
+            transport.hold(url);
+        }
+
+        let mut ids = Vec::new();
+        for (index, url) in urls.iter().enumerate() {
+            ids.push(start(&engine, url, &dir.join(format!("{}.bin", index))).await);
+        }
+        let (a, b, c) = (&ids[0], &ids[1], &ids[2]);
+        assert_eq!(status(&engine, a), DownloadStatus::Downloading);
+        assert_eq!(status(&engine, b), DownloadStatus::Queued);
+        assert_eq!(status(&engine, c), DownloadStatus::Queued);
+
+        // The later download jumps the queue once it has a higher priority
+        engine.lock().unwrap().set_priority(c, DownloadPriority::High).unwrap();
+
+        transport.release(urls[0]);
+        wait_for_status(&engine, a, DownloadStatus::Completed).await;
+        wait_for_status(&engine, c, DownloadStatus::Downloading).await;
+        assert_eq!(status(&engine, b), DownloadStatus::Queued);
+
+        transport.release(urls[2]);
+        wait_for_status(&engine, c, DownloadStatus::Completed).await;
+        wait_for_status(&engine, b, DownloadStatus::Downloading).await;
+
+        transport.release(urls[1]);
+        wait_for_status(&engine, b, DownloadStatus::Completed).await;
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn a_read_only_engine_sees_journaled_progress_without_writing() {
+        let (engine, transport, dir) = test_engine(3);
+        let url = "https://fake.test/shared.bin";
+        transport.serve(url, file_body(64 * 1024));
+        transport.hold(url);
+        let id = start(&engine, url, &dir.join("shared.bin")).await;
+        engine.save_state().unwrap();
+        // Only in the journal until the next snapshot
+        engine.pause_download(id.clone()).unwrap();
+
+        let state_path = dir.join("download_state.json");
+        let journal_path = dir.join("download_state.journal");
+        let state = fs::read(&state_path).unwrap();
+        let journal = fs::read(&journal_path).unwrap();
+
+        let reader = DownloadEngine::read_only(dir.clone());
+        assert_eq!(reader.download(&id).unwrap().unwrap().status, DownloadStatus::Paused);
+        assert_eq!(fs::read(&state_path).unwrap(), state);
+        assert_eq!(fs::read(&journal_path).unwrap(), journal);
+
+        transport.release(url);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_one_process_at_a_time_holds_the_data_dir() {
+        let dir = std::env::temp_dir().join(format!("yuukips-lock-test-{}", Uuid::new_v4()));
+        let lock = DownloadEngine::lock_data_dir(&dir).unwrap();
+        assert!(DownloadEngine::lock_data_dir(&dir).is_err());
+        drop(lock);
+        assert!(DownloadEngine::lock_data_dir(&dir).is_ok());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn credentials_stay_with_the_primary_host_and_the_state_file() {
+        let (engine, transport, dir) = test_engine(3);
+        let url = "https://fake.test/private.bin";
+        transport.serve(url, file_body(64 * 1024));
+        transport.hold(url);
+
+        let options = DownloadOptions {
+            auth: Some(DownloadAuth::Bearer { token: "secret-token".to_string() }),
+            mirrors: vec!["https://mirror.test/private.bin".to_string()],
+            ..Default::default()
+        };
+        let path = dir.join("private.bin");
+        let id = engine.start_download(url.to_string(), path.to_string_lossy().to_string(), None, options).await.unwrap();
+
+        let has_authorization = |headers: Vec<(String, String)>| headers.iter().any(|(name, _)| name == "Authorization");
+        assert!(has_authorization(engine.request_headers(&id, url)));
+        assert!(!has_authorization(engine.request_headers(&id, "https://mirror.test/private.bin")));
+
+        let json = serde_json::to_string(&engine.download(&id).unwrap().unwrap()).unwrap();
+        assert!(!json.contains("secret-token"));
+
+        engine.save_state().unwrap();
+        let reloaded = DownloadEngine::with_transport(dir.clone(), transport.clone(), Arc::new(ManualClock::default()));
+        assert!(reloaded.download(&id).unwrap().unwrap().auth.is_some());
+
+        transport.release(url);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,1001 @@
+//! Archive extraction module
+//! Unpacks split-volume zip (`.zip` + `.z01…`) and 7z (`.7z` / `.7z.001…`) archives into a game folder
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Emitter, Manager};
+
+// Global cancel flag for the running extraction
+static EXTRACT_CANCEL_FLAG: once_cell::sync::Lazy<Arc<AtomicBool>> =
+    once_cell::sync::Lazy::new(|| Arc::new(AtomicBool::new(false)));
+
+/// Resume journal written into the target folder while extracting
+const EXTRACT_STATE_FILE: &str = ".yuukips_extract.json";
+/// Suffix of the temp file an entry is written to before being renamed into place
+const EXTRACT_TEMP_SUFFIX: &str = ".extracting";
+const EXTRACT_BUFFER_SIZE: usize = 1024 * 1024;
+const EXTRACT_STATE_SAVE_INTERVAL_MS: u128 = 2000;
+
+const ZIP_LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const ZIP_EOCD_SIG: u32 = 0x0605_4b50;
+const ZIP64_EOCD_SIG: u32 = 0x0606_4b50;
+const ZIP64_EOCD_LOCATOR_SIG: u32 = 0x0706_4b50;
+
+#[derive(Serialize, Clone)]
+pub struct ExtractProgress {
+    pub archive_path: String,
+    pub current_entry: String,
+    pub entries_done: usize,
+    pub total_entries: usize,
+    pub progress: f64,
+    pub bytes_processed: u64,
+    pub total_bytes: u64,
+    pub speed_mbps: f64,
+}
+
+/// Entries already extracted and verified for a given archive
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct ExtractState {
+    archive: String,
+    archive_size: u64,
+    completed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArchiveKind {
+    Zip,
+    SevenZip,
+}
+
+/// Presents an ordered list of volume files as one continuous stream
+struct MultiVolumeReader {
+    volumes: Vec<(fs::File, u64)>,
+    starts: Vec<u64>,
+    total_len: u64,
+    position: u64,
+}
+
+impl MultiVolumeReader {
+    fn open(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut volumes = Vec::with_capacity(paths.len());
+        let mut starts = Vec::with_capacity(paths.len());
+        let mut total_len = 0u64;
+        for path in paths {
+            let file = fs::File::open(path)
+                .map_err(|e| format!("Failed to open archive volume {}: {}", path.display(), e))?;
+            let len = file.metadata()
+                .map_err(|e| format!("Failed to read archive volume metadata {}: {}", path.display(), e))?
+                .len();
+            starts.push(total_len);
+            total_len += len;
+            volumes.push((file, len));
+        }
+        Ok(Self { volumes, starts, total_len, position: 0 })
+    }
+
+    /// Absolute offset of the first byte of a volume (zip "disk number")
+    fn volume_start(&self, disk: u32) -> Result<u64, String> {
+        self.starts.get(disk as usize).copied()
+            .ok_or_else(|| format!("Archive references missing volume {}", disk + 1))
+    }
+}
+
+impl Read for MultiVolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.total_len {
+            return Ok(0);
+        }
+        // Last volume whose start is at or before the current position
+        let index = self.starts.partition_point(|&start| start <= self.position) - 1;
+        let (file, len) = &mut self.volumes[index];
+        let offset = self.position - self.starts[index];
+        let available = (*len - offset).min(buf.len() as u64) as usize;
+        if available == 0 {
+            return Ok(0);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let read = file.read(&mut buf[..available])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for MultiVolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.total_len as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+        };
+        if target < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Seek before start of archive"));
+        }
+        self.position = target as u64;
+        Ok(self.position)
+    }
+}
+
+/// Central directory entry of a zip archive
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    name: String,
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    disk_start: u32,
+    local_header_offset: u64,
+}
+
+impl ZipEntry {
+    fn is_directory(&self) -> bool {
+        self.name.ends_with('/') || self.name.ends_with('\\')
+    }
+}
+
+/// Shared bookkeeping for progress, cancellation and the resume journal
+struct ExtractContext {
+    app_handle: Option<tauri::AppHandle>, // progress events are skipped without one
+    cancel_flag: Arc<AtomicBool>,
+    archive_path: String,
+    target_folder: PathBuf,
+    state: ExtractState,
+    completed: HashSet<String>,
+    total_entries: usize,
+    total_bytes: u64,
+    bytes_processed: u64,
+    start_time: Instant,
+    last_progress_time: Instant,
+    last_state_save: Instant,
+}
+
+impl ExtractContext {
+    fn check_cancelled(&self) -> Result<(), String> {
+        if self.cancel_flag.load(Ordering::Relaxed) {
+            return Err("Extraction cancelled by user".to_string());
+        }
+        Ok(())
+    }
+
+    fn add_progress(&mut self, bytes: u64, current_entry: &str) {
+        self.bytes_processed += bytes;
+        if self.last_progress_time.elapsed().as_millis() >= 1000 {
+            self.emit_progress(current_entry);
+        }
+    }
+
+    fn emit_progress(&mut self, current_entry: &str) {
+        let now = Instant::now();
+        let progress = if self.total_bytes > 0 {
+            (self.bytes_processed as f64 / self.total_bytes as f64) * 100.0
+        } else {
+            100.0
+        };
+        let elapsed_secs = now.duration_since(self.start_time).as_secs_f64();
+        let speed_mbps = if elapsed_secs > 0.0 {
+            (self.bytes_processed as f64 / (1024.0 * 1024.0)) / elapsed_secs
+        } else {
+            0.0
+        };
+
+        let progress_data = ExtractProgress {
+            archive_path: self.archive_path.clone(),
+            current_entry: current_entry.to_string(),
+            entries_done: self.completed.len(),
+            total_entries: self.total_entries,
+            progress,
+            bytes_processed: self.bytes_processed,
+            total_bytes: self.total_bytes,
+            speed_mbps,
+        };
+        if let Some(app_handle) = &self.app_handle {
+            if let Err(e) = app_handle.emit("extract-progress", &progress_data) {
+                log::warn!("[Extract] Failed to emit progress event: {}", e);
+            }
+        }
+        self.last_progress_time = now;
+    }
+
+    fn is_completed(&self, name: &str, output_path: &Path) -> bool {
+        self.completed.contains(name) && output_path.exists()
+    }
+
+    fn mark_completed(&mut self, name: &str) {
+        if self.completed.insert(name.to_string()) {
+            self.state.completed.push(name.to_string());
+        }
+        if self.last_state_save.elapsed().as_millis() >= EXTRACT_STATE_SAVE_INTERVAL_MS {
+            self.save_state();
+        }
+    }
+
+    fn save_state(&mut self) {
+        if let Err(e) = save_extract_state(&self.target_folder, &self.state) {
+            log::warn!("[Extract] Failed to save extraction state: {}", e);
+        }
+        self.last_state_save = Instant::now();
+    }
+}
+
+/// Extract a zip or 7z archive (including split volumes) into `target_folder`
+#[command]
+pub async fn extract_archive(
+    archive_path: String,
+    target_folder: String,
+    window: tauri::Window,
+) -> Result<String, String> {
+    // Reset cancel flag at start
+    EXTRACT_CANCEL_FLAG.store(false, Ordering::Relaxed);
+    let cancel_flag = EXTRACT_CANCEL_FLAG.clone();
+
+    log::info!("[Extract] Extracting {} into {}", archive_path, target_folder);
+
+    let result = tokio::task::spawn_blocking({
+        let app_handle = window.app_handle().clone();
+        let archive_path = archive_path.clone();
+        move || extract_archive_blocking(Path::new(&archive_path), Path::new(&target_folder), Some(app_handle), cancel_flag)
+    })
+    .await
+    .map_err(|e| format!("Extraction task failed: {}", e))
+    .and_then(|result| result);
+
+    match result {
+        Ok(entries) => {
+            let completion_data = serde_json::json!({
+                "archivePath": archive_path,
+                "entries": entries
+            });
+            if let Err(e) = window.emit("extract-complete", completion_data) {
+                log::warn!("[Extract] Failed to emit completion event: {}", e);
+            }
+            Ok(format!("Extracted {} entries", entries))
+        }
+        Err(e) => {
+            log::error!("[Extract] Extraction failed for {}: {}", archive_path, e);
+            let error_data = serde_json::json!({
+                "archivePath": archive_path,
+                "error": e.clone()
+            });
+            if let Err(emit_err) = window.emit("extract-error", error_data) {
+                log::warn!("[Extract] Failed to emit error event: {}", emit_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[command]
+pub fn cancel_extraction() -> Result<String, String> {
+    log::info!("[Extract] Cancel extraction requested");
+    EXTRACT_CANCEL_FLAG.store(true, Ordering::Relaxed);
+    Ok("Extraction cancellation requested".to_string())
+}
+
+/// List the volume files that make up an archive, in stream order
+#[command]
+pub fn get_archive_volumes(archive_path: String) -> Result<Vec<String>, String> {
+    let (_, volumes) = resolve_archive_volumes(Path::new(&archive_path))?;
+    Ok(volumes.iter().map(|p| p.to_string_lossy().to_string()).collect())
+}
+
+/// Total size of an archive's entries once unpacked, from the zip central directory or the 7z header
+pub fn archive_unpacked_size(archive_path: &Path) -> Result<u64, String> {
+    let (kind, volumes) = resolve_archive_volumes(archive_path)?;
+    let mut reader = MultiVolumeReader::open(&volumes)?;
+    match kind {
+        ArchiveKind::Zip => Ok(read_zip_central_directory(&mut reader)?.iter().map(|e| e.uncompressed_size).sum()),
+        ArchiveKind::SevenZip => {
+            let total_len = reader.total_len;
+            let archive = sevenz_rust::SevenZReader::new(reader, total_len, sevenz_rust::Password::empty())
+                .map_err(|e| format!("Failed to open 7z archive: {}", e))?;
+            Ok(archive.archive().files.iter().filter(|e| e.has_stream()).map(|e| e.size()).sum())
+        }
+    }
+}
+
+/// Blocking extraction worker, returns the number of entries in the archive
+pub fn extract_archive_blocking(
+    archive_path: &Path,
+    target_folder: &Path,
+    app_handle: Option<tauri::AppHandle>,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<usize, String> {
+    let (kind, volumes) = resolve_archive_volumes(archive_path)?;
+    log::info!("[Extract] Archive has {} volume(s)", volumes.len());
+
+    fs::create_dir_all(target_folder)
+        .map_err(|e| format!("Failed to create target folder: {}", e))?;
+
+    let mut reader = MultiVolumeReader::open(&volumes)?;
+    let archive_id = volumes.last()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Only trust a previous journal if it was written for the same volume set
+    let state = match load_extract_state(target_folder) {
+        Some(state) if state.archive == archive_id && state.archive_size == reader.total_len => {
+            log::info!("[Extract] Resuming extraction, {} entries already done", state.completed.len());
+            state
+        }
+        _ => ExtractState {
+            archive: archive_id,
+            archive_size: reader.total_len,
+            completed: Vec::new(),
+        },
+    };
+
+    let now = Instant::now();
+    let mut ctx = ExtractContext {
+        app_handle,
+        cancel_flag,
+        archive_path: archive_path.to_string_lossy().to_string(),
+        target_folder: target_folder.to_path_buf(),
+        completed: state.completed.iter().cloned().collect(),
+        state,
+        total_entries: 0,
+        total_bytes: 0,
+        bytes_processed: 0,
+        start_time: now,
+        last_progress_time: now,
+        last_state_save: now,
+    };
+
+    let result = match kind {
+        ArchiveKind::Zip => extract_zip(&mut reader, &mut ctx),
+        ArchiveKind::SevenZip => extract_7z(reader, &mut ctx),
+    };
+
+    match result {
+        Ok(()) => {
+            ctx.emit_progress("");
+            remove_extract_state(target_folder);
+            log::info!("[Extract] Extraction completed: {} entries", ctx.total_entries);
+            Ok(ctx.total_entries)
+        }
+        Err(e) => {
+            // Keep what has been verified so far for the next attempt
+            ctx.save_state();
+            Err(e)
+        }
+    }
+}
+
+/// Whether a file name is one of the archive volumes `extract_archive` understands
+pub fn is_archive_volume_name(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    lower.contains(".7z.") || lower.ends_with(".7z") || lower.ends_with(".zip")
+        || (lower.len() > 4 && lower[lower.len() - 4..].starts_with(".z")
+            && lower[lower.len() - 2..].chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Work out the archive type and the ordered list of its volumes from any one volume path
+fn resolve_archive_volumes(archive_path: &Path) -> Result<(ArchiveKind, Vec<PathBuf>), String> {
+    let parent = archive_path.parent().unwrap_or_else(|| Path::new("."));
+    let file_name = archive_path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Invalid archive path".to_string())?;
+    let lower = file_name.to_lowercase();
+
+    // name.7z.001, name.7z.002, ...
+    if let Some(pos) = lower.rfind(".7z.") {
+        let base = &file_name[..pos + 3];
+        let mut volumes = Vec::new();
+        for index in 1.. {
+            let path = parent.join(format!("{}.{:03}", base, index));
+            if !path.exists() {
+                break;
+            }
+            volumes.push(path);
+        }
+        if volumes.is_empty() {
+            return Err(format!("First volume {}.001 not found", base));
+        }
+        return Ok((ArchiveKind::SevenZip, volumes));
+    }
+
+    if lower.ends_with(".7z") {
+        if !archive_path.exists() {
+            return Err(format!("Archive not found: {}", archive_path.display()));
+        }
+        return Ok((ArchiveKind::SevenZip, vec![archive_path.to_path_buf()]));
+    }
+
+    // name.z01 … name.zNN followed by name.zip (which holds the central directory)
+    let stem = if lower.ends_with(".zip") {
+        &file_name[..file_name.len() - 4]
+    } else if lower.len() > 4 && lower[lower.len() - 4..].starts_with(".z")
+        && lower[lower.len() - 2..].chars().all(|c| c.is_ascii_digit()) {
+        &file_name[..file_name.len() - 4]
+    } else {
+        return Err(format!("Unsupported archive type: {}", file_name));
+    };
+
+    let main_volume = parent.join(format!("{}.zip", stem));
+    if !main_volume.exists() {
+        return Err(format!("Archive not found: {}", main_volume.display()));
+    }
+    let mut volumes = Vec::new();
+    for index in 1.. {
+        let path = parent.join(format!("{}.z{:02}", stem, index));
+        if !path.exists() {
+            break;
+        }
+        volumes.push(path);
+    }
+    volumes.push(main_volume);
+    Ok((ArchiveKind::Zip, volumes))
+}
+
+/// Join an archive entry name onto the target folder, rejecting absolute paths and `..`
+fn safe_output_path(target_folder: &Path, name: &str) -> Result<PathBuf, String> {
+    let normalized = name.replace('\\', "/");
+    let relative = Path::new(normalized.trim_end_matches('/'));
+    let mut output = target_folder.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => output.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("Refusing to extract unsafe path: {}", name)),
+        }
+    }
+    if output == target_folder {
+        return Err(format!("Refusing to extract entry with empty name: {}", name));
+    }
+    Ok(output)
+}
+
+fn temp_output_path(output_path: &Path) -> PathBuf {
+    let mut temp = output_path.as_os_str().to_owned();
+    temp.push(EXTRACT_TEMP_SUFFIX);
+    PathBuf::from(temp)
+}
+
+fn extract_zip(reader: &mut MultiVolumeReader, ctx: &mut ExtractContext) -> Result<(), String> {
+    let entries = read_zip_central_directory(reader)?;
+    ctx.total_entries = entries.len();
+    ctx.total_bytes = entries.iter().map(|e| e.uncompressed_size).sum();
+    log::info!("[Extract] Zip archive contains {} entries ({} bytes)", ctx.total_entries, ctx.total_bytes);
+
+    let mut buffer = vec![0u8; EXTRACT_BUFFER_SIZE];
+    for entry in &entries {
+        ctx.check_cancelled()?;
+        let output_path = safe_output_path(&ctx.target_folder, &entry.name)?;
+
+        if entry.is_directory() {
+            fs::create_dir_all(&output_path)
+                .map_err(|e| format!("Failed to create directory {}: {}", output_path.display(), e))?;
+            ctx.mark_completed(&entry.name);
+            continue;
+        }
+
+        if ctx.is_completed(&entry.name, &output_path) {
+            ctx.add_progress(entry.uncompressed_size, &entry.name);
+            continue;
+        }
+
+        if entry.flags & 0x1 != 0 {
+            return Err(format!("Encrypted zip entries are not supported: {}", entry.name));
+        }
+
+        let data_offset = zip_entry_data_offset(reader, entry)?;
+        reader.seek(SeekFrom::Start(data_offset))
+            .map_err(|e| format!("Failed to seek to entry {}: {}", entry.name, e))?;
+        let compressed = (&mut *reader).take(entry.compressed_size);
+        let mut source: Box<dyn Read + '_> = match entry.method {
+            0 => Box::new(compressed),
+            8 => Box::new(flate2::read::DeflateDecoder::new(compressed)),
+            method => return Err(format!("Unsupported compression method {} for {}", method, entry.name)),
+        };
+
+        let crc32 = write_entry(&mut *source, &output_path, &entry.name, entry.uncompressed_size, &mut buffer, ctx)?;
+        if crc32 != entry.crc32 {
+            let _ = fs::remove_file(temp_output_path(&output_path));
+            return Err(format!(
+                "CRC mismatch for {}: expected {:08x}, got {:08x}",
+                entry.name, entry.crc32, crc32
+            ));
+        }
+        finish_entry(&output_path)?;
+        ctx.mark_completed(&entry.name);
+    }
+    Ok(())
+}
+
+/// Stream one entry into its temp file, returning the CRC32 of the written data
+fn write_entry(
+    source: &mut dyn Read,
+    output_path: &Path,
+    name: &str,
+    expected_size: u64,
+    buffer: &mut [u8],
+    ctx: &mut ExtractContext,
+) -> Result<u32, String> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let temp_path = temp_output_path(output_path);
+    let mut output = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+
+    let mut hasher = crc32fast::Hasher::new();
+    let mut written = 0u64;
+    loop {
+        if let Err(e) = ctx.check_cancelled() {
+            drop(output);
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        let read = source.read(buffer)
+            .map_err(|e| format!("Failed to read entry {}: {}", name, e))?;
+        if read == 0 {
+            break;
+        }
+        output.write_all(&buffer[..read])
+            .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+        hasher.update(&buffer[..read]);
+        written += read as u64;
+        ctx.add_progress(read as u64, name);
+    }
+    output.sync_all()
+        .map_err(|e| format!("Failed to flush {}: {}", temp_path.display(), e))?;
+
+    if written != expected_size {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Size mismatch for {}: expected {} bytes, got {}", name, expected_size, written));
+    }
+    Ok(hasher.finalize())
+}
+
+/// Move a verified temp file into place
+fn finish_entry(output_path: &Path) -> Result<(), String> {
+    let temp_path = temp_output_path(output_path);
+    if output_path.exists() {
+        fs::remove_file(output_path)
+            .map_err(|e| format!("Failed to replace {}: {}", output_path.display(), e))?;
+    }
+    fs::rename(&temp_path, output_path)
+        .map_err(|e| format!("Failed to move {} into place: {}", output_path.display(), e))
+}
+
+fn extract_7z(reader: MultiVolumeReader, ctx: &mut ExtractContext) -> Result<(), String> {
+    let total_len = reader.total_len;
+    let mut archive = sevenz_rust::SevenZReader::new(reader, total_len, sevenz_rust::Password::empty())
+        .map_err(|e| format!("Failed to open 7z archive: {}", e))?;
+
+    let entries = &archive.archive().files;
+    ctx.total_entries = entries.len();
+    ctx.total_bytes = entries.iter().filter(|e| e.has_stream()).map(|e| e.size()).sum();
+    log::info!("[Extract] 7z archive contains {} entries ({} bytes)", ctx.total_entries, ctx.total_bytes);
+
+    let mut buffer = vec![0u8; EXTRACT_BUFFER_SIZE];
+    let mut failure: Option<String> = None;
+    let result = archive.for_each_entries(|entry, source| {
+        // Returning false only stops the current block, so abort with an error instead
+        extract_7z_entry(entry, source, &mut buffer, ctx)
+            .map(|_| true)
+            .map_err(|e| {
+                failure = Some(e.clone());
+                sevenz_rust::Error::other(e)
+            })
+    });
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    // Solid blocks are verified by sevenz-rust while streaming, mismatches surface here
+    result.map_err(|e| format!("Failed to extract 7z archive: {}", e))
+}
+
+fn extract_7z_entry(
+    entry: &sevenz_rust::SevenZArchiveEntry,
+    source: &mut dyn Read,
+    buffer: &mut [u8],
+    ctx: &mut ExtractContext,
+) -> Result<(), String> {
+    ctx.check_cancelled()?;
+    let name = entry.name().to_string();
+    let output_path = safe_output_path(&ctx.target_folder, &name)?;
+
+    if entry.is_directory() {
+        fs::create_dir_all(&output_path)
+            .map_err(|e| format!("Failed to create directory {}: {}", output_path.display(), e))?;
+        ctx.mark_completed(&name);
+        return Ok(());
+    }
+
+    if ctx.is_completed(&name, &output_path) {
+        // Entries in a solid block still have to be decoded to reach the next one
+        let skipped = io::copy(source, &mut io::sink())
+            .map_err(|e| format!("Failed to skip entry {}: {}", name, e))?;
+        ctx.add_progress(skipped, &name);
+        return Ok(());
+    }
+
+    let crc32 = write_entry(source, &output_path, &name, entry.size(), buffer, ctx)?;
+    if entry.has_crc && crc32 as u64 != entry.crc {
+        let _ = fs::remove_file(temp_output_path(&output_path));
+        return Err(format!("CRC mismatch for {}: expected {:08x}, got {:08x}", name, entry.crc, crc32));
+    }
+    finish_entry(&output_path)?;
+    ctx.mark_completed(&name);
+    Ok(())
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap_or([0; 4]))
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap_or([0; 8]))
+}
+
+fn read_exact_at(reader: &mut MultiVolumeReader, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0u8; len];
+    reader.seek(SeekFrom::Start(offset))
+        .and_then(|_| reader.read_exact(&mut buf))
+        .map_err(|e| format!("Failed to read archive at offset {}: {}", offset, e))?;
+    Ok(buf)
+}
+
+/// Parse the end of central directory (with ZIP64 support) and every central directory entry
+fn read_zip_central_directory(reader: &mut MultiVolumeReader) -> Result<Vec<ZipEntry>, String> {
+    // The EOCD record sits at the end of the last volume, followed by at most 64KB of comment
+    let tail_len = reader.total_len.min(22 + 65535);
+    let tail_start = reader.total_len - tail_len;
+    let tail = read_exact_at(reader, tail_start, tail_len as usize)?;
+    let eocd_pos = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(&tail, i) == ZIP_EOCD_SIG)
+        .ok_or_else(|| "Not a zip archive (end of central directory not found)".to_string())?;
+    let eocd = &tail[eocd_pos..];
+
+    let mut cd_disk = read_u16(eocd, 6) as u32;
+    let mut total_entries = read_u16(eocd, 10) as u64;
+    let mut cd_size = read_u32(eocd, 12) as u64;
+    let mut cd_offset = read_u32(eocd, 16) as u64;
+
+    let needs_zip64 = cd_disk == 0xFFFF || total_entries == 0xFFFF
+        || cd_size == 0xFFFF_FFFF || cd_offset == 0xFFFF_FFFF;
+    let eocd_abs = tail_start + eocd_pos as u64;
+    if eocd_abs >= 20 {
+        let locator = read_exact_at(reader, eocd_abs - 20, 20)?;
+        if read_u32(&locator, 0) == ZIP64_EOCD_LOCATOR_SIG {
+            let zip64_disk = read_u32(&locator, 4);
+            let zip64_offset = reader.volume_start(zip64_disk)?.checked_add(read_u64(&locator, 8))
+                .ok_or_else(|| "Invalid ZIP64 end of central directory locator".to_string())?;
+            let record = read_exact_at(reader, zip64_offset, 56)?;
+            if read_u32(&record, 0) != ZIP64_EOCD_SIG {
+                return Err("Invalid ZIP64 end of central directory record".to_string());
+            }
+            cd_disk = read_u32(&record, 20);
+            total_entries = read_u64(&record, 32);
+            cd_size = read_u64(&record, 40);
+            cd_offset = read_u64(&record, 48);
+        } else if needs_zip64 {
+            return Err("ZIP64 archive is missing its end of central directory locator".to_string());
+        }
+    }
+
+    // Sizes and counts come from the archive itself, so they are checked before anything is allocated for them
+    let cd_start = reader.volume_start(cd_disk)?.checked_add(cd_offset)
+        .filter(|start| start.checked_add(cd_size).is_some_and(|end| end <= reader.total_len))
+        .ok_or_else(|| "Corrupt archive: central directory lies outside the archive".to_string())?;
+    let cd = read_exact_at(reader, cd_start, cd_size as usize)?;
+
+    // Every entry takes at least 46 bytes of the central directory
+    let mut entries = Vec::with_capacity(total_entries.min(cd_size / 46) as usize);
+    let mut pos = 0usize;
+    while entries.len() < total_entries as usize {
+        if pos + 46 > cd.len() || read_u32(&cd, pos) != ZIP_CENTRAL_HEADER_SIG {
+            return Err(format!("Corrupt central directory at entry {}", entries.len()));
+        }
+        let flags = read_u16(&cd, pos + 8);
+        let method = read_u16(&cd, pos + 10);
+        let crc32 = read_u32(&cd, pos + 16);
+        let mut compressed_size = read_u32(&cd, pos + 20) as u64;
+        let mut uncompressed_size = read_u32(&cd, pos + 24) as u64;
+        let name_len = read_u16(&cd, pos + 28) as usize;
+        let extra_len = read_u16(&cd, pos + 30) as usize;
+        let comment_len = read_u16(&cd, pos + 32) as usize;
+        let mut disk_start = read_u16(&cd, pos + 34) as u32;
+        let mut local_header_offset = read_u32(&cd, pos + 42) as u64;
+
+        let name_start = pos + 46;
+        let extra_start = name_start + name_len;
+        let next = extra_start + extra_len + comment_len;
+        if next > cd.len() {
+            return Err(format!("Corrupt central directory at entry {}", entries.len()));
+        }
+        let name = String::from_utf8_lossy(&cd[name_start..extra_start]).to_string();
+
+        // ZIP64 extended information only carries the fields that overflowed, in this order
+        let extra = &cd[extra_start..extra_start + extra_len];
+        let mut extra_pos = 0usize;
+        while extra_pos + 4 <= extra.len() {
+            let header_id = read_u16(extra, extra_pos);
+            let data_len = read_u16(extra, extra_pos + 2) as usize;
+            let data_start = extra_pos + 4;
+            let data_end = (data_start + data_len).min(extra.len());
+            if header_id == 0x0001 {
+                let data = &extra[data_start..data_end];
+                let mut field = 0usize;
+                if uncompressed_size == 0xFFFF_FFFF && field + 8 <= data.len() {
+                    uncompressed_size = read_u64(data, field);
+                    field += 8;
+                }
+                if compressed_size == 0xFFFF_FFFF && field + 8 <= data.len() {
+                    compressed_size = read_u64(data, field);
+                    field += 8;
+                }
+                if local_header_offset == 0xFFFF_FFFF && field + 8 <= data.len() {
+                    local_header_offset = read_u64(data, field);
+                    field += 8;
+                }
+                if disk_start == 0xFFFF && field + 4 <= data.len() {
+                    disk_start = read_u32(data, field);
+                }
+            }
+            extra_pos = data_start + data_len;
+        }
+
+        entries.push(ZipEntry {
+            name,
+            method,
+            flags,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            disk_start,
+            local_header_offset,
+        });
+        pos = next;
+    }
+
+    Ok(entries)
+}
+
+/// Resolve the absolute offset of an entry's data by reading its local file header
+fn zip_entry_data_offset(reader: &mut MultiVolumeReader, entry: &ZipEntry) -> Result<u64, String> {
+    let header_offset = reader.volume_start(entry.disk_start)?.checked_add(entry.local_header_offset)
+        .ok_or_else(|| format!("Corrupt archive: local file header of {} lies outside the archive", entry.name))?;
+    let header = read_exact_at(reader, header_offset, 30)?;
+    if read_u32(&header, 0) != ZIP_LOCAL_HEADER_SIG {
+        return Err(format!("Invalid local file header for {}", entry.name));
+    }
+    let name_len = read_u16(&header, 26) as u64;
+    let extra_len = read_u16(&header, 28) as u64;
+    header_offset.checked_add(30 + name_len + extra_len)
+        .ok_or_else(|| format!("Corrupt archive: data of {} lies outside the archive", entry.name))
+}
+
+fn load_extract_state(target_folder: &Path) -> Option<ExtractState> {
+    let content = fs::read_to_string(target_folder.join(EXTRACT_STATE_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_extract_state(target_folder: &Path, state: &ExtractState) -> Result<(), String> {
+    let content = serde_json::to_string(state)
+        .map_err(|e| format!("Failed to serialize extraction state: {}", e))?;
+    crate::utils::write_file_atomic(&target_folder.join(EXTRACT_STATE_FILE), content.as_bytes())
+}
+
+fn remove_extract_state(target_folder: &Path) {
+    let state_path = target_folder.join(EXTRACT_STATE_FILE);
+    if state_path.exists() {
+        if let Err(e) = fs::remove_file(&state_path) {
+            log::warn!("[Extract] Failed to remove extraction state: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yuukips-extract-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Bytes that deflate can't shrink, so an archive of them spans several volumes
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }).collect()
+    }
+
+    /// Deflated zip of `entries`, cut into volumes of `volume_size` bytes the way a split zip is
+    fn split_zip(entries: &[(&str, &[u8])], volume_size: usize) -> Vec<Vec<u8>> {
+        let locate = |offset: usize| ((offset / volume_size) as u16, (offset % volume_size) as u32);
+        let mut stream = Vec::new();
+        let mut central = Vec::new();
+        for (name, data) in entries {
+            let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let crc32 = crc32fast::hash(data);
+            let (disk, offset) = locate(stream.len());
+
+            stream.extend_from_slice(&ZIP_LOCAL_HEADER_SIG.to_le_bytes());
+            stream.extend_from_slice(&[20, 0, 0, 0, 8, 0, 0, 0, 0, 0]); // version, flags, method, time, date
+            stream.extend_from_slice(&crc32.to_le_bytes());
+            stream.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            stream.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            stream.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            stream.extend_from_slice(&0u16.to_le_bytes());
+            stream.extend_from_slice(name.as_bytes());
+            stream.extend_from_slice(&compressed);
+
+            central.extend_from_slice(&ZIP_CENTRAL_HEADER_SIG.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0, 8, 0, 0, 0, 0, 0]); // made by, version, flags, method, time, date
+            central.extend_from_slice(&crc32.to_le_bytes());
+            central.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0, 0, 0, 0]); // extra and comment lengths
+            central.extend_from_slice(&disk.to_le_bytes());
+            central.extend_from_slice(&[0; 6]); // internal and external attributes
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let (cd_disk, cd_offset) = locate(stream.len());
+        let cd_size = central.len() as u32;
+        stream.extend_from_slice(&central);
+        let (last_disk, _) = locate(stream.len());
+        stream.extend_from_slice(&ZIP_EOCD_SIG.to_le_bytes());
+        stream.extend_from_slice(&last_disk.to_le_bytes());
+        stream.extend_from_slice(&cd_disk.to_le_bytes());
+        stream.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        stream.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        stream.extend_from_slice(&cd_size.to_le_bytes());
+        stream.extend_from_slice(&cd_offset.to_le_bytes());
+        stream.extend_from_slice(&0u16.to_le_bytes());
+        stream.chunks(volume_size).map(|volume| volume.to_vec()).collect()
+    }
+
+    /// Write volumes as `name.z01`, `name.z02`, … with the last one as `name.zip`, returning their paths
+    fn write_volumes(dir: &Path, name: &str, volumes: &[Vec<u8>]) -> Vec<PathBuf> {
+        volumes.iter().enumerate().map(|(index, volume)| {
+            let path = if index + 1 == volumes.len() {
+                dir.join(format!("{}.zip", name))
+            } else {
+                dir.join(format!("{}.z{:02}", name, index + 1))
+            };
+            fs::write(&path, volume).unwrap();
+            path
+        }).collect()
+    }
+
+    fn extract(archive: &Path, target: &Path, cancelled: bool) -> Result<usize, String> {
+        extract_archive_blocking(archive, target, None, Arc::new(AtomicBool::new(cancelled)))
+    }
+
+    #[test]
+    fn extracts_every_entry_and_removes_the_journal() {
+        let dir = test_dir();
+        let config = b"[game]\nversion=1\n".repeat(50);
+        let asset = noise(100_000, 7);
+        let archive = write_volumes(&dir, "game", &split_zip(&[("config.ini", &config), ("data/asset.bin", &asset)], usize::MAX));
+        let target = dir.join("out");
+
+        assert_eq!(extract(&archive[0], &target, false), Ok(2));
+        assert_eq!(fs::read(target.join("config.ini")).unwrap(), config);
+        assert_eq!(fs::read(target.join("data/asset.bin")).unwrap(), asset);
+        assert!(!target.join(EXTRACT_STATE_FILE).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rejects_an_entry_whose_crc_does_not_match() {
+        let dir = test_dir();
+        let mut volumes = split_zip(&[("config.ini", b"version=1")], usize::MAX);
+        let central = volumes[0].windows(4)
+            .position(|window| window == ZIP_CENTRAL_HEADER_SIG.to_le_bytes())
+            .unwrap();
+        volumes[0][central + 16] ^= 0xFF;
+        let archive = write_volumes(&dir, "game", &volumes);
+        let target = dir.join("out");
+
+        let error = extract(&archive[0], &target, false).unwrap_err();
+        assert!(error.contains("CRC mismatch for config.ini"), "{}", error);
+        assert!(!target.join("config.ini").exists());
+        assert!(!temp_output_path(&target.join("config.ini")).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn resumes_after_a_cancel_without_redoing_finished_entries() {
+        let dir = test_dir();
+        let first = noise(50_000, 1);
+        let second = noise(50_000, 2);
+        let archive = write_volumes(&dir, "game", &split_zip(&[("first.bin", &first), ("data/second.bin", &second)], usize::MAX));
+        let target = dir.join("out");
+
+        let error = extract(&archive[0], &target, true).unwrap_err();
+        assert!(error.contains("cancelled"), "{}", error);
+        assert!(!target.join("first.bin").exists());
+
+        // What a cancel during the second entry leaves behind: the first entry in place and journaled,
+        // the second half written to its temp file
+        fs::write(target.join("first.bin"), b"already extracted").unwrap();
+        fs::create_dir_all(target.join("data")).unwrap();
+        fs::write(temp_output_path(&target.join("data/second.bin")), &second[..10_000]).unwrap();
+        let state = ExtractState {
+            archive: "game.zip".to_string(),
+            archive_size: fs::metadata(&archive[0]).unwrap().len(),
+            completed: vec!["first.bin".to_string()],
+        };
+        save_extract_state(&target, &state).unwrap();
+
+        assert_eq!(extract(&archive[0], &target, false), Ok(2));
+        assert_eq!(fs::read(target.join("first.bin")).unwrap(), b"already extracted");
+        assert_eq!(fs::read(target.join("data/second.bin")).unwrap(), second);
+        assert!(!temp_output_path(&target.join("data/second.bin")).exists());
+        assert!(!target.join(EXTRACT_STATE_FILE).exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reads_split_volumes_in_order_from_any_of_them() {
+        let dir = test_dir();
+        let first = noise(30_000, 3);
+        let second = noise(30_000, 4);
+        let volumes = split_zip(&[("first.bin", &first), ("second.bin", &second)], 16 * 1024);
+        assert!(volumes.len() >= 4);
+        let paths = write_volumes(&dir, "game", &volumes);
+        assert!(paths.last().unwrap().ends_with("game.zip"));
+
+        for path in [&paths[0], &paths[1], paths.last().unwrap()] {
+            let listed = get_archive_volumes(path.to_string_lossy().to_string()).unwrap();
+            let expected: Vec<String> = paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+            assert_eq!(listed, expected);
+        }
+
+        // Entries cross volume boundaries, so any volume out of order corrupts them
+        let target = dir.join("out");
+        assert_eq!(extract(&paths[1], &target, false), Ok(2));
+        assert_eq!(fs::read(target.join("first.bin")).unwrap(), first);
+        assert_eq!(fs::read(target.join("second.bin")).unwrap(), second);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rejects_a_central_directory_beyond_the_end_of_the_archive() {
+        // Only an end of central directory record, claiming 1000 entries in 1 MB
+        let mut eocd = ZIP_EOCD_SIG.to_le_bytes().to_vec();
+        eocd.extend_from_slice(&[0, 0, 0, 0]); // this disk, central directory disk
+        eocd.extend_from_slice(&1000u16.to_le_bytes());
+        eocd.extend_from_slice(&1000u16.to_le_bytes());
+        eocd.extend_from_slice(&1_000_000u32.to_le_bytes());
+        eocd.extend_from_slice(&0u32.to_le_bytes());
+        eocd.extend_from_slice(&0u16.to_le_bytes());
+
+        let dir = test_dir();
+        let archive = dir.join("hostile.zip");
+        fs::write(&archive, eocd).unwrap();
+
+        let error = archive_unpacked_size(&archive).unwrap_err();
+        assert!(error.contains("outside the archive"), "{}", error);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
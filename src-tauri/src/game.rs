@@ -14,7 +14,7 @@ use tauri::Emitter;
 
 use crate::hoyoplay::{ remove_all_hoyo_pass};
 use crate::patch::{
-    check_and_apply_patches, cleanup_remaining_patches, restore_from_backups,
+    check_and_apply_patches, cleanup_remaining_patches, end_patch_session, restore_from_backups,
     restore_original_files,
 };
 use crate::proxy;
@@ -42,6 +42,8 @@ pub struct LaunchResult {
     pub message: String,
     #[serde(rename = "processId")]
     pub process_id: u32,
+    #[serde(rename = "patchStatus")]
+    pub patch_status: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,6 +51,8 @@ pub struct PatchCheckResult {
     pub has_message: bool,
     pub message: String,
     pub can_proceed: bool,
+    pub cached_at: Option<i64>, // set when the API was unreachable and a cached patch plan was used
+    pub patch_status: Option<String>, // "Offline, using cached patch from ..." for the launch UI
 }
 
 /// Check for patch messages before launching
@@ -104,7 +108,7 @@ pub fn check_patch_message(
             .map_err(|e| format!("Failed to create async runtime: {}", e))?;
 
         let result = rt.block_on(async {
-            match crate::patch::fetch_patch_info(
+            match crate::patch::fetch_patch_info_or_cached(
                 _game_id.clone(),
                 _version.clone(),
                 _channel.clone(),
@@ -112,25 +116,19 @@ pub fn check_patch_message(
             )
             .await
             {
-                Ok(patch_response) => {
-                    if !patch_response.message.is_empty() {
-                        PatchCheckResult {
-                            has_message: true,
-                            message: patch_response.message.clone(),
-                            can_proceed: true,
-                        }
-                    } else {
-                        PatchCheckResult {
-                            has_message: false,
-                            message: String::new(),
-                            can_proceed: true,
-                        }
-                    }
-                }
+                Ok((patch_response, cached_at)) => PatchCheckResult {
+                    has_message: !patch_response.message.is_empty(),
+                    message: patch_response.message.clone(),
+                    can_proceed: true,
+                    cached_at,
+                    patch_status: cached_at.map(crate::patch::offline_patch_status),
+                },
                 Err(_) => PatchCheckResult {
                     has_message: false,
                     message: String::new(),
                     can_proceed: true,
+                    cached_at: None,
+                    patch_status: None,
                 },
             }
         });
@@ -209,6 +207,7 @@ pub fn launch_game(
         let md5_str = format!("{:x}", md5);
 
         // Apply patches if needed
+        let mut patch_status = String::new();
         let (patched_files, patch_response_data) = match check_and_apply_patches(
             _game_id.clone(),
             _version.clone(),
@@ -220,6 +219,7 @@ pub fn launch_game(
                 if !patch_message.is_empty() {
                     log::info!("🔧 Patch status: {}", patch_message);
                 }
+                patch_status = patch_message;
                 let patch_response_data = response.clone();
 
                 // Check if we need to show a message to user before proceeding
@@ -300,6 +300,7 @@ pub fn launch_game(
                         md5_str
                     ),
                     process_id,
+                    patch_status,
                 };
                 match serde_json::to_string(&result) {
                     Ok(json) => Ok(json),
@@ -915,27 +916,36 @@ fn handle_game_stopped_cleanup_with_handle(handle: &GameMonitorHandle) {
         );
 
         if let Some(response) = patch_response {
-            // Try API-based restoration first
-            match restore_original_files(&response, &handle.game_folder_path) {
-                Ok(message) => {
+            // Try API-based restoration first, falling back to the backups for whatever it couldn't restore
+            let (restored, failed) = match restore_original_files(&response, &handle.game_folder_path) {
+                Ok((message, restored_files, failed_files)) => {
                     log::info!("🔄 {}", message);
+                    (restored_files, failed_files)
                 }
                 Err(e) => {
                     log::error!("⚠️ API restoration failed: {}", e);
-                    // Fallback to backup restoration
-                    match restore_from_backups(&handle.game_folder_path, &patched_files) {
-                        Ok(message) => {
-                            log::info!("🔄 {}", message);
-                        }
-                        Err(e) => {
-                            log::error!("⚠️ Backup restoration also failed: {}", e);
-                        }
+                    (Vec::new(), patched_files.clone())
+                }
+            };
+            if !failed.is_empty() {
+                match restore_from_backups(&handle.game_folder_path, &failed) {
+                    Ok(message) => {
+                        log::info!("🔄 {}", message);
+                    }
+                    Err(e) => {
+                        log::error!("⚠️ Backup restoration also failed: {}", e);
                     }
                 }
             }
 
-            // Additional cleanup: rename any remaining patched files to .patch
-            match cleanup_remaining_patches(&handle.game_folder_path, &patched_files) {
+            // Additional cleanup: rename any remaining patched files to .patch.
+            // Files that failed to restore are game files, so they stay in place rather than go missing.
+            let remaining: Vec<String> = patched_files
+                .iter()
+                .filter(|file| !restored.contains(file) && !failed.contains(file))
+                .cloned()
+                .collect();
+            match cleanup_remaining_patches(&handle.game_folder_path, &remaining) {
                 Ok(message) => {
                     if !message.is_empty() {
                         log::info!("🧹 {}", message);
@@ -961,6 +971,9 @@ fn handle_game_stopped_cleanup_with_handle(handle: &GameMonitorHandle) {
     } else {
         log::info!("ℹ️ No patched files to clean up");
     }
+
+    // Originals are back, the crash-recovery journal is no longer needed
+    end_patch_session();
 }
 
 /// Stop game monitoring
@@ -0,0 +1,231 @@
+//! Request header module
+//! Custom headers, credentials and user agent sent with download requests, from the download itself and from host rules in the settings
+
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use tauri::{command, State};
+
+use crate::download::DownloadEngine;
+
+const REDACTED: &str = "<redacted>";
+
+/// Headers the downloader sets itself and that a download or rule may not override
+const RESERVED_HEADERS: &[&str] = &["range", "if-range", "host", "content-length", "connection", "transfer-encoding"];
+
+/// Extra request headers by name; values of credential-like headers never show up in logs
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct RequestHeaders(pub BTreeMap<String, String>);
+
+impl RequestHeaders {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for RequestHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, value)| {
+                (name, if is_sensitive_header(name) { REDACTED } else { value.as_str() })
+            }))
+            .finish()
+    }
+}
+
+/// Credentials sent as the `Authorization` header
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DownloadAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+impl fmt::Debug for DownloadAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Basic { username, .. } => f.debug_struct("Basic")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+            Self::Bearer { .. } => f.debug_struct("Bearer").field("token", &REDACTED).finish(),
+        }
+    }
+}
+
+impl DownloadAuth {
+    fn header_value(&self) -> String {
+        match self {
+            Self::Basic { username, password } => {
+                format!("Basic {}", base64_encode(format!("{}:{}", username, password).as_bytes()))
+            }
+            Self::Bearer { token } => format!("Bearer {}", token),
+        }
+    }
+}
+
+/// Headers applied to every download from a host; `*.example.com` matches the subdomains of example.com
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HostHeaderRule {
+    pub host: String,
+    #[serde(default)]
+    pub headers: RequestHeaders,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub auth: Option<DownloadAuth>,
+}
+
+impl HostHeaderRule {
+    fn matches(&self, host: &str) -> bool {
+        let pattern = self.host.trim().to_lowercase();
+        let host = host.to_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host.len() > domain.len() && host.ends_with(domain)
+                && host[..host.len() - domain.len()].ends_with('.'),
+            None => host == pattern,
+        }
+    }
+}
+
+/// Credential-like headers whose values are kept out of logs
+pub fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_lowercase();
+    matches!(name.as_str(), "authorization" | "proxy-authorization" | "cookie")
+        || ["token", "secret", "key", "auth", "password"].iter().any(|word| name.contains(word))
+}
+
+/// Headers as `Name: value` for a log line, with credentials redacted
+pub fn redact_headers(headers: &[(String, String)]) -> String {
+    headers.iter()
+        .map(|(name, value)| format!("{}: {}", name, if is_sensitive_header(name) { REDACTED } else { value }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Check that headers are well-formed and leave the downloader's own headers alone
+pub fn validate_request_headers(headers: &RequestHeaders) -> Result<(), String> {
+    for (name, value) in &headers.0 {
+        if RESERVED_HEADERS.contains(&name.to_lowercase().as_str()) {
+            return Err(format!("Header {} is set by the downloader and cannot be overridden", name));
+        }
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        HeaderValue::from_str(value)
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+    }
+    Ok(())
+}
+
+fn validate_user_agent(user_agent: &Option<String>) -> Result<(), String> {
+    match user_agent {
+        Some(user_agent) if HeaderValue::from_str(user_agent).is_err() || user_agent.trim().is_empty() => {
+            Err("Invalid user agent".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validate everything a download brings along for its requests
+pub fn validate_request_options(headers: &RequestHeaders, auth: &Option<DownloadAuth>, user_agent: &Option<String>) -> Result<(), String> {
+    validate_request_headers(headers)?;
+    validate_user_agent(user_agent)?;
+    if let Some(auth) = auth {
+        HeaderValue::from_str(&auth.header_value())
+            .map_err(|_| "Invalid credentials".to_string())?;
+    }
+    Ok(())
+}
+
+/// Replace a header, matching its name case-insensitively
+fn set_header(headers: &mut Vec<(String, String)>, name: &str, value: String) {
+    headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+    headers.push((name.to_string(), value));
+}
+
+fn apply(headers: &mut Vec<(String, String)>, extra: &RequestHeaders, auth: &Option<DownloadAuth>, user_agent: &Option<String>) {
+    for (name, value) in &extra.0 {
+        set_header(headers, name, value.clone());
+    }
+    if let Some(user_agent) = user_agent {
+        set_header(headers, "User-Agent", user_agent.clone());
+    }
+    if let Some(auth) = auth {
+        set_header(headers, "Authorization", auth.header_value());
+    }
+}
+
+/// Whether two URLs point at the same host; a download's own headers and credentials only go to its primary URL's host
+pub fn same_host(primary_url: &str, url: &str) -> bool {
+    let host = |url: &str| url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase));
+    matches!((host(primary_url), host(url)), (Some(a), Some(b)) if a == b)
+}
+
+/// Headers for a request to `url`: matching host `rules` in order, then the download's own, which win
+pub fn resolve_request_headers(
+    url: &str,
+    rules: &[HostHeaderRule],
+    headers: &RequestHeaders,
+    auth: &Option<DownloadAuth>,
+    user_agent: &Option<String>,
+) -> Vec<(String, String)> {
+    let mut resolved = Vec::new();
+    let host = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string));
+    if let Some(host) = host {
+        for rule in rules.iter().filter(|rule| rule.matches(&host)) {
+            apply(&mut resolved, &rule.headers, &rule.auth, &rule.user_agent);
+        }
+    }
+    apply(&mut resolved, headers, auth, user_agent);
+    resolved
+}
+
+/// Custom headers followed by the downloader's own, in the form the transport takes
+pub fn header_list<'a>(custom: &'a [(String, String)], own: Vec<(&'a str, String)>) -> Vec<(&'a str, String)> {
+    custom.iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .chain(own)
+        .collect()
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let triple = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+        for (index, shift) in [18, 12, 6, 0].iter().enumerate() {
+            if index <= chunk.len() {
+                output.push(ALPHABET[((triple >> shift) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[command]
+pub fn get_app_download_header_rules(engine: State<'_, DownloadEngine>) -> Result<Vec<HostHeaderRule>, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(settings.download_header_rules.clone())
+}
+
+#[command]
+pub fn set_app_download_header_rules(engine: State<'_, DownloadEngine>, rules: Vec<HostHeaderRule>) -> Result<(), String> {
+    for rule in &rules {
+        let host = rule.host.trim();
+        if host.is_empty() || host.contains('/') || host.contains(':') {
+            return Err(format!("Invalid host in header rule: '{}'", rule.host));
+        }
+        validate_request_options(&rule.headers, &rule.auth, &rule.user_agent)
+            .map_err(|e| format!("Header rule for {}: {}", rule.host, e))?;
+    }
+
+    let mut settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    settings.download_header_rules = rules;
+    settings.save().map_err(|e| format!("Save error: {}", e))?;
+    Ok(())
+}
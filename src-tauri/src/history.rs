@@ -0,0 +1,178 @@
+//! Download history module
+//! Keeps finished downloads in their own store so they can be searched after leaving the live download list
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, State};
+
+use crate::download::{DownloadEngine, DownloadItem, DownloadStatus};
+
+/// Oldest entries are dropped beyond this many
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// Which history entries to return. Dates are seconds since the epoch and match the
+/// time a download finished (or started, if it never finished).
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryFilter {
+    #[serde(default)]
+    pub query: Option<String>, // matched against file name and URL, case-insensitive
+    #[serde(default)]
+    pub statuses: Vec<DownloadStatus>, // empty means any status
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// One page of matching entries, newest first
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub items: Vec<DownloadItem>,
+    pub total: usize, // matches before pagination
+}
+
+/// Finished downloads of one engine, persisted to download_history.json in its data directory
+pub struct HistoryStore {
+    path: PathBuf,
+    entries: Mutex<Vec<DownloadItem>>,
+}
+
+fn finished_at(download: &DownloadItem) -> u64 {
+    download.end_time.unwrap_or(download.start_time)
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, download: &DownloadItem) -> bool {
+        if let Some(query) = self.query.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            let query = query.to_lowercase();
+            if !download.file_name.to_lowercase().contains(&query)
+                && !download.url.to_lowercase().contains(&query)
+            {
+                return false;
+            }
+        }
+        if !self.statuses.is_empty() && !self.statuses.contains(&download.status) {
+            return false;
+        }
+        let time = finished_at(download);
+        self.from.map_or(true, |from| time >= from) && self.to.map_or(true, |to| time <= to)
+    }
+
+    /// Sort newest first and cut out the requested page
+    pub fn paginate(&self, mut items: Vec<DownloadItem>) -> HistoryPage {
+        items.sort_by_key(|download| std::cmp::Reverse(finished_at(download)));
+        let total = items.len();
+        let items = items.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        HistoryPage { items, total }
+    }
+}
+
+impl HistoryStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("download_history.json");
+        let entries = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::error!("Failed to parse download history: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<DownloadItem>>, String> {
+        self.entries.lock()
+            .map_err(|e| format!("Failed to lock download history: {}", e))
+    }
+
+    fn save(&self, entries: &[DownloadItem]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(entries)
+            .map_err(|e| format!("Failed to serialize download history: {}", e))?;
+        crate::utils::write_file_atomic(&self.path, json.as_bytes())
+    }
+
+    /// Store a download that reached Completed, Error or Cancelled, replacing any earlier run of it.
+    /// Its request headers and credentials are not kept.
+    pub fn record(&self, download: &DownloadItem) {
+        let Ok(mut history) = self.lock() else {
+            return;
+        };
+        let mut entry = download.clone();
+        entry.headers = Default::default();
+        entry.auth = None;
+        history.retain(|entry| entry.id != download.id);
+        history.push(entry);
+        if history.len() > MAX_HISTORY_ENTRIES {
+            history.sort_by_key(finished_at);
+            let excess = history.len() - MAX_HISTORY_ENTRIES;
+            history.drain(..excess);
+        }
+        if let Err(e) = self.save(&history) {
+            log::error!("Failed to save download history: {}", e);
+        }
+    }
+
+    /// History entries matching `filter`, without pagination
+    pub fn find(&self, filter: &HistoryFilter) -> Result<Vec<DownloadItem>, String> {
+        let history = self.lock()?;
+        Ok(history.iter().filter(|entry| filter.matches(entry)).cloned().collect())
+    }
+
+    /// Drop one entry from the history; returns whether it was there. Files on disk are left alone.
+    pub fn remove(&self, download_id: &str) -> Result<bool, String> {
+        let mut history = self.lock()?;
+        let before = history.len();
+        history.retain(|entry| entry.id != download_id);
+        if history.len() == before {
+            return Ok(false);
+        }
+        self.save(&history)?;
+        Ok(true)
+    }
+
+    /// Forget every finished download; files on disk are left alone
+    pub fn clear(&self) -> Result<(), String> {
+        let mut history = self.lock()?;
+        history.clear();
+        self.save(&history)
+    }
+}
+
+/// Search finished downloads
+#[command]
+pub fn search_download_history(engine: State<'_, DownloadEngine>, filter: HistoryFilter) -> Result<HistoryPage, String> {
+    let entries = engine.history().find(&filter)?;
+    Ok(filter.paginate(entries))
+}
+
+/// Remove one entry from the history without touching the downloaded file
+#[command]
+pub fn remove_download_history_entry(engine: State<'_, DownloadEngine>, download_id: String) -> Result<(), String> {
+    if engine.history().remove(&download_id)? {
+        Ok(())
+    } else {
+        Err("Download not found in history".to_string())
+    }
+}
+
+/// Forget every finished download; files on disk are left alone
+#[command]
+pub fn clear_download_history(engine: State<'_, DownloadEngine>) -> Result<(), String> {
+    engine.history().clear()
+}
@@ -26,8 +26,17 @@ pub struct GitHubAsset {
 
 /// Create an HTTP client with optional proxy bypass
 pub fn create_http_client(use_proxy: bool) -> Result<reqwest::Client, String> {
-    let mut client_builder = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
+    build_http_client(use_proxy, reqwest::Client::builder().timeout(Duration::from_secs(30)))
+}
+
+/// Like `create_http_client`, but without the overall request timeout, for transfers that may take minutes.
+/// Only connecting is limited, so callers must time out stalled reads themselves.
+pub fn create_streaming_http_client(use_proxy: bool) -> Result<reqwest::Client, String> {
+    build_http_client(use_proxy, reqwest::Client::builder().connect_timeout(Duration::from_secs(30)))
+}
+
+fn build_http_client(use_proxy: bool, client_builder: reqwest::ClientBuilder) -> Result<reqwest::Client, String> {
+    let mut client_builder = client_builder
         .user_agent("YuukiPS-Launcher/".to_owned()+env!("CARGO_PKG_VERSION"))
         .danger_accept_invalid_certs(true) // Accept invalid certs to handle Windows TLS issues
         .danger_accept_invalid_hostnames(true); // Accept invalid hostnames to handle Windows TLS issues
@@ -204,11 +213,13 @@ pub async fn download_and_install_update(
     let mut downloaded = 0u64;
     let mut stream = response.bytes_stream();
     let start_time = Instant::now();
+    let transfer = crate::bandwidth::BANDWIDTH_SHAPER.register(&download_url, crate::bandwidth::UPDATE_WEIGHT);
     
     // Download with progress tracking
     use futures_util::StreamExt;
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|e| format!("Download error: {}", e))?;
+        transfer.acquire(chunk.len()).await;
         
         file.write_all(&chunk)
             .await
@@ -0,0 +1,486 @@
+//! Game install job module
+//! Fetches the game download manifest, reuses valid archive parts and queues the rest as one grouped download job
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+use tauri::{command, State};
+use uuid::Uuid;
+
+use crate::download::{DownloadEngine, DownloadItem, DownloadOptions, DownloadStatus};
+use crate::extract::{archive_unpacked_size, is_archive_volume_name};
+use crate::http::create_http_client;
+use crate::utils::{calculate_md5_streamed, format_file_size, is_valid_url};
+
+/// Response of `/game/download/pc/{id}/{channel}/{version}.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameDownloadManifest {
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub metode: u32,
+    #[serde(default)]
+    pub file: Vec<GameDownloadFile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retcode: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameDownloadFile {
+    pub url: String,
+    pub file: String, // archive part name, placed directly in the target folder
+    pub md5: String,
+    pub package_size: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>, // alternative URLs for the same part, tried when `url` fails
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallPartStatus {
+    Verified, // already present in the target folder with a matching MD5
+    Queued,   // handed to the download manager
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallPart {
+    pub file: String,
+    pub url: String,
+    pub md5: String,
+    pub package_size: u64,
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    pub file_path: String,
+    pub status: InstallPartStatus,
+    pub download_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallJob {
+    pub id: String,
+    pub game_id: String,
+    pub channel: String,
+    pub version: String,
+    pub target_folder: String,
+    pub parts: Vec<InstallPart>,
+    pub total_size: u64,
+    pub unpacked_size: u64,
+    pub created_at: u64,
+}
+
+/// Aggregate progress of an install job across all of its parts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallJobProgress {
+    pub job: InstallJob,
+    pub status: String,
+    pub downloaded_size: u64,
+    pub total_size: u64,
+    pub progress: f64,
+    pub speed: u64,
+    pub completed_parts: u32,
+    pub total_parts: u32,
+    pub error_message: Option<String>,
+}
+
+/// Install jobs of one engine, persisted to install_jobs.json in its data directory
+pub struct InstallJobStore {
+    path: PathBuf,
+    jobs: Mutex<Vec<InstallJob>>,
+}
+
+impl InstallJobStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("install_jobs.json");
+        let jobs = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::error!("Failed to parse install jobs: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { path, jobs: Mutex::new(jobs) }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<InstallJob>>, String> {
+        self.jobs.lock()
+            .map_err(|e| format!("Failed to lock install jobs: {}", e))
+    }
+
+    fn save(&self, jobs: &[InstallJob]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(jobs)
+            .map_err(|e| format!("Failed to serialize install jobs: {}", e))?;
+        crate::utils::write_file_atomic(&self.path, json.as_bytes())
+    }
+}
+
+/// Guess at how much larger the unpacked game is than its archive, used for the disk space check
+/// until every part is on disk and the archive itself can tell. Game assets are mostly compressed
+/// already, but scripts, configs and text do shrink; overestimating only asks for more room,
+/// while underestimating lets the install run out of space halfway through extraction.
+const UNPACKED_SIZE_FACTOR: f64 = 1.5;
+
+/// Size of the unpacked game: the archive's own entry sizes once every part is present,
+/// otherwise the size of the parts times `UNPACKED_SIZE_FACTOR`
+fn estimate_unpacked_size(parts: &[InstallPart], total_size: u64) -> u64 {
+    if parts.iter().all(|p| p.status == InstallPartStatus::Verified) {
+        if let Some(part) = parts.iter().find(|p| is_archive_volume_name(&p.file)) {
+            match archive_unpacked_size(Path::new(&part.file_path)) {
+                Ok(size) => return size,
+                Err(e) => log::warn!("Failed to read unpacked size of {}: {}", part.file, e),
+            }
+        }
+    }
+    (total_size as f64 * UNPACKED_SIZE_FACTOR) as u64
+}
+
+/// Fetch the download manifest for a game version from the API
+pub async fn fetch_game_download_manifest_internal(
+    game_id: &Number,
+    channel: &Number,
+    version: &str,
+) -> Result<GameDownloadManifest, String> {
+    let client = create_http_client(false)?;
+
+    let url = format!(
+        "https://ps.yuuki.me/game/download/pc/{}/{}/{}.json",
+        game_id, channel, version
+    );
+
+    log::info!("🔍 Fetching game download manifest: {}", url);
+
+    let response = client.get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch download manifest: {}", e))?;
+
+    let status = response.status();
+    let manifest: GameDownloadManifest = response.json()
+        .await
+        .map_err(|e| format!("Failed to parse download manifest (status {}): {}", status, e))?;
+
+    validate_manifest(&manifest)?;
+
+    log::info!("📦 Download manifest received: method={}, parts={}", manifest.metode, manifest.file.len());
+
+    Ok(manifest)
+}
+
+/// Reject error responses and manifests whose parts cannot be downloaded safely
+fn validate_manifest(manifest: &GameDownloadManifest) -> Result<(), String> {
+    if let Some(retcode) = manifest.retcode {
+        if retcode != 0 {
+            return Err(format!("Download manifest not available: {} (retcode {})", manifest.message, retcode));
+        }
+    }
+
+    if manifest.metode != 1 {
+        return Err(format!("Unsupported download method: {}", manifest.metode));
+    }
+
+    if manifest.file.is_empty() {
+        return Err("Download manifest does not list any files".to_string());
+    }
+
+    for part in &manifest.file {
+        // Parts are written straight into the target folder, so names must not contain paths
+        let is_plain_name = Path::new(&part.file).file_name()
+            .map(|name| name == part.file.as_str())
+            .unwrap_or(false);
+        if !is_plain_name || part.file == ".." {
+            return Err(format!("Invalid file name in manifest: {}", part.file));
+        }
+
+        if !is_valid_url(&part.url) {
+            return Err(format!("Invalid URL for {}: {}", part.file, part.url));
+        }
+
+        if let Some(mirror) = part.mirrors.iter().find(|mirror| !is_valid_url(mirror)) {
+            return Err(format!("Invalid mirror URL for {}: {}", part.file, mirror));
+        }
+
+        if part.md5.len() != 32 || !part.md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid MD5 for {}: {}", part.file, part.md5));
+        }
+
+        if part.package_size == 0 {
+            return Err(format!("Invalid package size for {}", part.file));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether an archive part already present on disk matches the manifest
+async fn is_part_already_present(file_path: &Path, part: &GameDownloadFile) -> bool {
+    let size_matches = fs::metadata(file_path)
+        .map(|m| m.len() == part.package_size)
+        .unwrap_or(false);
+    if !size_matches {
+        return false;
+    }
+
+    match calculate_md5_streamed(file_path).await {
+        Ok(md5) => md5.eq_ignore_ascii_case(&part.md5),
+        Err(e) => {
+            log::warn!("⚠️ Failed to verify existing part {}: {}", file_path.display(), e);
+            false
+        }
+    }
+}
+
+/// Compute aggregate progress of a job from its parts and their downloads
+fn build_job_progress(engine: &DownloadEngine, job: &InstallJob) -> Result<InstallJobProgress, String> {
+    let downloads: Vec<DownloadItem> = engine.get_group_downloads(&job.id)?;
+
+    let mut downloaded_size = 0u64;
+    let mut speed = 0u64;
+    let mut completed_parts = 0u32;
+    let mut has_active = false;
+    let mut error_message = None;
+    let mut cancelled = false;
+    let mut insufficient_space = false;
+
+    for part in &job.parts {
+        if part.status == InstallPartStatus::Verified {
+            downloaded_size += part.package_size;
+            completed_parts += 1;
+            continue;
+        }
+
+        let download = part.download_id.as_ref()
+            .and_then(|id| downloads.iter().find(|d| &d.id == id));
+        match download {
+            Some(download) => {
+                downloaded_size += download.downloaded_size.min(part.package_size);
+                match download.status {
+                    DownloadStatus::Completed => completed_parts += 1,
+                    DownloadStatus::Error => {
+                        error_message.get_or_insert_with(|| format!(
+                            "{}: {}", part.file, download.error_message.clone().unwrap_or_default()
+                        ));
+                    }
+                    DownloadStatus::Cancelled => cancelled = true,
+                    DownloadStatus::Downloading => {
+                        has_active = true;
+                        speed += download.speed;
+                    }
+                    DownloadStatus::Paused | DownloadStatus::Queued | DownloadStatus::Verifying => has_active = true,
+                    DownloadStatus::InsufficientSpace => {
+                        has_active = true;
+                        insufficient_space = true;
+                    }
+                }
+            }
+            None => cancelled = true, // download was removed from the manager
+        }
+    }
+
+    let total_parts = job.parts.len() as u32;
+    let status = if completed_parts == total_parts {
+        "completed"
+    } else if error_message.is_some() {
+        "error"
+    } else if insufficient_space {
+        "insufficient_space"
+    } else if has_active {
+        "downloading"
+    } else if cancelled {
+        "cancelled"
+    } else {
+        "queued"
+    };
+
+    Ok(InstallJobProgress {
+        job: job.clone(),
+        status: status.to_string(),
+        downloaded_size,
+        total_size: job.total_size,
+        progress: if job.total_size > 0 {
+            (downloaded_size as f64 / job.total_size as f64) * 100.0
+        } else {
+            0.0
+        },
+        speed,
+        completed_parts,
+        total_parts,
+        error_message,
+    })
+}
+
+/// Fetch and validate the download manifest for a game version
+#[command]
+pub async fn fetch_game_download_manifest(
+    game_id: Number,
+    channel: Number,
+    version: String,
+) -> Result<GameDownloadManifest, String> {
+    fetch_game_download_manifest_internal(&game_id, &channel, &version).await
+}
+
+/// Create an install job: reuse valid parts in the target folder and queue the rest as one download group
+#[command]
+pub async fn create_game_install_job(
+    engine: State<'_, DownloadEngine>,
+    game_id: Number,
+    channel: Number,
+    version: String,
+    target_folder: String,
+) -> Result<InstallJobProgress, String> {
+    let manifest = fetch_game_download_manifest_internal(&game_id, &channel, &version).await?;
+
+    let target = Path::new(&target_folder);
+    fs::create_dir_all(target)
+        .map_err(|e| format!("Failed to create target folder {}: {}", target_folder, e))?;
+
+    let job_id = Uuid::new_v4().to_string();
+    let total_size: u64 = manifest.file.iter().map(|f| f.package_size).sum();
+
+    // Reuse any part already present with a matching MD5
+    let mut parts = Vec::new();
+    for file in &manifest.file {
+        let file_path = target.join(&file.file);
+        let present = is_part_already_present(&file_path, file).await;
+        if present {
+            log::info!("✅ Reusing existing part: {}", file.file);
+        }
+
+        parts.push(InstallPart {
+            file: file.file.clone(),
+            url: file.url.clone(),
+            md5: file.md5.to_lowercase(),
+            package_size: file.package_size,
+            mirrors: file.mirrors.clone(),
+            file_path: file_path.to_string_lossy().to_string(),
+            status: if present { InstallPartStatus::Verified } else { InstallPartStatus::Queued },
+            download_id: None,
+        });
+    }
+
+    // Archives still to download plus the unpacked game must fit on the target drive
+    let unpacked_size = estimate_unpacked_size(&parts, total_size);
+    let remaining_size: u64 = parts.iter()
+        .filter(|p| p.status == InstallPartStatus::Queued)
+        .map(|p| p.package_size)
+        .sum();
+    let required_space = remaining_size + unpacked_size;
+    let available_space = engine.get_unreserved_disk_space(&target_folder)?;
+    if available_space < required_space {
+        return Err(format!(
+            "Insufficient disk space: {} required ({} to download + {} unpacked), {} available",
+            format_file_size(required_space),
+            format_file_size(remaining_size),
+            format_file_size(unpacked_size),
+            format_file_size(available_space)
+        ));
+    }
+
+    let mut started: Vec<String> = Vec::new();
+    for part in parts.iter_mut().filter(|p| p.status == InstallPartStatus::Queued) {
+        let options = DownloadOptions {
+            group_id: Some(job_id.clone()),
+            expected_size: Some(part.package_size),
+            mirrors: part.mirrors.clone(),
+            expected_md5: Some(part.md5.clone()),
+            preallocate: true, // multi-GB archive parts should fail on a full disk before they start
+            ..Default::default()
+        };
+        let result = engine.start_download(
+            part.url.clone(),
+            part.file_path.clone(),
+            Some(part.file.clone()),
+            options,
+        ).await;
+        match result {
+            Ok(download_id) => {
+                started.push(download_id.clone());
+                part.download_id = Some(download_id);
+            }
+            Err(e) => {
+                // Without a saved job nothing would ever track the parts queued so far
+                for download_id in started {
+                    if let Err(e) = engine.cancel_download(download_id) {
+                        log::warn!("Failed to cancel part of abandoned install job: {}", e);
+                    }
+                }
+                return Err(format!("Failed to queue {}: {}", part.file, e));
+            }
+        }
+    }
+
+    let job = InstallJob {
+        id: job_id,
+        game_id: game_id.to_string(),
+        channel: channel.to_string(),
+        version,
+        target_folder,
+        parts,
+        total_size,
+        unpacked_size,
+        created_at: engine.clock().unix_time(),
+    };
+
+    log::info!("📥 Created install job {} for game {} ({} parts, {} to download)",
+        job.id, job.game_id, job.parts.len(), format_file_size(remaining_size));
+
+    {
+        let store = engine.install_jobs();
+        let mut jobs = store.lock()?;
+        jobs.push(job.clone());
+        store.save(&jobs)?;
+    }
+
+    build_job_progress(&engine, &job)
+}
+
+/// Get aggregate progress of an install job
+#[command]
+pub fn get_game_install_job(engine: State<'_, DownloadEngine>, job_id: String) -> Result<InstallJobProgress, String> {
+    let job = {
+        let jobs = engine.install_jobs().lock()?;
+        jobs.iter().find(|j| j.id == job_id).cloned()
+            .ok_or_else(|| format!("Install job {} not found", job_id))?
+    };
+
+    build_job_progress(&engine, &job)
+}
+
+/// Get aggregate progress of all install jobs
+#[command]
+pub fn get_game_install_jobs(engine: State<'_, DownloadEngine>) -> Result<Vec<InstallJobProgress>, String> {
+    let jobs = {
+        let jobs = engine.install_jobs().lock()?;
+        jobs.clone()
+    };
+
+    jobs.iter().map(|job| build_job_progress(&engine, job)).collect()
+}
+
+/// Cancel every download of an install job that has not finished yet and forget the job
+#[command]
+pub fn cancel_game_install_job(engine: State<'_, DownloadEngine>, job_id: String) -> Result<(), String> {
+    let job = {
+        let store = engine.install_jobs();
+        let mut jobs = store.lock()?;
+        let index = jobs.iter().position(|j| j.id == job_id)
+            .ok_or_else(|| format!("Install job {} not found", job_id))?;
+        let job = jobs.remove(index);
+        store.save(&jobs)?;
+        job
+    };
+
+    for download in engine.get_group_downloads(&job.id)? {
+        if !matches!(download.status, DownloadStatus::Completed | DownloadStatus::Error | DownloadStatus::Cancelled) {
+            engine.cancel_download(download.id)?;
+        }
+    }
+
+    log::info!("🛑 Cancelled install job {}", job_id);
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Write-ahead journal module
+//! Append-only JSON-lines log kept next to a snapshot file and replayed after a crash
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+/// Entries of type `T`, one JSON object per line. A crash can only tear the line being
+/// written, so unreadable lines are skipped when reading back.
+pub struct Journal<T> {
+    path: PathBuf,
+    file: Option<File>,
+    _entry: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Journal<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, file: None, _entry: PhantomData }
+    }
+
+    fn open(&mut self) -> Result<&mut File, String> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| format!("Failed to open journal {}: {}", self.path.display(), e))?;
+            self.file = Some(file);
+        }
+        self.file.as_mut().ok_or_else(|| "Journal is not open".to_string())
+    }
+
+    /// Append one entry; it reaches the OS right away but is only forced to disk by `sync`
+    pub fn append(&mut self, entry: &T) -> Result<(), String> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+        line.push('\n');
+        let file = self.open()?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write journal entry: {}", e))
+    }
+
+    /// Force appended entries to disk
+    pub fn sync(&mut self) -> Result<(), String> {
+        let file = self.open()?;
+        file.sync_data()
+            .map_err(|e| format!("Failed to sync journal: {}", e))
+    }
+
+    /// Every readable entry, oldest first
+    pub fn read_all(&self) -> Vec<T> {
+        let Ok(contents) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        let mut skipped = 0;
+        let entries: Vec<T> = contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(_) => {
+                    skipped += 1;
+                    None
+                }
+            })
+            .collect();
+        if skipped > 0 {
+            log::warn!("Skipped {} unreadable entries in journal {}", skipped, self.path.display());
+        }
+        entries
+    }
+
+    /// Drop every entry, once they are covered by a new snapshot
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.file = None;
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to clear journal {}: {}", self.path.display(), e)),
+        }
+    }
+}
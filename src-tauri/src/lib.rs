@@ -4,24 +4,50 @@
 use tauri::Manager;
 
 // Import all modules
+mod bandwidth;
+mod delta;
 mod download;
+mod extract;
 mod game;
+mod headers;
+mod history;
 mod hoyoplay;
 mod http;
+mod install;
+mod journal;
+mod metalink;
+mod mirrors;
 mod patch;
+mod pipeline;
 mod proxy;
+mod schedule;
 mod settings;
 mod system;
+mod throughput;
+mod transport;
 mod utils;
 
 // Re-export commonly used functions for easier access
+pub use bandwidth::*;
+pub use delta::*;
 pub use download::*;
+pub use extract::*;
 pub use game::*;
+pub use headers::*;
+pub use history::*;
 pub use hoyoplay::*;
 pub use http::*;
+pub use install::*;
+pub use journal::*;
+pub use metalink::*;
+pub use mirrors::*;
 pub use patch::*;
+pub use pipeline::*;
+pub use schedule::*;
 pub use settings::*;
 pub use system::*;
+pub use throughput::*;
+pub use transport::*;
 pub use utils::*;
 
 // Task manager monitoring functions are already available through pub use system::*
@@ -110,6 +136,8 @@ pub fn run() {
             download::get_download_status,
             download::clear_completed_downloads,
             download::get_download_stats,
+            download::get_throughput_history,
+            download::clear_throughput_history,
             download::open_download_location,
             download::set_download_directory,
             download::get_download_directory,
@@ -140,6 +168,38 @@ pub fn run() {
             download::set_max_simultaneous_downloads,
             download::check_and_fix_stalled_downloads,
             download::get_download_resume_support,
+            download::verify_and_repair_download,
+            download::get_download_queue,
+            download::move_download_up,
+            download::move_download_down,
+            download::move_download_to_front,
+            download::set_download_priority,
+            download::pin_download,
+            download::search_downloads,
+            download::get_downloads_by_status,
+            download::remove_download,
+            history::search_download_history,
+            history::remove_download_history_entry,
+            history::clear_download_history,
+            bandwidth::get_bandwidth_status,
+            mirrors::get_mirror_health,
+            metalink::import_download_list,
+            metalink::export_download_queue,
+            pipeline::set_completion_actions,
+            pipeline::get_completion_pipelines,
+            pipeline::retry_completion_pipeline,
+            pipeline::cancel_completion_pipeline,
+            pipeline::remove_completion_pipeline,
+            // Game install job functions
+            install::fetch_game_download_manifest,
+            install::create_game_install_job,
+            install::get_game_install_job,
+            install::get_game_install_jobs,
+            install::cancel_game_install_job,
+            // Archive extraction functions
+            extract::extract_archive,
+            extract::cancel_extraction,
+            extract::get_archive_volumes,
             // Settings functions (new simple JSON persistence)
             settings::get_app_speed_limit,
             settings::set_app_speed_limit,
@@ -147,7 +207,16 @@ pub fn run() {
             settings::set_app_divide_speed_enabled,
             settings::get_app_max_simultaneous_downloads,
             settings::set_app_max_simultaneous_downloads,
+            settings::get_app_download_segments,
+            settings::set_app_download_segments,
             settings::get_all_app_settings,
+            // Download schedule functions
+            schedule::get_app_download_schedule,
+            schedule::set_app_download_schedule,
+            schedule::get_download_schedule_status,
+            // Download header rule functions
+            headers::get_app_download_header_rules,
+            headers::set_app_download_header_rules,
             // Proxy functions
             proxy::get_proxy_addr,
             proxy::set_proxy_addr,
@@ -205,6 +274,8 @@ pub fn run() {
             clear_download_progress,
             check_patch_status,
             fetch_patch_info_command,
+            get_unfinished_patch_session,
+            recover_patch_session,
             restore_game_files,
             // HoyoPlay functions (includes moved functions from utils.rs)
             get_game_executable_names,
@@ -241,6 +312,48 @@ pub fn run() {
                 Err(e) => log::error!("⚠️ Startup proxy check failed: {}", e),
             }
             
+            // Offer to undo patches left applied by a crash or forced close
+            if let Some(session) = pending_patch_session() {
+                log::warn!("⚠️ Unfinished patch session found in {} ({} files)", session.game_folder, session.files.len());
+                use tauri_plugin_dialog::DialogExt;
+                app.dialog()
+                    .message(format!("The launcher was closed while a game was patched, so {} patched files are still in:\n{}\n\nRestore the original game files now?", session.files.len(), session.game_folder))
+                    .title("Unfinished Patch Session")
+                    .buttons(tauri_plugin_dialog::MessageDialogButtons::OkCancelCustom("Restore".to_string(), "Later".to_string()))
+                    .show(|restore| {
+                        if !restore {
+                            log::info!("ℹ️ Patch session restore postponed");
+                            return;
+                        }
+                        match recover_patch_session() {
+                            Ok(message) => log::info!("✅ Patch session restored: {}", message),
+                            Err(e) => log::error!("⚠️ Patch session restore failed: {}", e),
+                        }
+                    });
+            }
+            
+            // One download engine for the app's lifetime, pushing progress, status and queue events to the frontend
+            let data_dir = DownloadEngine::default_data_dir();
+            // Held for the app's lifetime so yuukips-cli won't run downloads on the same state
+            match DownloadEngine::lock_data_dir(&data_dir) {
+                Ok(lock) => {
+                    app.manage(lock);
+                }
+                Err(e) => log::warn!("⚠️ Download state is in use by another process: {}", e),
+            }
+            let engine = DownloadEngine::new(data_dir);
+            engine.set_event_handle(app.handle().clone());
+            app.manage(engine.clone());
+            
+            // Start the shared bandwidth limiter with the saved speed limit
+            match engine.settings().lock() {
+                Ok(app_settings) => engine.apply_speed_limit(&app_settings),
+                Err(e) => log::error!("⚠️ Failed to read settings for bandwidth limit: {}", e),
+            }
+            
+            // Pause and resume the download queue at scheduled window boundaries
+            schedule::start_schedule_monitor(engine);
+            
             // Show the main window after initialization
             let main_window = app.get_webview_window("main").unwrap();
             main_window.show().unwrap();
@@ -0,0 +1,523 @@
+//! Download list module
+//! Imports and exports sets of downloads as Metalink (RFC 5854) documents and aria2 input lists
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{command, State};
+
+use crate::download::{DownloadEngine, DownloadItem, DownloadOptions};
+use crate::utils::is_valid_url;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadListFormat {
+    Metalink,
+    Aria2,
+}
+
+impl DownloadListFormat {
+    /// Metalink for `.meta4` and `.metalink` files, an aria2 list for anything else
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("meta4" | "metalink") => Self::Metalink,
+            _ => Self::Aria2,
+        }
+    }
+
+    /// Metalink documents are XML, aria2 lists are plain text
+    fn detect(contents: &str) -> Self {
+        let start = contents.trim_start_matches('\u{feff}').trim_start();
+        if start.starts_with('<') {
+            Self::Metalink
+        } else {
+            Self::Aria2
+        }
+    }
+}
+
+/// One file of a download list; `urls` are in preference order
+#[derive(Clone, Debug, Default)]
+pub struct DownloadListEntry {
+    pub name: String, // relative path below the target folder
+    pub size: Option<u64>,
+    pub urls: Vec<String>,
+    pub md5: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl DownloadListEntry {
+    /// Entry for a download, named by its path below `root` so folders survive an import elsewhere
+    fn from_download(download: &DownloadItem, root: &Path) -> Self {
+        let path = Path::new(&download.file_path);
+        let relative = path.strip_prefix(root).ok()
+            .filter(|_| !root.as_os_str().is_empty())
+            .map(|relative| relative.components()
+                .map(|part| part.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/"))
+            .filter(|relative| !relative.is_empty());
+        let name = relative
+            .or_else(|| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_else(|| download.file_name.clone());
+        let size = if download.total_size > 0 { Some(download.total_size) } else { download.expected_size };
+        let mut urls = vec![download.url.clone()];
+        urls.extend(download.mirrors.iter().filter(|mirror| **mirror != download.url).cloned());
+
+        Self {
+            name,
+            size,
+            urls,
+            md5: download.expected_md5.clone(),
+            sha256: download.expected_sha256.clone(),
+        }
+    }
+}
+
+/// Result of importing a list: the downloads that were added and why others were not
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadListImport {
+    pub download_ids: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Deepest folder holding every one of `paths`; empty when they share none (e.g. different drives)
+fn common_root<'a>(paths: impl IntoIterator<Item = &'a Path>) -> PathBuf {
+    let mut root: Option<Vec<Component<'a>>> = None;
+    for path in paths {
+        let parent: Vec<Component<'a>> = path.parent().map(|p| p.components().collect()).unwrap_or_default();
+        root = Some(match root {
+            None => parent,
+            Some(root) => root.into_iter().zip(parent).take_while(|(a, b)| a == b).map(|(a, _)| a).collect(),
+        });
+    }
+    root.unwrap_or_default().into_iter().collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// A file name from a list as a path below the target folder; absolute paths and `..` are refused
+fn safe_relative_path(name: &str) -> Result<PathBuf, String> {
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return Err(format!("File name leaves the target folder: {}", name)),
+            part if part.contains(':') => return Err(format!("File name is not a relative path: {}", name)),
+            part => path.push(part),
+        }
+    }
+    if path.as_os_str().is_empty() || name.starts_with(['/', '\\']) {
+        return Err(format!("File name is not a relative path: {}", name));
+    }
+    Ok(path)
+}
+
+/// Local names and unescaped values of an element's attributes
+fn attributes(element: &BytesStart<'_>) -> Result<Vec<(String, String)>, String> {
+    element.attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(|e| format!("Invalid Metalink document: {}", e))?;
+            let name = String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string();
+            let value = attribute.unescape_value()
+                .map_err(|e| format!("Invalid Metalink document: {}", e))?;
+            Ok((name, value.to_string()))
+        })
+        .collect()
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// State of `parse_metalink` while it walks the document
+#[derive(Default)]
+struct MetalinkParser {
+    open: Vec<(String, Vec<(String, String)>)>, // local name and attributes of each open element
+    text: String, // text of the innermost element, entities and CDATA resolved
+    file: Option<(DownloadListEntry, Vec<(i64, String)>)>, // file being read and its ranked URLs
+    entries: Vec<DownloadListEntry>,
+    is_metalink: bool, // root element was <metalink>
+}
+
+impl MetalinkParser {
+    fn start(&mut self, element: &BytesStart<'_>) -> Result<(), String> {
+        let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+        let attributes = attributes(element)?;
+        if self.open.is_empty() {
+            if name != "metalink" {
+                return Err("Not a Metalink document".to_string());
+            }
+            self.is_metalink = true;
+        }
+        if name == "file" && self.file.is_none() {
+            let file_name = attribute(&attributes, "name")
+                .ok_or_else(|| "Metalink file without a name".to_string())?;
+            self.file = Some((DownloadListEntry { name: file_name.to_string(), ..Default::default() }, Vec::new()));
+        }
+        self.open.push((name, attributes));
+        self.text.clear();
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        let Some((name, attributes)) = self.open.pop() else {
+            return Ok(());
+        };
+        let text = std::mem::take(&mut self.text);
+        let parent = self.open.last().map(|(parent, _)| parent.as_str());
+        // Piece hashes cover parts of the file, not the whole of it
+        let in_pieces = self.open.iter().any(|(open, _)| open == "pieces");
+        let Some((entry, urls)) = self.file.as_mut() else {
+            return Ok(());
+        };
+
+        match name.as_str() {
+            "size" if parent == Some("file") => {
+                entry.size = Some(text.trim().parse::<u64>()
+                    .map_err(|e| format!("Invalid size for {}: {}", entry.name, e))?);
+            }
+            "hash" if !in_pieces => {
+                let value = Some(text.trim().to_lowercase());
+                match attribute(&attributes, "type").map(str::to_lowercase).as_deref() {
+                    Some("md5") => entry.md5 = value,
+                    Some("sha-256" | "sha256") => entry.sha256 = value,
+                    _ => {}
+                }
+            }
+            "url" => {
+                // Metalink 4 prefers low `priority`, Metalink 3 high `preference`
+                let rank = attribute(&attributes, "priority")
+                    .and_then(|p| p.parse::<i64>().ok())
+                    .or_else(|| attribute(&attributes, "preference").and_then(|p| p.parse::<i64>().ok()).map(|p| -p))
+                    .unwrap_or(i64::MAX);
+                let url = text.trim().to_string();
+                if is_valid_url(&url) {
+                    urls.push((rank, url));
+                }
+            }
+            "file" => {
+                let (mut entry, mut urls) = self.file.take().unwrap_or_default();
+                urls.sort_by_key(|(rank, _)| *rank);
+                for (_, url) in urls {
+                    if !entry.urls.contains(&url) {
+                        entry.urls.push(url);
+                    }
+                }
+                self.entries.push(entry);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Files of a Metalink 4 document; Metalink 3 `<url preference>` ordering is understood as well.
+/// Elements are matched by local name, so namespace prefixes don't matter.
+pub fn parse_metalink(xml: &str) -> Result<Vec<DownloadListEntry>, String> {
+    let mut reader = Reader::from_str(xml.trim_start_matches('\u{feff}'));
+    let mut parser = MetalinkParser::default();
+    loop {
+        let event = reader.read_event()
+            .map_err(|e| format!("Invalid Metalink document at byte {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(element) => parser.start(&element)?,
+            Event::Empty(element) => {
+                parser.start(&element)?;
+                parser.end()?;
+            }
+            Event::End(_) => parser.end()?,
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| format!("Invalid Metalink document: {}", e))?;
+                parser.text.push_str(&text);
+            }
+            Event::CData(data) => parser.text.push_str(&String::from_utf8_lossy(&data.into_inner())),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !parser.is_metalink {
+        return Err("Not a Metalink document".to_string());
+    }
+    if parser.entries.is_empty() {
+        return Err("Metalink document lists no files".to_string());
+    }
+    Ok(parser.entries)
+}
+
+/// Entries of an aria2 input file: tab-separated mirrors of one file per line, followed by
+/// indented `out=`, `dir=` and `checksum=` options. Other options are ignored.
+pub fn parse_aria2_list(text: &str) -> Result<Vec<DownloadListEntry>, String> {
+    let mut entries: Vec<DownloadListEntry> = Vec::new();
+    let mut dir: Option<String> = None;
+
+    // The output name is only final once all of an entry's options have been read
+    let finish = |entries: &mut Vec<DownloadListEntry>, dir: &mut Option<String>| {
+        if let (Some(entry), Some(dir)) = (entries.last_mut(), dir.take()) {
+            entry.name = format!("{}/{}", dir.trim_end_matches(['/', '\\']), entry.name);
+        }
+    };
+
+    for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with([' ', '\t']) {
+            finish(&mut entries, &mut dir);
+            let urls: Vec<String> = line.split('\t')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect();
+            if let Some(url) = urls.iter().find(|url| !is_valid_url(url)) {
+                return Err(format!("Line {}: unsupported URL {}", number + 1, url));
+            }
+            let name = urls.first()
+                .and_then(|url| url::Url::parse(url).ok())
+                .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+                .filter(|name| !name.is_empty())
+                .map(|name| percent_decode(&name))
+                .unwrap_or_else(|| "download".to_string());
+            entries.push(DownloadListEntry { name, urls, ..Default::default() });
+            continue;
+        }
+
+        let Some(entry) = entries.last_mut() else {
+            return Err(format!("Line {}: option before the first URL", number + 1));
+        };
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return Err(format!("Line {}: expected key=value", number + 1));
+        };
+        match key.trim() {
+            "out" => entry.name = value.trim().to_string(),
+            "dir" => dir = Some(value.trim().to_string()),
+            "checksum" => {
+                let (algorithm, digest) = value.split_once('=')
+                    .ok_or_else(|| format!("Line {}: expected checksum=TYPE=DIGEST", number + 1))?;
+                let digest = Some(digest.trim().to_lowercase());
+                match algorithm.trim().to_lowercase().as_str() {
+                    "md5" => entry.md5 = digest,
+                    "sha-256" | "sha256" => entry.sha256 = digest,
+                    other => log::warn!("[DownloadList] Ignoring unsupported {} checksum for {}", other, entry.name),
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(&mut entries, &mut dir);
+
+    if entries.is_empty() {
+        return Err("Download list has no URLs".to_string());
+    }
+    Ok(entries)
+}
+
+/// Decode `%XX` escapes in a URL path segment
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = segment.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn write_metalink(entries: &[DownloadListEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<metalink xmlns=\"urn:ietf:params:xml:ns:metalink\">\n");
+    xml.push_str("  <generator>YuukiPS Launcher</generator>\n");
+    for entry in entries {
+        xml.push_str(&format!("  <file name=\"{}\">\n", escape_xml(&entry.name)));
+        if let Some(size) = entry.size {
+            xml.push_str(&format!("    <size>{}</size>\n", size));
+        }
+        if let Some(md5) = &entry.md5 {
+            xml.push_str(&format!("    <hash type=\"md5\">{}</hash>\n", escape_xml(md5)));
+        }
+        if let Some(sha256) = &entry.sha256 {
+            xml.push_str(&format!("    <hash type=\"sha-256\">{}</hash>\n", escape_xml(sha256)));
+        }
+        for (index, url) in entry.urls.iter().enumerate() {
+            xml.push_str(&format!("    <url priority=\"{}\">{}</url>\n", index + 1, escape_xml(url)));
+        }
+        xml.push_str("  </file>\n");
+    }
+    xml.push_str("</metalink>\n");
+    xml
+}
+
+/// aria2 takes a single checksum per file, so SHA-256 wins over MD5. Sizes have no aria2 option and are left out.
+pub fn write_aria2_list(entries: &[DownloadListEntry]) -> String {
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&entry.urls.join("\t"));
+        text.push('\n');
+        text.push_str(&format!("  out={}\n", entry.name));
+        if let Some(sha256) = &entry.sha256 {
+            text.push_str(&format!("  checksum=sha-256={}\n", sha256));
+        } else if let Some(md5) = &entry.md5 {
+            text.push_str(&format!("  checksum=md5={}\n", md5));
+        }
+    }
+    text
+}
+
+/// Queue every file of a Metalink document or aria2 list below `target_folder`
+#[command]
+pub async fn import_download_list(
+    engine: State<'_, DownloadEngine>,
+    path: String,
+    target_folder: String,
+    format: Option<DownloadListFormat>,
+) -> Result<DownloadListImport, String> {
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read download list {}: {}", path, e))?;
+    let entries = match format.unwrap_or_else(|| DownloadListFormat::detect(&contents)) {
+        DownloadListFormat::Metalink => parse_metalink(&contents)?,
+        DownloadListFormat::Aria2 => parse_aria2_list(&contents)?,
+    };
+
+    let mut import = DownloadListImport::default();
+    for entry in entries {
+        let relative = match safe_relative_path(&entry.name) {
+            Ok(relative) => relative,
+            Err(e) => {
+                import.skipped.push(e);
+                continue;
+            }
+        };
+        let Some((url, mirrors)) = entry.urls.split_first() else {
+            import.skipped.push(format!("{}: no supported URL", entry.name));
+            continue;
+        };
+
+        let file_path = Path::new(&target_folder).join(&relative);
+        let file_name = relative.file_name().map(|name| name.to_string_lossy().to_string());
+        let options = DownloadOptions {
+            expected_size: entry.size,
+            mirrors: mirrors.to_vec(),
+            expected_md5: entry.md5.clone(),
+            expected_sha256: entry.sha256.clone(),
+            ..Default::default()
+        };
+        match engine.start_download(url.clone(), file_path.to_string_lossy().to_string(), file_name, options).await {
+            Ok(id) => import.download_ids.push(id),
+            Err(e) => import.skipped.push(format!("{}: {}", entry.name, e)),
+        }
+    }
+
+    log::info!("[DownloadList] Imported {} downloads from {} ({} skipped)", import.download_ids.len(), path, import.skipped.len());
+    Ok(import)
+}
+
+/// Write every unfinished download, in queue order, to a Metalink document or aria2 list; returns how many were written
+#[command]
+pub fn export_download_queue(
+    engine: State<'_, DownloadEngine>,
+    path: String,
+    format: Option<DownloadListFormat>,
+) -> Result<usize, String> {
+    let downloads = engine.unfinished_downloads()?;
+    let root = common_root(downloads.iter().map(|download| Path::new(&download.file_path)));
+    let entries: Vec<DownloadListEntry> = downloads.iter()
+        .map(|download| DownloadListEntry::from_download(download, &root))
+        .collect();
+    let contents = match format.unwrap_or_else(|| DownloadListFormat::from_path(Path::new(&path))) {
+        DownloadListFormat::Metalink => write_metalink(&entries),
+        DownloadListFormat::Aria2 => write_aria2_list(&entries),
+    };
+    fs::write(&path, contents)
+        .map_err(|e| format!("Failed to write download list {}: {}", path, e))?;
+
+    log::info!("[DownloadList] Exported {} downloads to {}", entries.len(), path);
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_prefixed_elements_cdata_and_entities() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- <file name="commented-out.bin"/> -->
+<ml:metalink xmlns:ml="urn:ietf:params:xml:ns:metalink">
+  <ml:file name="data/Game &amp; Data.zip">
+    <ml:size>1024</ml:size>
+    <ml:hash type="md5">0123456789ABCDEF0123456789ABCDEF</ml:hash>
+    <ml:pieces type="sha-256" length="512">
+      <ml:hash>piece</ml:hash>
+    </ml:pieces>
+    <ml:url priority="2"><![CDATA[https://mirror.example.com/game.zip?a=1&b=2]]></ml:url>
+    <ml:url priority="1">https://cdn.example.com/game.zip?a=1&amp;b=2</ml:url>
+  </ml:file>
+</ml:metalink>"#;
+
+        let entries = parse_metalink(xml).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.name, "data/Game & Data.zip");
+        assert_eq!(entry.size, Some(1024));
+        assert_eq!(entry.md5.as_deref(), Some("0123456789abcdef0123456789abcdef"));
+        assert_eq!(entry.sha256, None);
+        assert_eq!(entry.urls, vec![
+            "https://cdn.example.com/game.zip?a=1&b=2".to_string(),
+            "https://mirror.example.com/game.zip?a=1&b=2".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn only_takes_the_size_of_the_file_itself() {
+        let xml = r#"<metalink version="3.0" xmlns="http://www.metalinker.org/">
+  <files>
+    <file name="a.bin">
+      <resources><url type="http" preference="10">https://example.com/a.bin</url></resources>
+    </file>
+  </files>
+  <size>999</size>
+</metalink>"#;
+
+        let entries = parse_metalink(xml).unwrap();
+        assert_eq!(entries[0].size, None);
+        assert_eq!(entries[0].urls, vec!["https://example.com/a.bin".to_string()]);
+        assert!(parse_metalink("<html><file name=\"a\"/></html>").is_err());
+    }
+
+    #[test]
+    fn exports_paths_below_the_common_folder() {
+        let paths = [Path::new("/games/genshin/part1.zip"), Path::new("/games/genshin/audio/part2.zip")];
+        let root = common_root(paths);
+        assert_eq!(root, PathBuf::from("/games/genshin"));
+
+        let download: DownloadItem = serde_json::from_value(serde_json::json!({
+            "id": "id", "fileName": "part2.zip", "fileExtension": "zip", "totalSize": 0, "downloadedSize": 0,
+            "progress": 0.0, "speed": 0, "status": "queued", "timeRemaining": 0, "url": "https://example.com/part2.zip",
+            "filePath": "/games/genshin/audio/part2.zip", "startTime": 0, "endTime": null, "errorMessage": null,
+        })).unwrap();
+        assert_eq!(DownloadListEntry::from_download(&download, &root).name, "audio/part2.zip");
+        // Downloads with no folder in common (other drives) fall back to the file name
+        assert_eq!(DownloadListEntry::from_download(&download, Path::new("")).name, "part2.zip");
+    }
+}
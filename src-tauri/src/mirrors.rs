@@ -0,0 +1,426 @@
+//! Mirror selection module
+//! Keeps a health score per mirror host and moves a download to another mirror when its current one fails
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, State};
+
+use crate::download::DownloadEngine;
+use crate::headers::header_list;
+use crate::transport::{Clock, DownloadTransport};
+
+/// Consecutive errors on one mirror before the download moves to another
+pub const MIRROR_FAILOVER_ERRORS: u32 = 2;
+/// A host that just failed ranks below healthy ones for this long
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(120);
+/// Throughput is sampled over windows of this length
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(10);
+/// Switch when a window is slower than this fraction of the best window seen on the download
+const THROUGHPUT_DROP_RATIO: f64 = 0.25;
+/// Below this speed a drop is worth acting on, regardless of the ratio
+const THROUGHPUT_DROP_FLOOR: f64 = 4.0 * 1024.0 * 1024.0;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Default)]
+struct HostHealth {
+    successes: u32,
+    failures: u32,
+    consecutive_failures: u32,
+    throughput: f64, // moving average in bytes per second
+    last_failure: Option<Instant>,
+}
+
+impl HostHealth {
+    /// 0..=100ish, higher is better; hosts never used score 50
+    fn score(&self, now: Instant) -> f64 {
+        let reliability = f64::from(self.successes + 1) / f64::from(self.successes + self.failures + 2);
+        let mut score = reliability * 100.0;
+        score -= f64::from(self.consecutive_failures.min(5)) * 15.0;
+        if self.last_failure.is_some_and(|at| now.duration_since(at) < FAILURE_COOLDOWN) {
+            score -= 40.0;
+        }
+        // Up to 20 points for speed, one per MB/s
+        score + (self.throughput / (1024.0 * 1024.0)).min(20.0)
+    }
+}
+
+/// Health of one mirror host
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorHealthInfo {
+    pub host: String,
+    pub score: f64,
+    pub successes: u32,
+    pub failures: u32,
+    pub consecutive_failures: u32,
+    pub throughput: u64, // bytes per second
+}
+
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Health of every mirror host a download engine has used this session
+pub struct MirrorHealth {
+    hosts: Mutex<HashMap<String, HostHealth>>,
+    clock: Arc<dyn Clock>, // the engine's, so failure cooldowns follow it
+}
+
+impl MirrorHealth {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { hosts: Mutex::new(HashMap::new()), clock }
+    }
+
+    fn score(&self, url: &str) -> f64 {
+        let now = self.clock.now();
+        self.hosts.lock()
+            .ok()
+            .and_then(|health| health.get(&host_of(url)).map(|entry| entry.score(now)))
+            .unwrap_or(50.0)
+    }
+
+    /// Record a request that returned data
+    pub fn record_success(&self, url: &str) {
+        if let Ok(mut health) = self.hosts.lock() {
+            let entry = health.entry(host_of(url)).or_default();
+            entry.successes = entry.successes.saturating_add(1);
+            entry.consecutive_failures = 0;
+        }
+    }
+
+    /// Record a failed request or an HTTP error status
+    pub fn record_failure(&self, url: &str) {
+        if let Ok(mut health) = self.hosts.lock() {
+            let entry = health.entry(host_of(url)).or_default();
+            entry.failures = entry.failures.saturating_add(1);
+            entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+            entry.last_failure = Some(self.clock.now());
+        }
+    }
+
+    fn record_throughput(&self, url: &str, bytes_per_sec: f64) {
+        if let Ok(mut health) = self.hosts.lock() {
+            let entry = health.entry(host_of(url)).or_default();
+            entry.throughput = if entry.throughput > 0.0 {
+                entry.throughput * 0.7 + bytes_per_sec * 0.3
+            } else {
+                bytes_per_sec
+            };
+        }
+    }
+
+    /// Every host seen so far, healthiest first
+    pub fn hosts(&self) -> Result<Vec<MirrorHealthInfo>, String> {
+        let now = self.clock.now();
+        let health = self.hosts.lock()
+            .map_err(|e| format!("Failed to lock mirror health: {}", e))?;
+        let mut hosts: Vec<MirrorHealthInfo> = health.iter().map(|(host, entry)| MirrorHealthInfo {
+            host: host.clone(),
+            score: entry.score(now),
+            successes: entry.successes,
+            failures: entry.failures,
+            consecutive_failures: entry.consecutive_failures,
+            throughput: entry.throughput as u64,
+        }).collect();
+        hosts.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(hosts)
+    }
+}
+
+/// What a server says about the file behind a URL
+#[derive(Clone, Debug, Default)]
+pub struct RemoteFileInfo {
+    pub size: Option<u64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub accept_ranges: bool,
+}
+
+impl RemoteFileInfo {
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, size: Option<u64>) -> Self {
+        let header = |name: reqwest::header::HeaderName| {
+            headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string)
+        };
+        Self {
+            size,
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            accept_ranges: header(reqwest::header::ACCEPT_RANGES)
+                .is_some_and(|ranges| ranges.to_lowercase().contains("bytes")),
+        }
+    }
+
+    /// Value for `If-Range`: a strong ETag, else Last-Modified (weak ETags are not allowed there)
+    pub fn if_range_validator(&self) -> Option<String> {
+        self.etag.clone()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| self.last_modified.clone())
+    }
+
+    /// Check that `other` serves the same file; validators are only compared when both sides send them
+    pub fn matches(&self, other: &RemoteFileInfo) -> Result<(), String> {
+        if let Some(size) = self.size {
+            match other.size {
+                Some(other_size) if other_size == size => {}
+                Some(other_size) => return Err(format!("size {} does not match {}", other_size, size)),
+                None => return Err("size unknown".to_string()),
+            }
+        }
+        if let (Some(etag), Some(other_etag)) = (&self.etag, &other.etag) {
+            if etag.trim_start_matches("W/") != other_etag.trim_start_matches("W/") {
+                return Err(format!("ETag {} does not match {}", other_etag, etag));
+            }
+        }
+        if let (Some(modified), Some(other_modified)) = (&self.last_modified, &other.last_modified) {
+            if modified != other_modified {
+                return Err(format!("Last-Modified {} does not match {}", other_modified, modified));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// HEAD a URL and return what it says about the file
+pub async fn probe_remote_file(transport: &dyn DownloadTransport, url: &str, headers: &[(&str, String)]) -> Result<RemoteFileInfo, String> {
+    let response = tokio::time::timeout(PROBE_TIMEOUT, transport.head(url, headers)).await
+        .map_err(|_| "Failed to reach mirror: timed out".to_string())?
+        .map_err(|e| format!("Failed to reach mirror: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Mirror returned HTTP {}", response.status()));
+    }
+    Ok(RemoteFileInfo::from_headers(response.headers(), response.content_length()))
+}
+
+/// Bytes received over a fixed window, for spotting a slow mirror
+pub struct ThroughputWindow {
+    started: Instant,
+    bytes: u64,
+}
+
+impl ThroughputWindow {
+    /// Window opening at `now`, on the download engine's clock
+    pub fn starting_at(now: Instant) -> Self {
+        Self { started: now, bytes: 0 }
+    }
+
+    /// Count `bytes` received by `now`; returns the window's speed once it has run its full length
+    pub fn add(&mut self, bytes: u64, now: Instant) -> Option<f64> {
+        self.bytes += bytes;
+        let elapsed = now.duration_since(self.started);
+        if elapsed < THROUGHPUT_WINDOW {
+            return None;
+        }
+        let speed = self.bytes as f64 / elapsed.as_secs_f64();
+        *self = Self::starting_at(now);
+        Some(speed)
+    }
+}
+
+/// The mirrors of one download and which of them is in use
+pub struct MirrorSet {
+    engine: DownloadEngine,
+    download_id: String,
+    urls: Vec<String>,
+    current: AtomicUsize,
+    switching: AtomicBool,
+    state: Mutex<MirrorSetState>,
+}
+
+#[derive(Default)]
+struct MirrorSetState {
+    errors_on_current: u32,
+    best_throughput: f64,
+    rejected: HashSet<usize>, // serve a different file than the one being downloaded
+    reference: Option<RemoteFileInfo>,
+    current_info: Option<RemoteFileInfo>, // what the mirror in use reports, for If-Range
+}
+
+impl MirrorSet {
+    /// `urls` in preference order. `resume_from` pins the mirror that supplied the bytes on disk,
+    /// otherwise the healthiest mirror is used, earlier ones winning ties.
+    pub fn new(engine: DownloadEngine, download_id: &str, urls: Vec<String>, resume_from: Option<&str>) -> Self {
+        let mut unique: Vec<String> = Vec::new();
+        for url in urls {
+            let url = url.trim().to_string();
+            if !url.is_empty() && !unique.contains(&url) {
+                unique.push(url);
+            }
+        }
+
+        let start = resume_from
+            .and_then(|url| unique.iter().position(|u| u == url))
+            .unwrap_or_else(|| {
+                let health = engine.mirror_health();
+                let mut best = 0;
+                for (index, url) in unique.iter().enumerate() {
+                    if health.score(url) > health.score(&unique[best]) {
+                        best = index;
+                    }
+                }
+                best
+            });
+
+        Self {
+            engine,
+            download_id: download_id.to_string(),
+            urls: unique,
+            current: AtomicUsize::new(start),
+            switching: AtomicBool::new(false),
+            state: Mutex::new(MirrorSetState::default()),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.urls.get(self.current.load(Ordering::Relaxed)).cloned().unwrap_or_default()
+    }
+
+    pub fn download_id(&self) -> &str {
+        &self.download_id
+    }
+
+    pub fn has_alternatives(&self) -> bool {
+        self.urls.len() > 1
+    }
+
+    /// Validators of the file being downloaded, as reported by the mirror in use.
+    /// Any mirror switched to is compared against them.
+    pub fn set_reference(&self, info: RemoteFileInfo) {
+        if let Ok(mut state) = self.state.lock() {
+            state.reference = Some(info.clone());
+            state.current_info = Some(info);
+        }
+    }
+
+    /// `If-Range` value for a resumed request to the mirror in use
+    pub fn if_range(&self) -> Option<String> {
+        self.state.lock().ok()?.current_info.as_ref()?.if_range_validator()
+    }
+
+    /// Note that `url` returned data
+    pub fn report_success(&self, url: &str) {
+        self.engine.mirror_health().record_success(url);
+        if let Ok(mut state) = self.state.lock() {
+            state.errors_on_current = 0;
+        }
+    }
+
+    /// Note a failed request on `url`; true once the download should move to another mirror
+    pub fn report_error(&self, url: &str) -> bool {
+        self.engine.mirror_health().record_failure(url);
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        if url != self.current() {
+            return false;
+        }
+        state.errors_on_current += 1;
+        self.has_alternatives() && state.errors_on_current >= MIRROR_FAILOVER_ERRORS
+    }
+
+    /// Feed a throughput sample for `url`; true if it dropped far enough to try another mirror.
+    /// Ignored while a speed limit is active, since then the limit sets the pace.
+    pub fn report_throughput(&self, url: &str, bytes_per_sec: f64) -> bool {
+        self.engine.mirror_health().record_throughput(url, bytes_per_sec);
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let best = state.best_throughput;
+        state.best_throughput = best.max(bytes_per_sec);
+        self.has_alternatives()
+            && !self.engine.bandwidth_shaper().is_limited()
+            && best > 0.0
+            && bytes_per_sec < best * THROUGHPUT_DROP_RATIO
+            && bytes_per_sec < THROUGHPUT_DROP_FLOOR
+    }
+
+    /// Move away from `from_url` to the healthiest mirror that serves the same file.
+    /// With `keep_partial` (bytes already on disk) the new mirror must match the reference size
+    /// and validators and accept range requests. Returns the URL now in use if it changed.
+    pub async fn fail_over(&self, from_url: &str, keep_partial: bool, reason: &str) -> Option<String> {
+        if self.current() != from_url {
+            // Another connection of this download already switched
+            return Some(self.current());
+        }
+        if self.switching.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        let from_index = self.current.load(Ordering::Relaxed);
+        let (reference, rejected) = match self.state.lock() {
+            Ok(state) => (state.reference.clone(), state.rejected.clone()),
+            Err(_) => (None, HashSet::new()),
+        };
+
+        let mut candidates: Vec<usize> = (0..self.urls.len())
+            .filter(|index| *index != from_index && !rejected.contains(index))
+            .collect();
+        // Stable sort keeps the configured order among equally healthy mirrors
+        let health = self.engine.mirror_health();
+        candidates.sort_by(|a, b| health.score(&self.urls[*b]).total_cmp(&health.score(&self.urls[*a])));
+
+        let mut switched = None;
+        for index in candidates {
+            let url = &self.urls[index];
+            let custom_headers = self.engine.request_headers(&self.download_id, url);
+            let transport = self.engine.transport();
+            let info = match probe_remote_file(transport.as_ref(), url, &header_list(&custom_headers, Vec::new())).await {
+                Ok(info) => info,
+                Err(e) => {
+                    log::warn!("[Mirror] Skipping {} for {}: {}", host_of(url), self.download_id, e);
+                    health.record_failure(url);
+                    continue;
+                }
+            };
+
+            if keep_partial {
+                let compatible = match &reference {
+                    Some(reference) => reference.matches(&info),
+                    None => Err("validators of the partial file are unknown".to_string()),
+                }
+                .and_then(|_| if info.accept_ranges { Ok(()) } else { Err("no range support".to_string()) });
+                if let Err(e) = compatible {
+                    log::warn!("[Mirror] {} cannot continue {}: {}", host_of(url), self.download_id, e);
+                    if let Ok(mut state) = self.state.lock() {
+                        state.rejected.insert(index);
+                    }
+                    continue;
+                }
+            }
+
+            self.current.store(index, Ordering::Relaxed);
+            if let Ok(mut state) = self.state.lock() {
+                state.errors_on_current = 0;
+                state.best_throughput = 0.0;
+                if !keep_partial {
+                    state.reference = Some(info.clone());
+                }
+                state.current_info = Some(info);
+            }
+            switched = Some(url.clone());
+            break;
+        }
+
+        self.switching.store(false, Ordering::SeqCst);
+
+        match &switched {
+            Some(url) => {
+                log::info!("[Mirror] {} moved from {} to {}: {}", self.download_id, host_of(from_url), host_of(url), reason);
+                self.engine.record_mirror_switch(&self.download_id, from_url, url, reason);
+            }
+            None => log::warn!("[Mirror] No usable mirror to replace {} for {}", host_of(from_url), self.download_id),
+        }
+        switched
+    }
+}
+
+/// Health scores of all mirror hosts used this session
+#[command]
+pub fn get_mirror_health(engine: State<'_, DownloadEngine>) -> Result<Vec<MirrorHealthInfo>, String> {
+    engine.mirror_health().hosts()
+}
@@ -1,14 +1,21 @@
 //! Patch management module
 //! Handles game patching, file restoration, and patch verification
 
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use serde_json::Number;
 use serde::{Deserialize, Serialize};
 use tauri::command;
+use tokio::io::AsyncWriteExt;
 
-use crate::http::create_http_client;
+use crate::http::{create_http_client, create_streaming_http_client};
+use crate::delta::apply_bsdiff_delta;
+use crate::journal::Journal;
+use crate::system::get_yuukips_data_path;
 use crate::utils::{calculate_md5, create_parent_directories};
 
 // Global download progress state
@@ -41,6 +48,196 @@ pub struct PatchFile {
     pub location: String, // location file in game folder, so use path_folder+location
     pub md5: String, // for check match file 
     pub file: String, // file url to download
+    // Method 2 only: `file` is a bsdiff delta with MD5 `delta_md5`, applied to the file whose MD5 is `source_md5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_md5: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_md5: Option<String>,
+}
+
+/// Written to disk before a game file is touched, so a crash while patched can be undone on the next start
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum PatchJournalEntry {
+    SessionStarted {
+        game_id: Number,
+        version: String,
+        channel: Number,
+        game_folder: String,
+        started_at: u64,
+    },
+    FilePatching {
+        game_folder: String,
+        location: String,
+        had_original: bool, // false if the patch adds the file, so restoring means deleting it
+    },
+}
+
+/// A patch session that was never cleaned up, as offered for restoring on startup
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchSessionInfo {
+    pub game_id: Number,
+    pub version: String,
+    pub channel: Number,
+    pub game_folder: String,
+    pub started_at: u64,
+    pub files: Vec<String>,
+}
+
+fn patch_journal() -> Journal<PatchJournalEntry> {
+    let yuukips_dir = get_yuukips_data_path()
+        .unwrap_or_else(|_| ".".to_string());
+    Journal::new(PathBuf::from(yuukips_dir).join("patch_session.journal"))
+}
+
+/// `file.ext` -> `file.ext.{suffix}`, the naming used for `.backup` and `.patch` files
+fn sibling_path(file_path: &Path, suffix: &str) -> PathBuf {
+    file_path.with_extension(format!("{}.{}",
+        file_path.extension().and_then(|s| s.to_str()).unwrap_or(""), suffix))
+}
+
+/// Append a journal entry and force it to disk before going on
+fn journal_patch_step(journal: &mut Journal<PatchJournalEntry>, entry: &PatchJournalEntry) -> Result<(), String> {
+    journal.append(entry)
+        .and_then(|_| journal.sync())
+        .map_err(|e| format!("Failed to write patch journal: {}", e))
+}
+
+/// Journaled files as (game folder, location, had original), each once
+fn journaled_files(entries: &[PatchJournalEntry]) -> Vec<(String, String, bool)> {
+    let mut seen = HashSet::new();
+    entries.iter()
+        .filter_map(|entry| match entry {
+            PatchJournalEntry::FilePatching { game_folder, location, had_original } => {
+                Some((game_folder.clone(), location.clone(), *had_original))
+            }
+            PatchJournalEntry::SessionStarted { .. } => None,
+        })
+        .filter(|(game_folder, location, _)| seen.insert((game_folder.clone(), location.clone())))
+        .collect()
+}
+
+/// The patch session left behind by a crash or forced close, if any
+pub fn pending_patch_session() -> Option<PatchSessionInfo> {
+    let entries = patch_journal().read_all();
+    let files = journaled_files(&entries);
+    if files.is_empty() {
+        return None;
+    }
+    let started = entries.iter().rev().find_map(|entry| match entry {
+        PatchJournalEntry::SessionStarted { game_id, version, channel, game_folder, started_at } => {
+            Some((game_id.clone(), version.clone(), channel.clone(), game_folder.clone(), *started_at))
+        }
+        PatchJournalEntry::FilePatching { .. } => None,
+    });
+    let (game_id, version, channel, game_folder, started_at) = started.unwrap_or_else(|| {
+        (Number::from(0), String::new(), Number::from(0), files[0].0.clone(), 0)
+    });
+    Some(PatchSessionInfo {
+        game_id,
+        version,
+        channel,
+        game_folder,
+        started_at,
+        files: files.into_iter().map(|(_, location, _)| location).collect(),
+    })
+}
+
+/// Put the originals of every journaled file back and remove leftover `.backup`/`.patch` files
+fn undo_journaled_patches(entries: &[PatchJournalEntry]) -> Result<String, String> {
+    let mut restored = 0;
+    let mut removed = 0;
+    let mut leftovers = 0;
+    let mut failures = Vec::new();
+
+    for (game_folder, location, had_original) in journaled_files(entries) {
+        let file_path = Path::new(&game_folder).join(&location);
+        let backup_path = sibling_path(&file_path, "backup");
+        let patch_path = sibling_path(&file_path, "patch");
+
+        let result = if backup_path.exists() {
+            fs::copy(&backup_path, &file_path)
+                .and_then(|_| fs::remove_file(&backup_path))
+                .map(|_| restored += 1)
+        } else if !had_original && file_path.exists() {
+            fs::remove_file(&file_path).map(|_| removed += 1)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            failures.push(format!("{}: {}", location, e));
+            continue;
+        }
+
+        if patch_path.exists() {
+            match fs::remove_file(&patch_path) {
+                Ok(()) => leftovers += 1,
+                Err(e) => failures.push(format!("{}: {}", patch_path.display(), e)),
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(format!("Failed to restore some files: {}", failures.join(", ")));
+    }
+    Ok(format!(
+        "Restored {} original files, removed {} added files and {} leftover patch files",
+        restored, removed, leftovers
+    ))
+}
+
+/// Close the patch session once the game has stopped; kept for recovery if any backup was not put back
+pub fn end_patch_session() {
+    let mut journal = patch_journal();
+    let unrestored = journaled_files(&journal.read_all())
+        .into_iter()
+        .filter(|(game_folder, location, _)| sibling_path(&Path::new(game_folder).join(location), "backup").exists())
+        .count();
+    if unrestored > 0 {
+        log::warn!("⚠️ {} patched files still have backups, restore will be offered on next start", unrestored);
+        return;
+    }
+    if let Err(e) = journal.clear() {
+        log::warn!("⚠️ Failed to clear patch journal: {}", e);
+    }
+}
+
+/// Get the patch session left unfinished by a crash or forced close
+#[command]
+pub fn get_unfinished_patch_session() -> Result<Option<PatchSessionInfo>, String> {
+    Ok(pending_patch_session())
+}
+
+/// Undo an unfinished patch session: restore the originals, remove leftover files and turn off the proxy
+#[command]
+pub fn recover_patch_session() -> Result<String, String> {
+    let mut journal = patch_journal();
+    let entries = journal.read_all();
+    let Some(session) = pending_patch_session() else {
+        let _ = journal.clear();
+        return Ok("No unfinished patch session".to_string());
+    };
+
+    if crate::game::check_game_running_internal(&session.game_id, &session.channel).unwrap_or(false) {
+        return Err("The patched game is still running. Close it before restoring.".to_string());
+    }
+
+    if crate::proxy::is_proxy_running() {
+        if let Err(e) = crate::proxy::stop_proxy() {
+            log::warn!("⚠️ Failed to stop proxy during patch recovery: {}", e);
+        }
+    }
+    match crate::system::check_and_disable_windows_proxy() {
+        Ok(message) => log::info!("🔧 Patch recovery proxy check: {}", message),
+        Err(e) => log::warn!("⚠️ Patch recovery proxy check failed: {}", e),
+    }
+
+    log::info!("🔄 Recovering unfinished patch session for {} ({} files)", session.game_folder, session.files.len());
+    let message = undo_journaled_patches(&entries)?;
+    journal.clear()?;
+    log::info!("✅ {}", message);
+    Ok(message)
 }
 
 /// Get current download progress
@@ -122,7 +319,14 @@ pub fn restore_game_files(
         let patch_response = fetch_patch_info(game_id, version, channel, md5).await
             .map_err(|e| format!("Failed to fetch patch info for restoration: {}", e))?;
         
-        restore_original_files(&patch_response, &game_folder_path)
+        let (restored, failures) = restore_originals(&patch_response, &game_folder_path).await;
+        let message = restore_summary(&restored, &failures);
+        if !failures.is_empty() {
+            return Err(message);
+        }
+        // The originals are back, so an unfinished patch session for them is over too
+        end_patch_session();
+        Ok(message)
     })
 }
 
@@ -134,6 +338,14 @@ pub fn check_and_apply_patches(
     md5: String,
     game_folder_path: String,
 ) -> Result<(String, Option<PatchResponse>, Vec<String>), String> {
+    // Patching again would back up the patched files of the old session in place of the originals
+    if let Some(session) = pending_patch_session() {
+        return Err(format!(
+            "An unfinished patch session in {} must be restored before patching again",
+            session.game_folder
+        ));
+    }
+    
     // Ensure proxy is stopped before patching
     if crate::proxy::is_proxy_running() {
         log::info!("🔧 Stopping proxy before applying patches...");
@@ -145,8 +357,8 @@ pub fn check_and_apply_patches(
         .map_err(|e| format!("Failed to create async runtime: {}", e))?;
     
     rt.block_on(async {
-        // Fetch patch information
-        let patch_response = fetch_patch_info(game_id.clone(), version, channel.clone(), md5).await
+        // Fetch patch information, from the cache if the API is unreachable
+        let (patch_response, cached_at) = fetch_patch_info_or_cached(game_id.clone(), version.clone(), channel.clone(), md5).await
             .map_err(|e| format!("Failed to fetch patch info: {}", e))?;
         
         // Check if game is running and try to kill it if needed
@@ -173,25 +385,116 @@ pub fn check_and_apply_patches(
                 log::info!("✅ No patches needed for this game version");
                 Vec::new()
             }
-            1 => {
-                // Method 1: Apply file patches
-                apply_file_patches(&patch_response, &game_folder_path).await?
+            1 | 2 => {
+                // Method 1: Apply file patches, method 2: build them from binary deltas first.
+                // Each file is journaled before it is touched so a crash can be undone
+                let mut journal = patch_journal();
+                journal_patch_step(&mut journal, &PatchJournalEntry::SessionStarted {
+                    game_id: game_id.clone(),
+                    version: version.clone(),
+                    channel: channel.clone(),
+                    game_folder: game_folder_path.clone(),
+                    started_at: chrono::Utc::now().timestamp().max(0) as u64,
+                })?;
+                match apply_file_patches(&patch_response, &game_folder_path, &mut journal).await {
+                    Ok(files) => files,
+                    Err(e) => {
+                        // Don't leave the game half patched
+                        match undo_journaled_patches(&journal.read_all()) {
+                            Ok(message) => {
+                                log::info!("🔄 {}", message);
+                                let _ = journal.clear();
+                            }
+                            Err(undo_error) => log::error!("⚠️ Failed to undo partial patch: {}", undo_error),
+                        }
+                        return Err(e);
+                    }
+                }
             }
             _ => {
                 return Err(format!("Unsupported patch method: {}", patch_response.metode));
             }
         };
         
-        let message = if patched_files.is_empty() {
+        let mut message = if patched_files.is_empty() {
             "No patches applied".to_string()
         } else {
             format!("Applied {} patches successfully", patched_files.len())
         };
+        if let Some(fetched_at) = cached_at {
+            message = format!("{}: {}", offline_patch_status(fetched_at), message);
+        }
         
         Ok((message, Some(patch_response), patched_files))
     })
 }
 
+/// Last patch plan the API returned for a game build, used when the API can't be reached
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedPatchManifest {
+    pub response: PatchResponse,
+    pub fetched_at: i64, // seconds since epoch
+}
+
+fn patch_manifest_cache_path() -> PathBuf {
+    let yuukips_dir = get_yuukips_data_path()
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(yuukips_dir).join("patch_manifests.json")
+}
+
+fn patch_manifest_key(game_id: &Number, version: &str, channel: &Number, md5: &str) -> String {
+    format!("{}/{}/{}/{}", game_id, version, channel, md5.to_lowercase())
+}
+
+fn load_patch_manifests() -> HashMap<String, CachedPatchManifest> {
+    fs::read_to_string(patch_manifest_cache_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn cache_patch_manifest(key: String, response: &PatchResponse) -> Result<(), String> {
+    let mut manifests = load_patch_manifests();
+    manifests.insert(key, CachedPatchManifest {
+        response: response.clone(),
+        fetched_at: chrono::Utc::now().timestamp(),
+    });
+    let json = serde_json::to_string_pretty(&manifests)
+        .map_err(|e| format!("Failed to serialize patch manifests: {}", e))?;
+    crate::utils::write_file_atomic(&patch_manifest_cache_path(), json.as_bytes())
+}
+
+/// Status line for a launch that had to use a cached patch plan
+pub fn offline_patch_status(fetched_at: i64) -> String {
+    let date = chrono::DateTime::from_timestamp(fetched_at, 0)
+        .map(|date| date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "an unknown date".to_string());
+    format!("Offline, using cached patch from {}", date)
+}
+
+/// Fetch the patch plan, falling back to the cached one when the API can't be reached.
+/// The second value is when the cached plan was fetched, or `None` if it came from the API.
+pub async fn fetch_patch_info_or_cached(
+    game_id: Number,
+    version: String,
+    channel: Number,
+    md5: String,
+) -> Result<(PatchResponse, Option<i64>), String> {
+    let key = patch_manifest_key(&game_id, &version, &channel, &md5);
+    match fetch_patch_info(game_id, version, channel, md5).await {
+        Ok(response) => Ok((response, None)),
+        // A 404 is the API's answer for this build, not an outage
+        Err(e) if e.starts_with("PATCH_ERROR_404:") => Err(e),
+        Err(e) => match load_patch_manifests().remove(&key) {
+            Some(cached) => {
+                log::warn!("⚠️ Patch API unreachable ({}). {}", e, offline_patch_status(cached.fetched_at));
+                Ok((cached.response, Some(cached.fetched_at)))
+            }
+            None => Err(e),
+        },
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PatchErrorInfo {
     pub game_id: String,
@@ -255,6 +558,10 @@ pub async fn fetch_patch_info(
     log::info!("📦 Patch info received: method={}, proxy={}, files={}", 
              patch_response.metode, patch_response.proxy, patch_response.patched.len());
     
+    if let Err(e) = cache_patch_manifest(patch_manifest_key(&game_id, &version, &channel, &md5), &patch_response) {
+        log::warn!("⚠️ Failed to cache patch manifest: {}", e);
+    }
+    
     Ok(patch_response)
 }
 
@@ -262,6 +569,7 @@ pub async fn fetch_patch_info(
 async fn apply_file_patches(
     patch_response: &PatchResponse,
     game_folder_path: &str,
+    journal: &mut Journal<PatchJournalEntry>,
 ) -> Result<Vec<String>, String> {
     let mut patched_files = Vec::new();
     let cache_dir = Path::new(game_folder_path).join(".patch_cache");
@@ -272,6 +580,13 @@ async fn apply_file_patches(
             .map_err(|e| format!("Failed to create patch cache directory: {}", e))?;
     }
     
+    // Check every delta's source up front, so a mismatching install fails before anything is patched
+    if patch_response.metode == 2 {
+        for patch_file in &patch_response.patched {
+            delta_source(patch_file, game_folder_path)?;
+        }
+    }
+    
     for (index, patch_file) in patch_response.patched.iter().enumerate() {
         let file_path = Path::new(game_folder_path).join(&patch_file.location);
         let cache_file_path = cache_dir.join(format!("{}.patch", patch_file.location.replace(['/', '\\'], "_")));
@@ -296,12 +611,20 @@ async fn apply_file_patches(
             }
         }
         
-        if !use_cached {
+        if !use_cached && patch_response.metode == 2 {
+            build_patch_from_delta(patch_file, game_folder_path, &cache_file_path).await?;
+        } else if !use_cached {
             // Download the patch file
             download_and_verify_file(&patch_file.file, &cache_file_path, &patch_file.md5.to_uppercase()).await
                 .map_err(|e| format!("Failed to download patch for {}: {}", patch_file.location, e))?;
         }
         
+        journal_patch_step(journal, &PatchJournalEntry::FilePatching {
+            game_folder: game_folder_path.to_string(),
+            location: patch_file.location.clone(),
+            had_original: file_path.exists(),
+        })?;
+        
         // Create backup of original file if it exists
         if file_path.exists() {
             let backup_path = file_path.with_extension(format!("{}.backup", 
@@ -330,90 +653,188 @@ async fn apply_file_patches(
     Ok(patched_files)
 }
 
-/// Download and verify a file
-async fn download_and_verify_file(
-    url: &str,
-    file_path: &Path,
-    expected_md5: &str,
-) -> Result<(), String> {
-    let client = create_http_client(false)?;
+/// The unpatched file a method 2 delta applies to: the game file itself, or its backup if the game file was changed
+fn delta_source(patch_file: &PatchFile, game_folder_path: &str) -> Result<PathBuf, String> {
+    let source_md5 = patch_file.source_md5.as_ref()
+        .ok_or_else(|| format!("Delta patch for {} has no source MD5", patch_file.location))?
+        .to_uppercase();
+    let file_path = Path::new(game_folder_path).join(&patch_file.location);
+    [file_path.clone(), sibling_path(&file_path, "backup")]
+        .into_iter()
+        .find(|path| path.exists() && calculate_md5(path).is_ok_and(|md5| md5.to_uppercase() == source_md5))
+        .ok_or_else(|| format!(
+            "Cannot patch {}: the file doesn't match the version the delta was made for (expected MD5 {})",
+            patch_file.location, source_md5
+        ))
+}
+
+/// Download a method 2 delta, apply it to the verified source file and keep the result at `cache_file_path`
+async fn build_patch_from_delta(patch_file: &PatchFile, game_folder_path: &str, cache_file_path: &Path) -> Result<(), String> {
+    let source_path = delta_source(patch_file, game_folder_path)?;
+    let delta_md5 = patch_file.delta_md5.as_ref()
+        .ok_or_else(|| format!("Delta patch for {} has no delta MD5", patch_file.location))?
+        .to_uppercase();
     
-    // Update progress
-    {
-        let mut progress = DOWNLOAD_PROGRESS.lock()
-            .map_err(|e| format!("Failed to lock download progress: {}", e))?;
-        progress.current_file = file_path.file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        progress.status = "downloading".to_string();
-        progress.downloaded = 0;
-        progress.percentage = 0.0;
+    let delta_path = sibling_path(cache_file_path, "delta");
+    let cached_delta = delta_path.exists()
+        && calculate_md5(&delta_path).is_ok_and(|md5| md5.to_uppercase() == delta_md5);
+    if !cached_delta {
+        download_and_verify_file(&patch_file.file, &delta_path, &delta_md5).await
+            .map_err(|e| format!("Failed to download delta for {}: {}", patch_file.location, e))?;
     }
     
-    log::info!("⬇️ Downloading: {} -> {}", url, file_path.display());
-    
-    let response = client.get(url)
-        .send()
+    log::info!("🧩 Applying delta to {}", source_path.display());
+    let source = fs::read(&source_path)
+        .map_err(|e| format!("Failed to read {}: {}", source_path.display(), e))?;
+    let delta = fs::read(&delta_path)
+        .map_err(|e| format!("Failed to read delta for {}: {}", patch_file.location, e))?;
+    let patched = tokio::task::spawn_blocking(move || apply_bsdiff_delta(&source, &delta))
         .await
-        .map_err(|e| format!("Failed to start download: {}", e))?;
+        .map_err(|e| format!("Delta task failed: {}", e))?
+        .map_err(|e| format!("Failed to apply delta for {}: {}", patch_file.location, e))?;
     
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {}", response.status()));
+    let patched_md5 = format!("{:x}", md5::compute(&patched));
+    if patched_md5.to_uppercase() != patch_file.md5.to_uppercase() {
+        return Err(format!(
+            "Patched {} doesn't match: expected MD5 {}, got {}",
+            patch_file.location, patch_file.md5.to_uppercase(), patched_md5.to_uppercase()
+        ));
     }
     
-    let total_size = response.content_length().unwrap_or(0);
-    
-    // Update total size
-    {
-        let mut progress = DOWNLOAD_PROGRESS.lock()
-            .map_err(|e| format!("Failed to lock download progress: {}", e))?;
+    crate::utils::write_file_atomic(cache_file_path, &patched)?;
+    // The verified result is cached, so the delta is no longer needed
+    let _ = fs::remove_file(&delta_path);
+    log::info!("✅ Built patch from delta: {}", patch_file.location);
+    Ok(())
+}
+
+/// Attempts per patch file before giving up; each retry resumes where the last one stopped
+const PATCH_DOWNLOAD_ATTEMPTS: u32 = 5;
+/// A read that delivers nothing for this long counts as a failed attempt
+const PATCH_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const PATCH_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Bytes of a patch file received so far, with the MD5 of exactly those bytes
+struct PartialPatchFile {
+    path: PathBuf,
+    hasher: md5::Context,
+    len: u64,
+    validator: Option<String>, // ETag or Last-Modified of the response the bytes came from, sent as If-Range
+}
+
+impl PartialPatchFile {
+    /// Continue a `.part` file left by an earlier attempt or run, hashing what it already holds.
+    /// A `.part` file without a saved validator can't be checked against the server and starts over.
+    fn open(path: PathBuf) -> Result<Self, String> {
+        let validator = fs::read_to_string(Self::validator_path(&path)).ok()
+            .filter(|validator| !validator.is_empty());
+        let mut partial = Self { path, hasher: md5::Context::new(), len: 0, validator };
+        if partial.path.exists() && partial.validator.is_none() {
+            log::info!("🔄 No validator saved for {}, downloading it again", partial.path.display());
+            fs::remove_file(&partial.path)
+                .map_err(|e| format!("Failed to remove partial download: {}", e))?;
+        }
+        if partial.path.exists() {
+            let mut file = fs::File::open(&partial.path)
+                .map_err(|e| format!("Failed to open partial download: {}", e))?;
+            let mut buffer = vec![0u8; 1024 * 1024];
+            loop {
+                let read = file.read(&mut buffer)
+                    .map_err(|e| format!("Failed to read partial download: {}", e))?;
+                if read == 0 {
+                    break;
+                }
+                partial.hasher.consume(&buffer[..read]);
+                partial.len += read as u64;
+            }
+        }
+        Ok(partial)
+    }
+
+    fn validator_path(path: &Path) -> PathBuf {
+        sibling_path(path, "validator")
+    }
+
+    /// Remember which version of the file the received bytes belong to
+    fn set_validator(&mut self, validator: Option<String>) -> Result<(), String> {
+        let validator_path = Self::validator_path(&self.path);
+        match &validator {
+            Some(validator) => crate::utils::write_file_atomic(&validator_path, validator.as_bytes())?,
+            None => {
+                let _ = fs::remove_file(&validator_path);
+            }
+        }
+        self.validator = validator;
+        Ok(())
+    }
+
+    /// Drop what was received, for servers that answer a range request with the whole file
+    fn restart(&mut self) {
+        self.hasher = md5::Context::new();
+        self.len = 0;
+    }
+
+    /// Remove the `.part` file and its validator
+    fn discard(&self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_file(Self::validator_path(&self.path));
+    }
+}
+
+fn set_download_progress(file_path: &Path, status: &str, downloaded: u64, total_size: u64) {
+    if let Ok(mut progress) = DOWNLOAD_PROGRESS.lock() {
+        progress.current_file = file_path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        progress.status = status.to_string();
+        progress.downloaded = downloaded;
         progress.total_size = total_size;
+        progress.percentage = if total_size > 0 {
+            (downloaded as f64 / total_size as f64) * 100.0
+        } else {
+            0.0
+        };
     }
-    
-    // Create parent directories
+}
+
+/// Download a file through a `.part` file next to it, moving it to `file_path` only once its MD5 matches
+async fn download_and_verify_file(
+    url: &str,
+    file_path: &Path,
+    expected_md5: &str,
+) -> Result<(), String> {
+    let client = create_streaming_http_client(false)?;
     create_parent_directories(file_path)?;
+    let mut partial = PartialPatchFile::open(sibling_path(file_path, "part"))?;
     
-    // Download the entire response
-    let file_contents = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-    
-    let downloaded = file_contents.len() as u64;
-    
-    // Update progress to verifying
-    {
-        if let Ok(mut progress) = DOWNLOAD_PROGRESS.lock() {
-            progress.downloaded = downloaded;
-            progress.total_size = total_size;
-            progress.percentage = 100.0;
-            progress.status = "verifying".to_string();
-            progress.current_file = file_path.file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-        }
-    }
+    log::info!("⬇️ Downloading: {} -> {}", url, file_path.display());
+    set_download_progress(file_path, "downloading", partial.len, 0);
     
-    // Write to file
-    fs::write(file_path, &file_contents)
-        .map_err(|e| format!("Failed to write file: {}", e))?;
+    let mut attempt = 1;
+    let total_size = loop {
+        match download_attempt(&client, url, file_path, &mut partial).await {
+            Ok(total_size) => break total_size,
+            Err(e) if attempt < PATCH_DOWNLOAD_ATTEMPTS => {
+                let delay = Duration::from_secs(1 << (attempt - 1)).min(PATCH_RETRY_MAX_DELAY);
+                log::warn!("⚠️ Patch download attempt {}/{} failed for {}: {}. Resuming from {} bytes in {}s",
+                    attempt, PATCH_DOWNLOAD_ATTEMPTS, url, e, partial.len, delay.as_secs());
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                set_download_progress(file_path, "failed", partial.len, 0);
+                return Err(format!("{} (after {} attempts)", e, attempt));
+            }
+        }
+    };
     
-    // Verify MD5
-    let actual_md5 = format!("{:x}", md5::compute(&file_contents));
+    set_download_progress(file_path, "verifying", partial.len, total_size);
+    let actual_md5 = format!("{:x}", partial.hasher.clone().compute());
     if actual_md5.to_uppercase() != expected_md5.to_uppercase() {
-        // Update progress with failed status
-        if let Ok(mut progress) = DOWNLOAD_PROGRESS.lock() {
-            progress.downloaded = downloaded;
-            progress.total_size = total_size;
-            progress.percentage = 100.0;
-            progress.status = "failed".to_string();
-            progress.current_file = file_path.file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-        }
+        // Resuming can't fix a corrupt file, so the next try starts over
+        partial.discard();
+        set_download_progress(file_path, "failed", partial.len, total_size);
         return Err(format!(
             "MD5 mismatch for {}: expected {}, got {}",
             file_path.display(),
@@ -422,71 +843,193 @@ async fn download_and_verify_file(
         ));
     }
     
-    // Update progress to completed
-    {
-        if let Ok(mut progress) = DOWNLOAD_PROGRESS.lock() {
-            progress.downloaded = downloaded;
-            progress.total_size = total_size;
-            progress.percentage = 100.0;
-            progress.status = "completed".to_string();
-            progress.current_file = file_path.file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-        }
-    }
+    fs::rename(&partial.path, file_path)
+        .map_err(|e| format!("Failed to move download into place: {}", e))?;
+    partial.discard();
+    set_download_progress(file_path, "completed", partial.len, total_size);
     
     log::info!("✅ Download verified: {}", file_path.display());
     Ok(())
 }
 
-/// Restore original files by downloading them
+/// One request for the rest of the file, appended to the `.part` file; returns the total size
+async fn download_attempt(
+    client: &reqwest::Client,
+    url: &str,
+    file_path: &Path,
+    partial: &mut PartialPatchFile,
+) -> Result<u64, String> {
+    let mut request = client.get(url);
+    if partial.len > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", partial.len));
+        // A server whose file changed since then sends the whole new file instead of the rest
+        if let Some(validator) = &partial.validator {
+            request = request.header(reqwest::header::IF_RANGE, validator.clone());
+        }
+    }
+    let mut response = tokio::time::timeout(PATCH_STALL_TIMEOUT, request.send()).await
+        .map_err(|_| "Timed out waiting for the server".to_string())?
+        .map_err(|e| format!("Failed to start download: {}", e))?;
+    
+    let status = response.status();
+    if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && partial.len > 0 {
+        // Everything was already received; the MD5 check decides whether it's right
+        return Ok(partial.len);
+    }
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}", status));
+    }
+    
+    let resumed = status == reqwest::StatusCode::PARTIAL_CONTENT;
+    if resumed {
+        // The rest has to start exactly where the `.part` file ends, or the bytes would not line up
+        let range_start = response.headers().get(reqwest::header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|value| value.split('-').next())
+            .and_then(|start| start.trim().parse::<u64>().ok());
+        if range_start != Some(partial.len) {
+            let expected = partial.len;
+            partial.restart();
+            return Err(format!("Server resumed {} at {:?} instead of byte {}, restarting", url, range_start, expected));
+        }
+    } else {
+        if partial.len > 0 {
+            log::info!("🔄 Server sent the whole file instead of the rest, restarting {}", url);
+            partial.restart();
+        }
+        let info = crate::mirrors::RemoteFileInfo::from_headers(response.headers(), response.content_length());
+        partial.set_validator(info.if_range_validator())?;
+    }
+    let total_size = response.content_length().map_or(0, |length| length + partial.len);
+    
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial.path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", partial.path.display(), e))?;
+    
+    // Read the response chunk by chunk so it draws from the shared bandwidth limit
+    let transfer = crate::bandwidth::BANDWIDTH_SHAPER.register(url, crate::bandwidth::PATCH_WEIGHT);
+    loop {
+        let chunk = tokio::time::timeout(PATCH_STALL_TIMEOUT, response.chunk()).await
+            .map_err(|_| format!("No data received for {}s", PATCH_STALL_TIMEOUT.as_secs()))?
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        transfer.acquire(chunk.len()).await;
+        file.write_all(&chunk).await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        partial.hasher.consume(&chunk);
+        partial.len += chunk.len() as u64;
+        set_download_progress(file_path, "downloading", partial.len, total_size);
+    }
+    
+    file.sync_all().await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    if total_size > 0 && partial.len < total_size {
+        return Err(format!("Connection closed after {} of {} bytes", partial.len, total_size));
+    }
+    Ok(partial.len)
+}
+
+/// Restore the official files of a patch plan, returning a summary, the locations now back to original
+/// and the locations that could not be restored and are still patched
 pub fn restore_original_files(
     patch_response: &PatchResponse,
     game_folder_path: &str,
-) -> Result<String, String> {
+) -> Result<(String, Vec<String>, Vec<String>), String> {
     let rt = tokio::runtime::Runtime::new()
         .map_err(|e| format!("Failed to create async runtime: {}", e))?;
     
-    rt.block_on(async {
-        let mut restored_files = Vec::new();
-        
-        for patch_file in &patch_response.patched {
-            let file_path = Path::new(game_folder_path).join(&patch_file.file);
-            
-            // Save current file as .patch if it exists
-            if file_path.exists() {
-                let patch_backup_path = file_path.with_extension(format!("{}.patch", 
-                    file_path.extension().and_then(|s| s.to_str()).unwrap_or("")));
-                
-                fs::copy(&file_path, &patch_backup_path)
-                    .map_err(|e| format!("Failed to backup patched file {}: {}", patch_file.file, e))?;
+    let (restored, failures) = rt.block_on(restore_originals(patch_response, game_folder_path));
+    Ok((restore_summary(&restored, &failures), restored, failures))
+}
+
+fn restore_summary(restored: &[String], failures: &[String]) -> String {
+    if failures.is_empty() {
+        format!("Restored {} original files", restored.len())
+    } else {
+        format!("Restored {} original files, {} failed: {}", restored.len(), failures.len(), failures.join(", "))
+    }
+}
+
+/// Put back every file of the `original` list, from a matching backup or else downloaded and verified,
+/// and the backups of patched files the list doesn't cover. Returns the restored locations and the failures.
+async fn restore_originals(patch_response: &PatchResponse, game_folder_path: &str) -> (Vec<String>, Vec<String>) {
+    let mut restored = Vec::new();
+    let mut failures = Vec::new();
+    
+    for (index, original) in patch_response.original.iter().enumerate() {
+        log::info!("🔄 Restoring original {}/{}: {}", index + 1, patch_response.original.len(), original.location);
+        match restore_original_file(original, game_folder_path).await {
+            Ok(()) => restored.push(original.location.clone()),
+            Err(e) => {
+                log::error!("⚠️ {}", e);
+                failures.push(original.location.clone());
             }
-            
-            // Download original file (assuming the API provides original file URLs)
-            // This would need to be implemented based on your API structure
-            // For now, we'll try to restore from backup
-            let backup_path = file_path.with_extension(format!("{}.backup", 
-                file_path.extension().and_then(|s| s.to_str()).unwrap_or("")));
-            
-            if backup_path.exists() {
-                fs::copy(&backup_path, &file_path)
-                    .map_err(|e| format!("Failed to restore from backup {}: {}", patch_file.file, e))?;
-                
-                // Remove backup after successful restoration
-                fs::remove_file(&backup_path)
-                    .map_err(|e| format!("Failed to remove backup {}: {}", backup_path.display(), e))?;
-                
-                restored_files.push(patch_file.file.clone());
+        }
+    }
+    
+    // Files the API has no original for can only come back from their backup
+    for patch_file in &patch_response.patched {
+        if patch_response.original.iter().any(|original| original.location == patch_file.location) {
+            continue;
+        }
+        let file_path = Path::new(game_folder_path).join(&patch_file.location);
+        let backup_path = sibling_path(&file_path, "backup");
+        if !backup_path.exists() {
+            continue;
+        }
+        match fs::rename(&backup_path, &file_path) {
+            Ok(()) => restored.push(patch_file.location.clone()),
+            Err(e) => {
+                log::error!("⚠️ Failed to restore from backup {}: {}", patch_file.location, e);
+                failures.push(patch_file.location.clone());
             }
         }
-        
-        if restored_files.is_empty() {
-            Ok("No files were restored (no backups found)".to_string())
-        } else {
-            Ok(format!("Restored {} files from backups", restored_files.len()))
+    }
+    
+    (restored, failures)
+}
+
+/// Bring one file back to its official version; the file is only ever replaced by a rename
+async fn restore_original_file(original: &PatchFile, game_folder_path: &str) -> Result<(), String> {
+    let file_path = Path::new(game_folder_path).join(&original.location);
+    let backup_path = sibling_path(&file_path, "backup");
+    let expected_md5 = original.md5.to_uppercase();
+    let matches_original = |path: &Path| {
+        path.exists() && calculate_md5(path).is_ok_and(|md5| md5.to_uppercase() == expected_md5)
+    };
+    
+    if matches_original(&file_path) {
+        log::info!("✅ Already original: {}", original.location);
+    } else if matches_original(&backup_path) {
+        fs::rename(&backup_path, &file_path)
+            .map_err(|e| format!("Failed to restore from backup {}: {}", original.location, e))?;
+        log::info!("💾 Restored from backup: {}", original.location);
+    } else {
+        // Downloaded next to the file, so the swap is a rename on the same volume
+        let download_path = sibling_path(&file_path, "original");
+        if let Err(e) = download_and_verify_file(&original.file, &download_path, &expected_md5).await {
+            let _ = fs::remove_file(&download_path);
+            return Err(format!("Failed to download original for {}: {}", original.location, e));
         }
-    })
+        fs::rename(&download_path, &file_path)
+            .map_err(|e| format!("Failed to swap in original for {}: {}", original.location, e))?;
+        log::info!("⬇️ Restored from server: {}", original.location);
+    }
+    
+    // A backup that doesn't match the official file is stale
+    if backup_path.exists() {
+        fs::remove_file(&backup_path)
+            .map_err(|e| format!("Failed to remove backup {}: {}", backup_path.display(), e))?;
+    }
+    Ok(())
 }
 
 /// Restore files from .backup files
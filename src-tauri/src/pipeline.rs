@@ -0,0 +1,678 @@
+//! Completion pipeline module
+//! Runs follow-up actions once a download or a whole download group has completed: verify, extract, clean up, register and open
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+use tauri::{command, State};
+use uuid::Uuid;
+
+use crate::download::{calculate_checksum, ActivityType, DownloadEngine, DownloadItem, DownloadStatus};
+use crate::extract::{extract_archive_blocking, get_archive_volumes, is_archive_volume_name};
+
+/// Pushed with the whole pipeline whenever it or one of its steps changes status
+pub const PIPELINE_EVENT: &str = "download-pipeline";
+/// Pushed when a RegisterGame step succeeds; the frontend keeps game folders, so it stores the path
+pub const GAME_INSTALL_REGISTERED_EVENT: &str = "game-install-registered";
+
+/// One thing to do with the files of a finished download or group
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum CompletionAction {
+    /// Hash every file with an expected MD5 or SHA-256 again
+    VerifyHash,
+    /// Unpack every archive among the files into `target_folder`
+    Extract { target_folder: String },
+    /// Delete the archive volumes an earlier Extract step unpacked
+    DeleteArchives,
+    /// Record the folder as the install of a game version; defaults to the extract folder
+    RegisterGame {
+        game_id: Number,
+        channel: Number,
+        version: String,
+        #[serde(default)]
+        game_folder: Option<String>,
+    },
+    /// Show the folder in the file explorer; defaults to the extract folder
+    OpenFolder {
+        #[serde(default)]
+        folder: Option<String>,
+    },
+}
+
+impl CompletionAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::VerifyHash => "Verify hash",
+            Self::Extract { .. } => "Extract",
+            Self::DeleteArchives => "Delete archives",
+            Self::RegisterGame { .. } => "Register game",
+            Self::OpenFolder { .. } => "Open folder",
+        }
+    }
+}
+
+/// What a pipeline waits for: one download, or every download of a group
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PipelineTarget {
+    Download(String),
+    Group(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStatus {
+    Waiting,   // the target has not completed yet
+    Running,
+    Failed,    // stopped at a failed step, which a retry runs again
+    Completed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Done,
+    Skipped, // nothing to do for these files
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineStep {
+    pub action: CompletionAction,
+    pub status: StepStatus,
+    pub message: Option<String>, // result of the step, or why it failed or was skipped
+    pub attempts: u32,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionPipeline {
+    pub id: String,
+    pub target: PipelineTarget,
+    pub status: PipelineStatus,
+    pub steps: Vec<PipelineStep>,
+    #[serde(default)]
+    pub extracted_archives: Vec<String>, // volumes unpacked by Extract, for DeleteArchives
+    pub created_at: u64,
+}
+
+impl CompletionPipeline {
+    /// Folder the later steps work in: the last extract target, else the folder of the first file
+    fn working_folder(&self, downloads: &[DownloadItem]) -> Option<String> {
+        self.steps.iter()
+            .rev()
+            .find_map(|step| match &step.action {
+                CompletionAction::Extract { target_folder } => Some(target_folder.clone()),
+                _ => None,
+            })
+            .or_else(|| {
+                downloads.first()
+                    .and_then(|download| Path::new(&download.file_path).parent().map(|p| p.to_string_lossy().to_string()))
+            })
+    }
+}
+
+/// Payload of `GAME_INSTALL_REGISTERED_EVENT`
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GameInstallRegistration {
+    game_id: Number,
+    channel: Number,
+    version: String,
+    folder: String,
+}
+
+enum StepOutcome {
+    Done(String),
+    Skipped(String),
+}
+
+/// Completion pipelines of one engine, persisted to completion_pipelines.json in its data directory
+pub struct PipelineStore {
+    path: PathBuf,
+    pipelines: Mutex<Vec<CompletionPipeline>>,
+    cancel_flags: Mutex<HashMap<String, Arc<AtomicBool>>>, // one per running pipeline, set by cancel_completion_pipeline
+}
+
+impl PipelineStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("completion_pipelines.json");
+        let mut pipelines: Vec<CompletionPipeline> = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::error!("Failed to parse completion pipelines: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        // A step that was running when the launcher closed has to be retried
+        for pipeline in pipelines.iter_mut().filter(|p| p.status == PipelineStatus::Running) {
+            pipeline.status = PipelineStatus::Failed;
+            for step in pipeline.steps.iter_mut().filter(|s| s.status == StepStatus::Running) {
+                step.status = StepStatus::Failed;
+                step.message = Some("Interrupted when the launcher closed".to_string());
+            }
+        }
+        Self { path, pipelines: Mutex::new(pipelines), cancel_flags: Mutex::new(HashMap::new()) }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<CompletionPipeline>>, String> {
+        self.pipelines.lock()
+            .map_err(|e| format!("Failed to lock completion pipelines: {}", e))
+    }
+
+    fn save(&self, pipelines: &[CompletionPipeline]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(pipelines)
+            .map_err(|e| format!("Failed to serialize completion pipelines: {}", e))?;
+        crate::utils::write_file_atomic(&self.path, json.as_bytes())
+    }
+
+    fn cancel_flags(&self) -> MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.cancel_flags.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Cancel flag for a run of a pipeline, kept until the run ends
+    fn begin_run(&self, pipeline_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.cancel_flags().insert(pipeline_id.to_string(), flag.clone());
+        flag
+    }
+
+    fn end_run(&self, pipeline_id: &str) {
+        self.cancel_flags().remove(pipeline_id);
+    }
+
+    /// Ask a running pipeline to stop; returns whether it was running
+    fn cancel(&self, pipeline_id: &str) -> bool {
+        match self.cancel_flags().get(pipeline_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Change one pipeline under the lock and save; returns the closure's result and the changed pipeline
+fn update_pipeline<R>(
+    engine: &DownloadEngine,
+    pipeline_id: &str,
+    update: impl FnOnce(&mut CompletionPipeline) -> R,
+) -> Result<(R, CompletionPipeline), String> {
+    let store = engine.pipelines();
+    let mut pipelines = store.lock()?;
+    let pipeline = pipelines.iter_mut()
+        .find(|p| p.id == pipeline_id)
+        .ok_or_else(|| format!("Completion pipeline {} not found", pipeline_id))?;
+    let result = update(pipeline);
+    let pipeline = pipeline.clone();
+    store.save(&pipelines)?;
+    Ok((result, pipeline))
+}
+
+/// DeleteArchives only makes sense after an Extract step has succeeded
+fn validate_actions(actions: &[CompletionAction]) -> Result<(), String> {
+    if actions.is_empty() {
+        return Err("No completion actions given".to_string());
+    }
+    let mut extracts = false;
+    for action in actions {
+        match action {
+            CompletionAction::Extract { target_folder } if target_folder.trim().is_empty() => {
+                return Err("Extract needs a target folder".to_string());
+            }
+            CompletionAction::Extract { .. } => extracts = true,
+            CompletionAction::DeleteArchives if !extracts => {
+                return Err("Delete archives must come after an Extract action".to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The downloads a pipeline covers, sorted by path
+fn target_downloads(engine: &DownloadEngine, target: &PipelineTarget) -> Result<Vec<DownloadItem>, String> {
+    let mut downloads = match target {
+        PipelineTarget::Download(download_id) => engine.download(download_id)?.into_iter().collect(),
+        PipelineTarget::Group(group_id) => engine.get_group_downloads(group_id)?,
+    };
+    downloads.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    Ok(downloads)
+}
+
+/// Start a waiting pipeline if everything it covers has completed; returns whether it started
+fn start_if_ready(engine: &DownloadEngine, pipeline_id: &str) -> Result<bool, String> {
+    let target = {
+        let pipelines = engine.pipelines().lock()?;
+        pipelines.iter()
+            .find(|p| p.id == pipeline_id)
+            .map(|p| p.target.clone())
+            .ok_or_else(|| format!("Completion pipeline {} not found", pipeline_id))?
+    };
+
+    let downloads = target_downloads(engine, &target)?;
+    if downloads.is_empty() {
+        return Err("The downloads of this pipeline no longer exist".to_string());
+    }
+    if downloads.iter().any(|d| d.status != DownloadStatus::Completed) {
+        return Ok(false);
+    }
+
+    // Completions of several group members can race here; only the first one moves it out of Waiting
+    let (started, pipeline) = update_pipeline(engine, pipeline_id, |pipeline| {
+        if pipeline.status != PipelineStatus::Waiting {
+            return false;
+        }
+        pipeline.status = PipelineStatus::Running;
+        true
+    })?;
+    if started {
+        log::info!("[Pipeline] Starting completion pipeline {} ({} step(s))", pipeline.id, pipeline.steps.len());
+        engine.emit_event(PIPELINE_EVENT, &pipeline);
+        tauri::async_runtime::spawn(run_pipeline(engine.clone(), pipeline.id));
+    }
+    Ok(started)
+}
+
+/// Called by the download manager (with its lock held) when a download completes
+pub fn on_download_completed(engine: DownloadEngine, download_id: String, group_id: Option<String>) {
+    let waiting: Vec<String> = match engine.pipelines().lock() {
+        Ok(pipelines) => pipelines.iter()
+            .filter(|p| p.status == PipelineStatus::Waiting)
+            .filter(|p| match &p.target {
+                PipelineTarget::Download(id) => *id == download_id,
+                PipelineTarget::Group(id) => group_id.as_deref() == Some(id.as_str()),
+            })
+            .map(|p| p.id.clone())
+            .collect(),
+        Err(e) => {
+            log::error!("{}", e);
+            return;
+        }
+    };
+    if waiting.is_empty() {
+        return;
+    }
+
+    // Checking readiness needs the download manager, so it runs once the caller has let go of it
+    tauri::async_runtime::spawn(async move {
+        for pipeline_id in waiting {
+            if let Err(e) = start_if_ready(&engine, &pipeline_id) {
+                log::warn!("[Pipeline] Could not start completion pipeline {}: {}", pipeline_id, e);
+            }
+        }
+    });
+}
+
+/// Run the steps that are not done yet, in order, stopping at the first failure or when cancelled
+async fn run_pipeline(engine: DownloadEngine, pipeline_id: String) {
+    let cancel_flag = engine.pipelines().begin_run(&pipeline_id);
+    run_steps(&engine, &pipeline_id, &cancel_flag).await;
+    engine.pipelines().end_run(&pipeline_id);
+}
+
+async fn run_steps(engine: &DownloadEngine, pipeline_id: &str, cancel_flag: &Arc<AtomicBool>) {
+    loop {
+        if cancel_flag.load(Ordering::Relaxed) {
+            // The remaining steps stay pending for a retry
+            match update_pipeline(engine, pipeline_id, |pipeline| pipeline.status = PipelineStatus::Failed) {
+                Ok(((), pipeline)) => engine.emit_event(PIPELINE_EVENT, &pipeline),
+                Err(e) => log::error!("[Pipeline] {}", e),
+            }
+            log::info!("[Pipeline] Completion pipeline {} cancelled", pipeline_id);
+            return;
+        }
+
+        let now = engine.clock().unix_time();
+        let next = update_pipeline(engine, pipeline_id, |pipeline| {
+            let index = pipeline.steps.iter()
+                .position(|step| !matches!(step.status, StepStatus::Done | StepStatus::Skipped));
+            match index {
+                Some(index) => {
+                    let step = &mut pipeline.steps[index];
+                    step.status = StepStatus::Running;
+                    step.message = None;
+                    step.attempts += 1;
+                    step.started_at = Some(now);
+                    step.finished_at = None;
+                    Some((index, step.action.clone()))
+                }
+                None => {
+                    pipeline.status = PipelineStatus::Completed;
+                    None
+                }
+            }
+        });
+        let (next, pipeline) = match next {
+            Ok(result) => result,
+            Err(e) => {
+                log::error!("[Pipeline] {}", e);
+                return;
+            }
+        };
+        engine.emit_event(PIPELINE_EVENT, &pipeline);
+
+        let Some((index, action)) = next else {
+            log::info!("[Pipeline] Completion pipeline {} finished", pipeline_id);
+            return;
+        };
+
+        log::info!("[Pipeline] {}: running step {} ({})", pipeline_id, index + 1, action.name());
+        let mut working = pipeline;
+        let result = run_step(engine, &mut working, &action, cancel_flag).await;
+
+        let finished_at = engine.clock().unix_time();
+        let (status, message) = match &result {
+            Ok(StepOutcome::Done(message)) => (StepStatus::Done, message.clone()),
+            Ok(StepOutcome::Skipped(message)) => (StepStatus::Skipped, message.clone()),
+            Err(e) => {
+                log::error!("[Pipeline] {}: step {} ({}) failed: {}", pipeline_id, index + 1, action.name(), e);
+                (StepStatus::Failed, e.clone())
+            }
+        };
+        let failed = status == StepStatus::Failed;
+        let updated = update_pipeline(engine, pipeline_id, |pipeline| {
+            pipeline.extracted_archives = working.extracted_archives;
+            let step = &mut pipeline.steps[index];
+            step.status = status;
+            step.message = Some(message.clone());
+            step.finished_at = Some(finished_at);
+            if failed {
+                pipeline.status = PipelineStatus::Failed;
+            }
+        });
+        let pipeline = match updated {
+            Ok(((), pipeline)) => pipeline,
+            Err(e) => {
+                log::error!("[Pipeline] {}", e);
+                return;
+            }
+        };
+
+        engine.record_activity(
+            ActivityType::CompletionAction,
+            None,
+            Some(pipeline_id.to_string()),
+            Some(if failed { "error" } else { "completed" }.to_string()),
+            Some(format!("{}: {}", action.name(), message))
+        );
+        engine.emit_event(PIPELINE_EVENT, &pipeline);
+
+        if failed {
+            return;
+        }
+    }
+}
+
+async fn run_step(
+    engine: &DownloadEngine,
+    pipeline: &mut CompletionPipeline,
+    action: &CompletionAction,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<StepOutcome, String> {
+    match action {
+        CompletionAction::DeleteArchives => return delete_archives(&pipeline.extracted_archives),
+        CompletionAction::OpenFolder { folder: Some(folder) } => return open_folder(folder),
+        _ => {}
+    }
+
+    let downloads = target_downloads(engine, &pipeline.target)?;
+    if downloads.is_empty() {
+        return Err("The downloads of this pipeline no longer exist".to_string());
+    }
+
+    match action {
+        CompletionAction::VerifyHash => verify_hashes(&downloads).await,
+        CompletionAction::Extract { target_folder } => {
+            extract_downloads(engine, &downloads, target_folder, &mut pipeline.extracted_archives, cancel_flag).await
+        }
+        CompletionAction::RegisterGame { game_id, channel, version, game_folder } => {
+            let folder = game_folder.clone()
+                .or_else(|| pipeline.working_folder(&downloads))
+                .ok_or_else(|| "No game folder to register".to_string())?;
+            register_game(engine, game_id, channel, version, folder)
+        }
+        CompletionAction::OpenFolder { .. } => {
+            let folder = pipeline.working_folder(&downloads)
+                .ok_or_else(|| "No folder to open".to_string())?;
+            open_folder(&folder)
+        }
+        CompletionAction::DeleteArchives => unreachable!("handled above"),
+    }
+}
+
+async fn verify_hashes(downloads: &[DownloadItem]) -> Result<StepOutcome, String> {
+    let mut verified = 0;
+    let mut mismatches = Vec::new();
+    for download in downloads {
+        let Some((algorithm, expected)) = download.expected_checksum() else {
+            continue;
+        };
+        let actual = calculate_checksum(algorithm, Path::new(&download.file_path)).await
+            .map_err(|e| format!("Failed to hash {}: {}", download.file_name, e))?;
+        if actual == expected {
+            verified += 1;
+        } else {
+            mismatches.push(format!("{} ({} {}, expected {})", download.file_name, algorithm, actual, expected));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        return Err(format!("Checksum mismatch: {}", mismatches.join(", ")));
+    }
+    if verified == 0 {
+        return Ok(StepOutcome::Skipped("No file has an expected hash".to_string()));
+    }
+    Ok(StepOutcome::Done(format!("Verified {} file(s)", verified)))
+}
+
+/// Extract each distinct archive among the downloads; extraction resumes from its journal on a retry
+async fn extract_downloads(
+    engine: &DownloadEngine,
+    downloads: &[DownloadItem],
+    target_folder: &str,
+    extracted_archives: &mut Vec<String>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<StepOutcome, String> {
+    let mut archives: Vec<(String, Vec<String>)> = Vec::new();
+    let mut seen = HashSet::new();
+    for download in downloads {
+        let is_archive = Path::new(&download.file_path)
+            .file_name()
+            .is_some_and(|name| is_archive_volume_name(&name.to_string_lossy()));
+        if !is_archive {
+            continue;
+        }
+        let volumes = get_archive_volumes(download.file_path.clone())?;
+        if seen.insert(volumes.clone()) {
+            archives.push((download.file_path.clone(), volumes));
+        }
+    }
+    if archives.is_empty() {
+        return Err("None of the downloaded files is an archive".to_string());
+    }
+
+    let mut entries = 0;
+    for (archive_path, volumes) in &archives {
+        log::info!("[Pipeline] Extracting {} into {}", archive_path, target_folder);
+        let app_handle = engine.event_handle();
+        let archive = PathBuf::from(archive_path);
+        let target = PathBuf::from(target_folder);
+        let cancel_flag = cancel_flag.clone();
+        entries += tokio::task::spawn_blocking(move || {
+            extract_archive_blocking(&archive, &target, app_handle, cancel_flag)
+        })
+        .await
+        .map_err(|e| format!("Extraction task failed: {}", e))??;
+
+        for volume in volumes {
+            if !extracted_archives.contains(volume) {
+                extracted_archives.push(volume.clone());
+            }
+        }
+    }
+    Ok(StepOutcome::Done(format!("Extracted {} entries from {} archive(s) into {}", entries, archives.len(), target_folder)))
+}
+
+fn delete_archives(extracted_archives: &[String]) -> Result<StepOutcome, String> {
+    if extracted_archives.is_empty() {
+        return Ok(StepOutcome::Skipped("No archives were extracted".to_string()));
+    }
+
+    let mut deleted = 0;
+    for archive in extracted_archives {
+        let path = Path::new(archive);
+        if !path.exists() {
+            continue;
+        }
+        fs::remove_file(path)
+            .map_err(|e| format!("Failed to delete {}: {}", archive, e))?;
+        deleted += 1;
+    }
+    Ok(StepOutcome::Done(format!("Deleted {} archive file(s)", deleted)))
+}
+
+fn register_game(
+    engine: &DownloadEngine,
+    game_id: &Number,
+    channel: &Number,
+    version: &str,
+    folder: String,
+) -> Result<StepOutcome, String> {
+    if !Path::new(&folder).is_dir() {
+        return Err(format!("Game folder not found: {}", folder));
+    }
+    #[cfg(target_os = "windows")]
+    crate::game::validate_game_directory(game_id.clone(), channel.clone(), folder.clone())?;
+
+    engine.emit_event(GAME_INSTALL_REGISTERED_EVENT, GameInstallRegistration {
+        game_id: game_id.clone(),
+        channel: channel.clone(),
+        version: version.to_string(),
+        folder: folder.clone(),
+    });
+    Ok(StepOutcome::Done(format!("Registered {} as game {} version {} (channel {})", folder, game_id, version, channel)))
+}
+
+fn open_folder(folder: &str) -> Result<StepOutcome, String> {
+    crate::system::open_directory(folder.to_string()).map(StepOutcome::Done)
+}
+
+/// Attach completion actions to a download or group, replacing any it already has; runs at once if it has completed
+#[command]
+pub fn set_completion_actions(
+    engine: State<'_, DownloadEngine>,
+    target: PipelineTarget,
+    actions: Vec<CompletionAction>,
+) -> Result<CompletionPipeline, String> {
+    validate_actions(&actions)?;
+    if target_downloads(&engine, &target)?.is_empty() {
+        return Err("No download matches the completion target".to_string());
+    }
+
+    let pipeline = CompletionPipeline {
+        id: Uuid::new_v4().to_string(),
+        target: target.clone(),
+        status: PipelineStatus::Waiting,
+        steps: actions.into_iter()
+            .map(|action| PipelineStep {
+                action,
+                status: StepStatus::Pending,
+                message: None,
+                attempts: 0,
+                started_at: None,
+                finished_at: None,
+            })
+            .collect(),
+        extracted_archives: Vec::new(),
+        created_at: engine.clock().unix_time(),
+    };
+
+    {
+        let store = engine.pipelines();
+        let mut pipelines = store.lock()?;
+        if pipelines.iter().any(|p| p.target == target && p.status == PipelineStatus::Running) {
+            return Err("Completion actions are already running for this download".to_string());
+        }
+        pipelines.retain(|p| p.target != target);
+        pipelines.push(pipeline.clone());
+        store.save(&pipelines)?;
+    }
+    engine.emit_event(PIPELINE_EVENT, &pipeline);
+
+    start_if_ready(&engine, &pipeline.id)?;
+    Ok(engine.pipelines().lock()
+        .ok()
+        .and_then(|pipelines| pipelines.iter().find(|p| p.id == pipeline.id).cloned())
+        .unwrap_or(pipeline))
+}
+
+#[command]
+pub fn get_completion_pipelines(engine: State<'_, DownloadEngine>) -> Result<Vec<CompletionPipeline>, String> {
+    let pipelines = engine.pipelines().lock()?;
+    Ok(pipelines.clone())
+}
+
+/// Run a failed pipeline again from the step that failed
+#[command]
+pub fn retry_completion_pipeline(engine: State<'_, DownloadEngine>, pipeline_id: String) -> Result<(), String> {
+    let (retried, pipeline) = update_pipeline(&engine, &pipeline_id, |pipeline| {
+        if pipeline.status != PipelineStatus::Failed {
+            return false;
+        }
+        pipeline.status = PipelineStatus::Waiting;
+        for step in pipeline.steps.iter_mut().filter(|s| s.status == StepStatus::Failed) {
+            step.status = StepStatus::Pending;
+        }
+        true
+    })?;
+    if !retried {
+        return Err("Only a failed completion pipeline can be retried".to_string());
+    }
+    engine.emit_event(PIPELINE_EVENT, &pipeline);
+
+    start_if_ready(&engine, &pipeline_id)?;
+    Ok(())
+}
+
+/// Stop a running pipeline; an extraction in progress stops at its next entry and resumes on a retry
+#[command]
+pub fn cancel_completion_pipeline(engine: State<'_, DownloadEngine>, pipeline_id: String) -> Result<(), String> {
+    if engine.pipelines().cancel(&pipeline_id) {
+        log::info!("[Pipeline] Cancel requested for completion pipeline {}", pipeline_id);
+        Ok(())
+    } else {
+        Err("Only a running completion pipeline can be cancelled".to_string())
+    }
+}
+
+#[command]
+pub fn remove_completion_pipeline(engine: State<'_, DownloadEngine>, pipeline_id: String) -> Result<(), String> {
+    let store = engine.pipelines();
+    let mut pipelines = store.lock()?;
+    let pipeline = pipelines.iter()
+        .find(|p| p.id == pipeline_id)
+        .ok_or_else(|| format!("Completion pipeline {} not found", pipeline_id))?;
+    if pipeline.status == PipelineStatus::Running {
+        return Err("Cannot remove a completion pipeline while it is running".to_string());
+    }
+    pipelines.retain(|p| p.id != pipeline_id);
+    store.save(&pipelines)
+}
@@ -0,0 +1,261 @@
+//! Download scheduling module
+//! Weekly time windows that decide when queued downloads may run and which speed limit applies
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{command, State};
+
+use crate::download::DownloadEngine;
+use crate::settings::AppSettings;
+
+/// How often the monitor looks for window boundaries
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// How far ahead to look for the next window boundary
+const SCHEDULE_LOOKAHEAD_MINUTES: i64 = 8 * 24 * 60;
+
+static SCHEDULE_MONITOR_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// A recurring weekly time range, in local time.
+/// `start` and `end` are "HH:MM"; an `end` at or before `start` runs past midnight
+/// into the next day. Empty `days` means every day.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScheduleWindow {
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: String,
+    pub end: String,
+}
+
+/// A time range with its own speed limit in MB/s (0 = unlimited)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpeedWindow {
+    #[serde(flatten)]
+    pub window: ScheduleWindow,
+    pub speed_limit_mbps: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DownloadSchedule {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Windows in which queued downloads may run; empty means any time
+    #[serde(default)]
+    pub download_windows: Vec<ScheduleWindow>,
+    /// Speed limits that replace the global limit while their window is open; the first match wins
+    #[serde(default)]
+    pub speed_windows: Vec<SpeedWindow>,
+}
+
+/// Current schedule state as shown to the user
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleStatus {
+    pub enabled: bool,
+    pub downloads_allowed: bool,
+    pub speed_limit_mbps: f64,
+    /// Local time at which `downloads_allowed` next changes, if within a week
+    pub next_download_change: Option<String>,
+    /// Local time at which the speed limit next changes, if within a week
+    pub next_speed_change: Option<String>,
+}
+
+fn parse_time(value: &str) -> Result<u32, String> {
+    let time = NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|e| format!("Invalid time '{}', expected HH:MM: {}", value, e))?;
+    Ok(time.hour() * 60 + time.minute())
+}
+
+impl ScheduleWindow {
+    fn validate(&self) -> Result<(), String> {
+        parse_time(&self.start)?;
+        parse_time(&self.end)?;
+        Ok(())
+    }
+
+    fn runs_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, now: &DateTime<Local>) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let minute = now.hour() * 60 + now.minute();
+        let today = now.weekday();
+
+        if start < end {
+            self.runs_on(today) && minute >= start && minute < end
+        } else {
+            // Overnight window: the part after midnight belongs to the day it started on
+            (self.runs_on(today) && minute >= start) || (self.runs_on(today.pred()) && minute < end)
+        }
+    }
+}
+
+impl DownloadSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for window in &self.download_windows {
+            window.validate()?;
+        }
+        for speed_window in &self.speed_windows {
+            speed_window.window.validate()?;
+            if !speed_window.speed_limit_mbps.is_finite() || speed_window.speed_limit_mbps < 0.0 {
+                return Err("Scheduled speed limit must be 0 (unlimited) or a positive number".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn downloads_allowed_at(&self, now: &DateTime<Local>) -> bool {
+        !self.enabled
+            || self.download_windows.is_empty()
+            || self.download_windows.iter().any(|window| window.contains(now))
+    }
+
+    /// Speed limit of the first open speed window, if any
+    pub fn speed_limit_at(&self, now: &DateTime<Local>) -> Option<f64> {
+        if !self.enabled {
+            return None;
+        }
+        self.speed_windows.iter()
+            .find(|speed_window| speed_window.window.contains(now))
+            .map(|speed_window| speed_window.speed_limit_mbps)
+    }
+
+    /// First minute after `now` at which `value` changes
+    fn next_change<T: PartialEq>(&self, now: &DateTime<Local>, value: impl Fn(&DateTime<Local>) -> T) -> Option<DateTime<Local>> {
+        if !self.enabled {
+            return None;
+        }
+        let current = value(now);
+        let minute_start = now.with_second(0)?.with_nanosecond(0)?;
+        (1..=SCHEDULE_LOOKAHEAD_MINUTES)
+            .map(|offset| minute_start + ChronoDuration::minutes(offset))
+            .find(|candidate| value(candidate) != current)
+    }
+
+    pub fn next_download_change(&self, now: &DateTime<Local>) -> Option<DateTime<Local>> {
+        self.next_change(now, |time| self.downloads_allowed_at(time))
+    }
+
+    pub fn next_speed_change(&self, now: &DateTime<Local>) -> Option<DateTime<Local>> {
+        self.next_change(now, |time| self.speed_limit_at(time).map(f64::to_bits))
+    }
+}
+
+impl AppSettings {
+    /// Speed limit in effect at `now`, taking speed windows into account
+    pub fn effective_speed_limit_mbps(&self, now: &DateTime<Local>) -> f64 {
+        self.download_schedule.speed_limit_at(now).unwrap_or(self.speed_limit_mbps)
+    }
+}
+
+fn format_local_time(time: &DateTime<Local>) -> String {
+    time.format("%a %H:%M").to_string()
+}
+
+/// Why queued downloads may not start at `now` under `schedule`, or None if they may
+pub fn schedule_wait_reason(schedule: &DownloadSchedule, now: &DateTime<Local>) -> Option<String> {
+    if schedule.downloads_allowed_at(now) {
+        return None;
+    }
+    Some(match schedule.next_download_change(now) {
+        Some(opens) => format!("Waiting for the download window to open at {}", format_local_time(&opens)),
+        None => "Waiting for a download window".to_string(),
+    })
+}
+
+/// Watch for window boundaries and pause, resume or re-limit downloads when one passes
+pub fn start_schedule_monitor(engine: DownloadEngine) {
+    if SCHEDULE_MONITOR_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_allowed: Option<bool> = None;
+        let mut last_speed_limit: Option<f64> = None;
+
+        let settings = engine.settings();
+        let clock = engine.clock();
+        loop {
+            let snapshot = match settings.lock() {
+                Ok(settings) => {
+                    let now = clock.local_now();
+                    let schedule = &settings.download_schedule;
+                    Ok((
+                        schedule.downloads_allowed_at(&now),
+                        settings.effective_speed_limit_mbps(&now),
+                        schedule.next_download_change(&now),
+                    ))
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            let (allowed, speed_limit, next_change) = match snapshot {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    log::error!("[Schedule] Failed to read settings: {}", e);
+                    clock.sleep(SCHEDULE_CHECK_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            if last_speed_limit != Some(speed_limit) {
+                if let Ok(settings) = settings.lock() {
+                    engine.apply_speed_limit(&settings);
+                }
+                last_speed_limit = Some(speed_limit);
+            }
+
+            if last_allowed != Some(allowed) {
+                let next = next_change.as_ref().map(format_local_time);
+                if let Err(e) = engine.apply_download_window(allowed, last_allowed.is_some(), next) {
+                    log::error!("[Schedule] Failed to apply download window: {}", e);
+                }
+                last_allowed = Some(allowed);
+            }
+
+            clock.sleep(SCHEDULE_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[command]
+pub fn get_app_download_schedule(engine: State<'_, DownloadEngine>) -> Result<DownloadSchedule, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(settings.download_schedule.clone())
+}
+
+#[command]
+pub fn set_app_download_schedule(engine: State<'_, DownloadEngine>, schedule: DownloadSchedule) -> Result<(), String> {
+    schedule.validate()?;
+
+    let now = engine.clock().local_now();
+    let allowed = schedule.downloads_allowed_at(&now);
+    let next = schedule.next_download_change(&now).as_ref().map(format_local_time);
+    {
+        let settings = engine.settings();
+        let mut settings = settings.lock().map_err(|e| format!("Lock error: {}", e))?;
+        settings.download_schedule = schedule;
+        engine.apply_speed_limit(&settings);
+        settings.save().map_err(|e| format!("Save error: {}", e))?;
+    }
+
+    // Apply the new window right away instead of waiting for the monitor
+    engine.apply_download_window(allowed, false, next)
+}
+
+#[command]
+pub fn get_download_schedule_status(engine: State<'_, DownloadEngine>) -> Result<ScheduleStatus, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let schedule = &settings.download_schedule;
+    let now = engine.clock().local_now();
+    Ok(ScheduleStatus {
+        enabled: schedule.enabled,
+        downloads_allowed: schedule.downloads_allowed_at(&now),
+        speed_limit_mbps: settings.effective_speed_limit_mbps(&now),
+        next_download_change: schedule.next_download_change(&now).as_ref().map(format_local_time),
+        next_speed_change: schedule.next_speed_change(&now).as_ref().map(format_local_time),
+    })
+}
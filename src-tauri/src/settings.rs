@@ -1,14 +1,28 @@
+use crate::download::DownloadEngine;
+use crate::headers::HostHeaderRule;
+use crate::schedule::DownloadSchedule;
 use crate::system::get_yuukips_data_path;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use tauri::command;
+use std::sync::{Arc, Mutex};
+use tauri::{command, State};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AppSettings {
     pub speed_limit_mbps: f64,
     pub divide_speed_enabled: bool,
     pub max_simultaneous_downloads: u32,
+    #[serde(default = "default_download_segments")]
+    pub download_segments: u32,
+    #[serde(default)]
+    pub download_schedule: DownloadSchedule,
+    #[serde(default)]
+    pub download_header_rules: Vec<HostHeaderRule>, // extra headers for downloads from matching hosts
+}
+
+fn default_download_segments() -> u32 {
+    4
 }
 
 impl Default for AppSettings {
@@ -17,6 +31,9 @@ impl Default for AppSettings {
             speed_limit_mbps: 0.0,
             divide_speed_enabled: false,
             max_simultaneous_downloads: 3,
+            download_segments: default_download_segments(),
+            download_schedule: DownloadSchedule::default(),
+            download_header_rules: Vec::new(),
         }
     }
 }
@@ -84,74 +101,92 @@ impl AppSettings {
     }
 }
 
+/// Settings shared between the commands and the download engine that runs on them
+pub type SharedSettings = Arc<Mutex<AppSettings>>;
+
 // Global settings instance
-pub static SETTINGS: once_cell::sync::Lazy<std::sync::Mutex<AppSettings>> =
-    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(AppSettings::load()));
+pub static SETTINGS: once_cell::sync::Lazy<SharedSettings> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(AppSettings::load())));
 
 #[command]
-pub fn get_app_speed_limit() -> Result<f64, String> {
-    let settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn get_app_speed_limit(engine: State<'_, DownloadEngine>) -> Result<f64, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     Ok(settings.speed_limit_mbps)
 }
 
 #[command]
-pub fn set_app_speed_limit(speed_limit_mbps: f64) -> Result<(), String> {
-    let mut settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn set_app_speed_limit(engine: State<'_, DownloadEngine>, speed_limit_mbps: f64) -> Result<(), String> {
+    let mut settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     settings.speed_limit_mbps = speed_limit_mbps;
+    engine.apply_speed_limit(&settings);
     settings.save().map_err(|e| format!("Save error: {}", e))?;
     Ok(())
 }
 
 #[command]
-pub fn get_app_divide_speed_enabled() -> Result<bool, String> {
-    let settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn get_app_divide_speed_enabled(engine: State<'_, DownloadEngine>) -> Result<bool, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     Ok(settings.divide_speed_enabled)
 }
 
 #[command]
-pub fn set_app_divide_speed_enabled(enabled: bool) -> Result<(), String> {
-    let mut settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn set_app_divide_speed_enabled(engine: State<'_, DownloadEngine>, enabled: bool) -> Result<(), String> {
+    let mut settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     settings.divide_speed_enabled = enabled;
+    engine.apply_speed_limit(&settings);
     settings.save().map_err(|e| format!("Save error: {}", e))?;
     Ok(())
 }
 
 #[command]
-pub fn get_app_max_simultaneous_downloads() -> Result<u32, String> {
-    let settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn get_app_max_simultaneous_downloads(engine: State<'_, DownloadEngine>) -> Result<u32, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     Ok(settings.max_simultaneous_downloads)
 }
 
 #[command]
-pub fn set_app_max_simultaneous_downloads(max_downloads: u32) -> Result<(), String> {
+pub fn set_app_max_simultaneous_downloads(engine: State<'_, DownloadEngine>, max_downloads: u32) -> Result<(), String> {
     // Validate the input (minimum 1, maximum 64 for reasonable limits)
     if max_downloads < 1 || max_downloads > 64 {
         return Err("Max simultaneous downloads must be between 1 and 64".to_string());
     }
     
     let old_limit = {
-        let settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
-        settings.max_simultaneous_downloads
+        let mut settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+        let old_limit = settings.max_simultaneous_downloads;
+        settings.max_simultaneous_downloads = max_downloads;
+        settings.save().map_err(|e| format!("Save error: {}", e))?;
+        old_limit
     };
     
-    let mut settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
-    settings.max_simultaneous_downloads = max_downloads;
-    settings.save().map_err(|e| format!("Save error: {}", e))?;
-    
-    // Release the settings lock before calling download manager
-    drop(settings);
-    
     // Trigger queue management in download manager
-    crate::download::trigger_queue_management_on_settings_change(max_downloads, old_limit)
+    engine.trigger_queue_management_on_settings_change(max_downloads, old_limit)
         .map_err(|e| format!("Failed to update download queue: {}", e))?;
     
     Ok(())
 }
 
+#[command]
+pub fn get_app_download_segments(engine: State<'_, DownloadEngine>) -> Result<u32, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    Ok(settings.download_segments)
+}
 
+#[command]
+pub fn set_app_download_segments(engine: State<'_, DownloadEngine>, segments: u32) -> Result<(), String> {
+    // Validate the input (1 disables segmented downloads, 16 keeps servers happy)
+    if !(1..=16).contains(&segments) {
+        return Err("Download segments must be between 1 and 16".to_string());
+    }
+    
+    let mut settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
+    settings.download_segments = segments;
+    settings.save().map_err(|e| format!("Save error: {}", e))?;
+    Ok(())
+}
 
 #[command]
-pub fn get_all_app_settings() -> Result<AppSettings, String> {
-    let settings = SETTINGS.lock().map_err(|e| format!("Lock error: {}", e))?;
+pub fn get_all_app_settings(engine: State<'_, DownloadEngine>) -> Result<AppSettings, String> {
+    let settings = engine.settings().lock().map_err(|e| format!("Lock error: {}", e))?;
     Ok(settings.clone())
 }
@@ -68,6 +68,77 @@ pub fn is_running_as_admin() -> bool {
     }
 }
 
+/// Closest existing ancestor of a path, so space can be queried for files that don't exist yet
+fn nearest_existing_path(path: &std::path::Path) -> Result<&std::path::Path, String> {
+    path.ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .ok_or_else(|| format!("No existing directory found for path: {}", path.display()))
+}
+
+/// Bytes available to the current user on the volume holding `path`
+#[cfg(target_os = "windows")]
+pub fn get_free_disk_space(path: &std::path::Path) -> Result<u64, String> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+
+    let existing = nearest_existing_path(path)?;
+    let wide_path: Vec<u16> = existing.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+
+    unsafe {
+        let mut free_bytes: winapi::shared::ntdef::ULARGE_INTEGER = std::mem::zeroed();
+        let result = GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &mut free_bytes,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if result == 0 {
+            return Err(format!("Failed to get disk space: {}", std::io::Error::last_os_error()));
+        }
+        Ok(*free_bytes.QuadPart())
+    }
+}
+
+/// Bytes available to the current user on the volume holding `path` (non-Windows)
+#[cfg(not(target_os = "windows"))]
+pub fn get_free_disk_space(path: &std::path::Path) -> Result<u64, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = nearest_existing_path(path)?;
+    let c_path = CString::new(existing.as_os_str().as_bytes())
+        .map_err(|e| format!("Invalid path for disk space query: {}", e))?;
+
+    unsafe {
+        let mut stats: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stats) != 0 {
+            return Err(format!("Failed to get disk space: {}", std::io::Error::last_os_error()));
+        }
+        Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+}
+
+/// Identifier of the volume holding `path`, used to group downloads that share free space
+#[cfg(target_os = "windows")]
+pub fn get_volume_id(path: &std::path::Path) -> Option<String> {
+    use std::path::Component;
+
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) => Some(prefix.as_os_str().to_string_lossy().to_uppercase()),
+        _ => None,
+    }
+}
+
+/// Identifier of the volume holding `path`, used to group downloads that share free space (non-Windows)
+#[cfg(not(target_os = "windows"))]
+pub fn get_volume_id(path: &std::path::Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let existing = nearest_existing_path(path).ok()?;
+    std::fs::metadata(existing).ok().map(|metadata| metadata.dev().to_string())
+}
+
 /// Check if running as admin without returning error
 #[command]
 pub fn is_admin() -> bool {
@@ -0,0 +1,195 @@
+//! Throughput history module
+//! Rolling record of the bytes per second downloads moved, per download and overall, with hourly and daily totals
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// Overall samples kept: an hour of transfer at one sample per second
+const MAX_SAMPLES: usize = 3600;
+const MAX_DOWNLOAD_SAMPLES: usize = 600;
+/// Downloads with their own series; the one idle the longest is dropped first
+const MAX_TRACKED_DOWNLOADS: usize = 20;
+const MAX_HOURLY_BUCKETS: usize = 7 * 24;
+const MAX_DAILY_BUCKETS: usize = 90;
+const HOUR_SECS: u64 = 60 * 60;
+const DAY_SECS: u64 = 24 * HOUR_SECS;
+
+/// Bytes moved during one second
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ThroughputSample {
+    pub timestamp: u64, // seconds since the epoch
+    pub bytes_per_second: u64,
+}
+
+/// Transfer totals for one hour or day (UTC)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ThroughputBucket {
+    pub start: u64,
+    pub bytes: u64,
+    pub active_seconds: u64, // seconds in which anything was transferred, so bytes / active_seconds is the average rate
+    pub peak_bytes_per_second: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadThroughput {
+    pub download_id: String,
+    pub samples: VecDeque<ThroughputSample>,
+    pub total_bytes: u64,
+    pub peak_bytes_per_second: u64,
+    #[serde(skip)]
+    current: Option<ThroughputSample>, // second still being filled
+}
+
+/// Seconds without a sample were idle; the series only hold seconds that moved data
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ThroughputHistory {
+    pub samples: VecDeque<ThroughputSample>,
+    pub hourly: VecDeque<ThroughputBucket>,
+    pub daily: VecDeque<ThroughputBucket>,
+    pub downloads: Vec<DownloadThroughput>, // most recently active last
+    #[serde(skip)]
+    current: Option<ThroughputSample>,
+}
+
+/// Add `bytes` to the sample for second `now`, returning the previous sample once its second is over
+fn add_to_current(current: &mut Option<ThroughputSample>, bytes: u64, now: u64) -> Option<ThroughputSample> {
+    match current {
+        Some(sample) if sample.timestamp == now => {
+            sample.bytes_per_second += bytes;
+            None
+        }
+        _ => current.replace(ThroughputSample { timestamp: now, bytes_per_second: bytes }),
+    }
+}
+
+/// Take the current sample if its second is over
+fn take_finished(current: &mut Option<ThroughputSample>, now: u64) -> Option<ThroughputSample> {
+    if current.as_ref().is_some_and(|sample| sample.timestamp < now) {
+        current.take()
+    } else {
+        None
+    }
+}
+
+fn push_bounded<T>(items: &mut VecDeque<T>, item: T, max: usize) {
+    items.push_back(item);
+    while items.len() > max {
+        items.pop_front();
+    }
+}
+
+fn add_to_bucket(buckets: &mut VecDeque<ThroughputBucket>, sample: &ThroughputSample, width: u64, max: usize) {
+    let start = sample.timestamp - sample.timestamp % width;
+    match buckets.back_mut() {
+        Some(bucket) if bucket.start == start => {
+            bucket.bytes += sample.bytes_per_second;
+            bucket.active_seconds += 1;
+            bucket.peak_bytes_per_second = bucket.peak_bytes_per_second.max(sample.bytes_per_second);
+        }
+        _ => push_bounded(buckets, ThroughputBucket {
+            start,
+            bytes: sample.bytes_per_second,
+            active_seconds: 1,
+            peak_bytes_per_second: sample.bytes_per_second,
+        }, max),
+    }
+}
+
+impl DownloadThroughput {
+    fn finish_sample(&mut self, sample: ThroughputSample) {
+        self.peak_bytes_per_second = self.peak_bytes_per_second.max(sample.bytes_per_second);
+        push_bounded(&mut self.samples, sample, MAX_DOWNLOAD_SAMPLES);
+    }
+}
+
+impl ThroughputHistory {
+    /// History saved at `path`, or an empty one if there is none or it can't be read
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::error!("Failed to parse throughput history: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        // Compact, the series hold thousands of samples
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize throughput history: {}", e))?;
+        crate::utils::write_file_atomic(path, json.as_bytes())
+            .map_err(|e| format!("Failed to save throughput history: {}", e))
+    }
+
+    /// Count `bytes` transferred by a download at second `now`
+    pub fn record(&mut self, download_id: &str, bytes: u64, now: u64) {
+        if bytes == 0 {
+            return;
+        }
+        self.flush(now);
+        if let Some(sample) = add_to_current(&mut self.current, bytes, now) {
+            self.finish_sample(sample);
+        }
+
+        // Move the download to the back, so the front is always the one idle the longest
+        let mut entry = match self.downloads.iter().position(|d| d.download_id == download_id) {
+            Some(index) => self.downloads.remove(index),
+            None => DownloadThroughput {
+                download_id: download_id.to_string(),
+                ..Default::default()
+            },
+        };
+        entry.total_bytes += bytes;
+        if let Some(sample) = add_to_current(&mut entry.current, bytes, now) {
+            entry.finish_sample(sample);
+        }
+        self.downloads.push(entry);
+        if self.downloads.len() > MAX_TRACKED_DOWNLOADS {
+            let excess = self.downloads.len() - MAX_TRACKED_DOWNLOADS;
+            self.downloads.drain(..excess);
+        }
+    }
+
+    /// Close every sample whose second is over
+    fn flush(&mut self, now: u64) {
+        if let Some(sample) = take_finished(&mut self.current, now) {
+            self.finish_sample(sample);
+        }
+        for download in &mut self.downloads {
+            if let Some(sample) = take_finished(&mut download.current, now) {
+                download.finish_sample(sample);
+            }
+        }
+    }
+
+    fn finish_sample(&mut self, sample: ThroughputSample) {
+        add_to_bucket(&mut self.hourly, &sample, HOUR_SECS, MAX_HOURLY_BUCKETS);
+        add_to_bucket(&mut self.daily, &sample, DAY_SECS, MAX_DAILY_BUCKETS);
+        push_bounded(&mut self.samples, sample, MAX_SAMPLES);
+    }
+
+    /// Everything recorded up to `now`, with only the series of `download_id` if one is given
+    pub fn snapshot(&mut self, now: u64, download_id: Option<&str>) -> ThroughputHistory {
+        self.flush(now);
+        let mut snapshot = self.clone();
+        if let Some(download_id) = download_id {
+            snapshot.downloads.retain(|d| d.download_id == download_id);
+        }
+        snapshot
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
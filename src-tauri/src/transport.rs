@@ -0,0 +1,363 @@
+//! Download transport module
+//! The network and clock the download manager runs on, behind traits so a stand-in server and clock can replace them
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::time::{Duration, Instant};
+
+const USER_AGENT: &str = "YuukiPS-Launcher/1.0 (Download Manager)";
+/// HEAD requests only carry headers, so they get short timeouts
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+const PROBE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body of a response, read one chunk at a time
+#[async_trait]
+pub trait ResponseBody: Send {
+    /// The next chunk, or None once the body has been read completely
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String>;
+}
+
+/// Status, headers and streamed body of one response
+pub struct TransportResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    content_length: Option<u64>,
+    body: Box<dyn ResponseBody>,
+}
+
+impl TransportResponse {
+    pub fn new(status: StatusCode, headers: HeaderMap, content_length: Option<u64>, body: Box<dyn ResponseBody>) -> Self {
+        Self { status, headers, content_length, body }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        self.body.chunk().await
+    }
+}
+
+/// Where the download manager sends its requests
+#[async_trait]
+pub trait DownloadTransport: Send + Sync {
+    /// Ask for the size, range support and validators of a file
+    async fn head(&self, url: &str, headers: &[(&str, String)]) -> Result<TransportResponse, String>;
+
+    /// Fetch a file, or part of it with `Range`/`If-Range` among `headers`; custom headers come first and a
+    /// `User-Agent` among them replaces the transport's own
+    async fn get(&self, url: &str, headers: &[(&str, String)]) -> Result<TransportResponse, String>;
+}
+
+/// Time source for timestamps, throttling, timeouts and retry delays
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// Monotonic time, for intervals and timeouts
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, for timestamps that are saved or shown
+    fn utc_now(&self) -> DateTime<Utc>;
+
+    async fn sleep(&self, duration: Duration);
+
+    /// Seconds since the epoch
+    fn unix_time(&self) -> u64 {
+        self.utc_now().timestamp().max(0) as u64
+    }
+
+    /// Wall-clock time in the local time zone, for download and speed windows
+    fn local_now(&self) -> DateTime<Local> {
+        self.utc_now().with_timezone(&Local)
+    }
+}
+
+/// HTTP over the network with reqwest
+pub struct ReqwestTransport {
+    probe_client: reqwest::Client,
+    transfer_client: reqwest::Client, // no overall timeout, a transfer can take hours
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        let probe_client = reqwest::Client::builder()
+            .timeout(PROBE_TIMEOUT)
+            .connect_timeout(PROBE_CONNECT_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        let transfer_client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self { probe_client, transfer_client }
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ReqwestBody(reqwest::Response);
+
+#[async_trait]
+impl ResponseBody for ReqwestBody {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        self.0.chunk().await
+            .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn into_transport_response(response: reqwest::Response) -> TransportResponse {
+    TransportResponse::new(
+        response.status(),
+        response.headers().clone(),
+        response.content_length(),
+        Box::new(ReqwestBody(response)),
+    )
+}
+
+#[async_trait]
+impl DownloadTransport for ReqwestTransport {
+    async fn head(&self, url: &str, headers: &[(&str, String)]) -> Result<TransportResponse, String> {
+        let mut request = self.probe_client.head(url);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        request.send().await
+            .map(into_transport_response)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn get(&self, url: &str, headers: &[(&str, String)]) -> Result<TransportResponse, String> {
+        let mut request = self.transfer_client.get(url);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        request.send().await
+            .map(into_transport_response)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The real clock
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// In-memory stand-ins for the network and the clock, for driving a `DownloadEngine` in tests
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const CHUNK_SIZE: usize = 16 * 1024;
+
+    struct FakeFile {
+        body: Arc<Vec<u8>>,
+        held: Arc<AtomicBool>,    // bodies wait before their first chunk while set
+        fail_after: Option<usize>, // the next response breaks off after this many bytes
+    }
+
+    /// Serves files from memory with `Accept-Ranges: bytes`, honouring `Range: bytes=N-`
+    #[derive(Default)]
+    pub struct FakeTransport {
+        files: Mutex<HashMap<String, FakeFile>>,
+        ranges: Mutex<Vec<(String, Option<String>)>>, // `Range` of every GET, by URL
+    }
+
+    impl FakeTransport {
+        pub fn serve(&self, url: &str, body: Vec<u8>) {
+            self.files.lock().unwrap().insert(url.to_string(), FakeFile {
+                body: Arc::new(body),
+                held: Arc::new(AtomicBool::new(false)),
+                fail_after: None,
+            });
+        }
+
+        /// Keep responses for `url` from sending anything until `release`
+        pub fn hold(&self, url: &str) {
+            self.files.lock().unwrap()[url].held.store(true, Ordering::SeqCst);
+        }
+
+        pub fn release(&self, url: &str) {
+            self.files.lock().unwrap()[url].held.store(false, Ordering::SeqCst);
+        }
+
+        /// Break the next response for `url` off with a read error after `bytes` bytes
+        pub fn fail_once_after(&self, url: &str, bytes: usize) {
+            self.files.lock().unwrap().get_mut(url).unwrap().fail_after = Some(bytes);
+        }
+
+        /// `Range` header of each GET for `url`, in order
+        pub fn ranges(&self, url: &str) -> Vec<Option<String>> {
+            self.ranges.lock().unwrap().iter()
+                .filter(|(requested, _)| requested == url)
+                .map(|(_, range)| range.clone())
+                .collect()
+        }
+
+        fn file_headers(len: usize) -> HeaderMap {
+            let mut headers = HeaderMap::new();
+            headers.insert("Accept-Ranges", HeaderValue::from_static("bytes"));
+            headers.insert("ETag", HeaderValue::from_static("\"fake\""));
+            headers.insert("Content-Length", HeaderValue::from(len));
+            headers
+        }
+
+        fn not_found() -> TransportResponse {
+            TransportResponse::new(StatusCode::NOT_FOUND, HeaderMap::new(), Some(0), Box::new(FakeBody::empty()))
+        }
+    }
+
+    struct FakeBody {
+        data: Arc<Vec<u8>>,
+        position: usize,
+        end: usize,
+        fail_at: Option<usize>,
+        held: Arc<AtomicBool>,
+    }
+
+    impl FakeBody {
+        fn empty() -> Self {
+            Self { data: Arc::new(Vec::new()), position: 0, end: 0, fail_at: None, held: Arc::new(AtomicBool::new(false)) }
+        }
+    }
+
+    #[async_trait]
+    impl ResponseBody for FakeBody {
+        async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+            while self.held.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+            if self.fail_at == Some(self.position) {
+                return Err("connection reset by fake server".to_string());
+            }
+            if self.position >= self.end {
+                return Ok(None);
+            }
+            let limit = self.fail_at.unwrap_or(self.end).min(self.end);
+            let next = (self.position + CHUNK_SIZE).min(limit);
+            let chunk = self.data[self.position..next].to_vec();
+            self.position = next;
+            Ok(Some(chunk))
+        }
+    }
+
+    #[async_trait]
+    impl DownloadTransport for FakeTransport {
+        async fn head(&self, url: &str, _headers: &[(&str, String)]) -> Result<TransportResponse, String> {
+            let files = self.files.lock().unwrap();
+            let Some(file) = files.get(url) else {
+                return Ok(Self::not_found());
+            };
+            let len = file.body.len();
+            Ok(TransportResponse::new(StatusCode::OK, Self::file_headers(len), Some(len as u64), Box::new(FakeBody::empty())))
+        }
+
+        async fn get(&self, url: &str, headers: &[(&str, String)]) -> Result<TransportResponse, String> {
+            let range = headers.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("range"))
+                .map(|(_, value)| value.clone());
+            self.ranges.lock().unwrap().push((url.to_string(), range.clone()));
+
+            let mut files = self.files.lock().unwrap();
+            let Some(file) = files.get_mut(url) else {
+                return Ok(Self::not_found());
+            };
+            let len = file.body.len();
+            let start = range.as_deref()
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.split('-').next())
+                .and_then(|start| start.parse::<usize>().ok());
+
+            let mut headers = Self::file_headers(len);
+            let (status, start) = match start {
+                Some(start) if start < len => {
+                    let content_range = format!("bytes {}-{}/{}", start, len - 1, len);
+                    headers.insert("Content-Range", HeaderValue::from_str(&content_range).unwrap());
+                    headers.insert("Content-Length", HeaderValue::from(len - start));
+                    (StatusCode::PARTIAL_CONTENT, start)
+                }
+                Some(_) => return Ok(TransportResponse::new(StatusCode::RANGE_NOT_SATISFIABLE, HeaderMap::new(), Some(0), Box::new(FakeBody::empty()))),
+                None => (StatusCode::OK, 0),
+            };
+            let body = FakeBody {
+                data: file.body.clone(),
+                position: start,
+                end: len,
+                fail_at: file.fail_after.take().map(|bytes| start + bytes),
+                held: file.held.clone(),
+            };
+            Ok(TransportResponse::new(status, headers, Some((len - start) as u64), Box::new(body)))
+        }
+    }
+
+    /// Clock that only moves when told to; sleeping moves it forward instead of waiting
+    pub struct ManualClock {
+        start: Instant,
+        utc_start: DateTime<Utc>,
+        elapsed: Mutex<Duration>,
+    }
+
+    impl Default for ManualClock {
+        fn default() -> Self {
+            Self { start: Instant::now(), utc_start: Utc::now(), elapsed: Mutex::new(Duration::ZERO) }
+        }
+    }
+
+    impl ManualClock {
+        pub fn advance(&self, duration: Duration) {
+            *self.elapsed.lock().unwrap() += duration;
+        }
+
+        fn elapsed(&self) -> Duration {
+            *self.elapsed.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed()
+        }
+
+        fn utc_now(&self) -> DateTime<Utc> {
+            self.utc_start + chrono::Duration::from_std(self.elapsed()).unwrap_or_default()
+        }
+
+        async fn sleep(&self, duration: Duration) {
+            self.advance(duration);
+            tokio::task::yield_now().await;
+        }
+    }
+}
@@ -334,6 +334,129 @@ pub async fn calculate_md5_chunked(file_path: &Path) -> Result<String, String> {
     }
 }
 
+/// Calculate MD5 hash of a large file without a timeout, reading it in 1MB blocks on a blocking thread
+pub async fn calculate_md5_streamed(file_path: &Path) -> Result<String, String> {
+    use std::io::Read;
+    
+    let path = file_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = fs::File::open(&path)
+            .map_err(|e| format!("Failed to open file for MD5 calculation: {}", e))?;
+        let mut context = md5::Context::new();
+        let mut buffer = vec![0u8; 1024 * 1024];
+        
+        loop {
+            let bytes_read = file.read(&mut buffer)
+                .map_err(|e| format!("Failed to read file chunk: {}", e))?;
+            if bytes_read == 0 {
+                break;
+            }
+            context.consume(&buffer[..bytes_read]);
+        }
+        
+        Ok(format!("{:x}", context.compute()))
+    })
+    .await
+    .map_err(|e| format!("MD5 calculation task failed: {}", e))?
+}
+
+/// Calculate SHA-256 hash of a large file, reading it in 1MB blocks on a blocking thread
+pub async fn calculate_sha256_streamed(file_path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+    
+    let path = file_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = fs::File::open(&path)
+            .map_err(|e| format!("Failed to open file for SHA-256 calculation: {}", e))?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 1024 * 1024];
+        
+        loop {
+            let bytes_read = file.read(&mut buffer)
+                .map_err(|e| format!("Failed to read file chunk: {}", e))?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+        }
+        
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .map_err(|e| format!("SHA-256 calculation task failed: {}", e))?
+}
+
+/// Write `contents` to a temporary file next to `path` and flush it to disk
+fn write_synced_temp_file(path: &Path, contents: &[u8]) -> Result<std::path::PathBuf, String> {
+    use std::io::Write;
+    
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    
+    let mut file = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+    Ok(temp_path)
+}
+
+/// Replace a file so that readers see either the old or the new contents, never a partial write
+pub fn write_file_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let temp_path = write_synced_temp_file(path, contents)?;
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// Like `write_file_atomic`, but the replaced file is kept at `backup_path` as a fallback.
+/// If the process dies between the two renames only the backup exists, so readers should try it next.
+pub fn write_file_atomic_with_backup(path: &Path, contents: &[u8], backup_path: &Path) -> Result<(), String> {
+    let temp_path = write_synced_temp_file(path, contents)?;
+    if path.exists() {
+        fs::rename(path, backup_path)
+            .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    }
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// Exclusive lock on a file, released when dropped, that keeps two processes off the same state
+pub struct FileLock {
+    _file: fs::File,
+}
+
+impl FileLock {
+    /// Take the lock without waiting; fails while another process holds it
+    pub fn try_acquire(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        
+        let mut options = fs::OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            options.share_mode(0); // no other handle may open the file while this one is open
+        }
+        let file = options.open(path)
+            .map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+        
+        #[cfg(not(target_os = "windows"))]
+        {
+            use std::os::unix::io::AsRawFd;
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                return Err(format!("Failed to lock {}: {}", path.display(), std::io::Error::last_os_error()));
+            }
+        }
+        
+        Ok(Self { _file: file })
+    }
+}
+
 /// Create parent directories for a file path if they don't exist
 pub fn create_parent_directories(file_path: &Path) -> Result<(), String> {
     if let Some(parent) = file_path.parent() {
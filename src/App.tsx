@@ -148,6 +148,31 @@ function App() {
     initializeApp();
   }, [loadGames, checkForUpdates]);
 
+  // Store game folders registered by download completion pipelines with the other game directories
+  useEffect(() => {
+    const unlisten = DownloadService.onGameInstallRegistered(({ gameId, channel, version, folder }) => {
+      const storageKey = `game-${gameId}-directories-v2`;
+      let directories: Record<string, Record<number, string> | string> = {};
+      try {
+        const saved = localStorage.getItem(storageKey);
+        if (saved) {
+          directories = JSON.parse(saved);
+        }
+      } catch (error) {
+        console.error('Failed to parse saved directories:', error);
+      }
+      // An old-format entry (version -> path) is replaced by the per-channel format
+      const existing = directories[version];
+      directories[version] = { ...(typeof existing === 'object' ? existing : {}), [channel]: folder };
+      localStorage.setItem(storageKey, JSON.stringify(directories));
+      console.log(`Registered ${folder} for game ${gameId} version ${version} (channel ${channel})`);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   // Disable right-click context menu globally
   useEffect(() => {
     const handleContextMenu = (e: MouseEvent) => {
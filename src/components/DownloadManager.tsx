@@ -111,9 +111,37 @@ export const DownloadManager: React.FC<DownloadManagerProps> = ({ isOpen, onClos
       initializeDownloads();
       loadDefaultDownloadFolder();
 
-      // Set up polling for download updates
-      const interval = setInterval(loadData, 1000);
-      return () => clearInterval(interval);
+      // Progress is pushed by the backend; status and queue changes trigger a full reload
+      let lastSequence = 0;
+      const unlisteners = [
+        DownloadService.onDownloadProgress((event) => {
+          if (event.sequence <= lastSequence) return;
+          lastSequence = event.sequence;
+          setDownloads(prev => prev.map(d => d.id === event.id ? {
+            ...d,
+            downloadedSize: event.downloadedSize,
+            totalSize: event.totalSize,
+            progress: event.progress,
+            speed: event.speed,
+            timeRemaining: event.timeRemaining
+          } : d));
+        }),
+        DownloadService.onDownloadStatus((event) => {
+          lastSequence = Math.max(lastSequence, event.sequence);
+          loadData();
+        }),
+        DownloadService.onDownloadQueue((event) => {
+          lastSequence = Math.max(lastSequence, event.sequence);
+          loadData();
+        })
+      ];
+
+      // Slow fallback poll for stats and activities
+      const interval = setInterval(loadData, 5000);
+      return () => {
+        clearInterval(interval);
+        unlisteners.forEach(unlisten => unlisten.then(fn => fn()));
+      };
     }
   }, [isOpen, loadData]);
 
@@ -550,6 +578,8 @@ export const DownloadManager: React.FC<DownloadManagerProps> = ({ isOpen, onClos
         return <X className="w-4 h-4 text-gray-500" />;
       case 'queued':
         return <Clock className="w-4 h-4 text-orange-500" />;
+      case 'verifying':
+        return <RefreshCw className="w-4 h-4 text-purple-500 animate-spin" />;
       default:
         return <Clock className="w-4 h-4 text-gray-500" />;
     }
@@ -563,7 +593,7 @@ export const DownloadManager: React.FC<DownloadManagerProps> = ({ isOpen, onClos
       if (download.status === 'downloading') {
         await DownloadService.pauseDownload(id);
         await addUserInteraction(`Paused download: ${download.fileName || download.id}`);
-      } else if (download.status === 'paused') {
+      } else if (download.status === 'paused' || download.status === 'insufficient_space') {
         await DownloadService.resumeDownload(id);
         await addUserInteraction(`Resumed download: ${download.fileName || download.id}`);
       }
@@ -1,6 +1,6 @@
 import React, { useState, useEffect, useCallback, useMemo } from 'react';
 import { Game, GameEngine } from '../types';
-import { Play, Settings, Download, Clock, Square } from 'lucide-react';
+import { Play, Settings, Download, Clock, Square, WifiOff } from 'lucide-react';
 import { EngineSelectionModal } from './EngineSelectionModal';
 import { SSLCertificateModal } from './SSLCertificateModal';
 import { PatchErrorInfo, PatchErrorModal } from './PatchErrorModal';
@@ -40,6 +40,7 @@ export const GameDetails: React.FC<GameDetailsProps> = ({ game, onGameUpdate, on
   const [showProxyMessage, setShowProxyMessage] = useState(false);
   const [proxyMessage, setProxyMessage] = useState('');
   const [recommendedServer, setRecommendedServer] = useState('');
+  const [patchStatus, setPatchStatus] = useState<string | null>(null); // set when a cached patch plan was used offline
 
   const getGameFolderPath = useCallback((version: string, channel: number): string => {
     const storageKey = `${STORAGE_KEY_PREFIX}${game.id}${STORAGE_KEY_SUFFIX}`;
@@ -157,6 +158,10 @@ export const GameDetails: React.FC<GameDetailsProps> = ({ game, onGameUpdate, on
           if (parsedResult.processId) {
             setGameProcessId(parsedResult.processId);
           }
+          // Set when the patch API was unreachable and a cached patch plan was used
+          if (parsedResult.patchStatus?.startsWith('Offline')) {
+            setPatchStatus(parsedResult.patchStatus);
+          }
         } catch (error) {
           console.error('Failed to parse game launch result:', error, result);
         }
@@ -269,6 +274,7 @@ export const GameDetails: React.FC<GameDetailsProps> = ({ game, onGameUpdate, on
 
   const handleEngineLaunch = useCallback(async (engine: GameEngine, version: string, channel: number) => {
     setIsLaunching(true);
+    setPatchStatus(null);
     try {
       // First validate game folder path
       console.log("channel_id: "+channel)
@@ -311,6 +317,9 @@ export const GameDetails: React.FC<GameDetailsProps> = ({ game, onGameUpdate, on
 
       if (typeof patchCheckResult === 'string') {
         const checkResult = JSON.parse(patchCheckResult);
+        if (checkResult.cached_at) {
+          setPatchStatus(checkResult.patch_status);
+        }
         
         if (checkResult.has_message && checkResult.message) {
           // Check if this message should be ignored
@@ -580,6 +589,13 @@ export const GameDetails: React.FC<GameDetailsProps> = ({ game, onGameUpdate, on
                 </div>
               )}
 
+              {patchStatus && (
+                <div className="flex items-center space-x-2 text-yellow-400">
+                  <WifiOff className="w-5 h-5" />
+                  <span>{patchStatus}</span>
+                </div>
+              )}
+
               {!isInstalled && (
                 <div className="flex items-center space-x-2 text-red-400">
                   <Download className="w-5 h-5" />
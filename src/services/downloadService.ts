@@ -1,5 +1,23 @@
 import { invoke } from '@tauri-apps/api/core';
-import { DownloadItem, DownloadStats } from '../types';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import {
+  DownloadItem,
+  DownloadOptions,
+  DownloadPriority,
+  DownloadListFormat,
+  DownloadListImport,
+  CompletionAction,
+  CompletionPipeline,
+  GameInstallRegistration,
+  PipelineTarget,
+  HistoryFilter,
+  HistoryPage,
+  DownloadStats,
+  ThroughputHistory,
+  DownloadProgressEvent,
+  DownloadStatusEvent,
+  DownloadQueueEvent
+} from '../types';
 
 /**
  * Service for managing downloads through Tauri backend
@@ -8,13 +26,14 @@ export class DownloadService {
   /**
    * Start a new download
    */
-  static async startDownload(url: string, filePath: string, fileName?: string): Promise<string> {
-    console.log('[DownloadService] Starting download:', { url, filePath, fileName });
+  static async startDownload(url: string, filePath: string, fileName?: string, options?: DownloadOptions): Promise<string> {
+    console.log('[DownloadService] Starting download:', { url, filePath, fileName, options });
     try {
       const downloadId = await invoke<string>('start_download', {
         url,
         filePath,
-        fileName
+        fileName,
+        options
       });
       console.log('[DownloadService] Download started successfully with ID:', downloadId);
       return downloadId;
@@ -66,6 +85,81 @@ export class DownloadService {
     }
   }
 
+  /**
+   * Get waiting downloads in the order they will start
+   */
+  static async getDownloadQueue(): Promise<DownloadItem[]> {
+    try {
+      return await invoke<DownloadItem[]>('get_download_queue');
+    } catch (error) {
+      console.error('[DownloadService] Failed to get download queue:', error);
+      throw new Error(`Failed to get download queue: ${error}`);
+    }
+  }
+
+  /**
+   * Move a waiting download up or down within its priority
+   */
+  static async moveDownload(downloadId: string, direction: 'up' | 'down'): Promise<void> {
+    try {
+      await invoke(direction === 'up' ? 'move_download_up' : 'move_download_down', { downloadId });
+    } catch (error) {
+      console.error('[DownloadService] Failed to move download:', downloadId, error);
+      throw new Error(`Failed to move download: ${error}`);
+    }
+  }
+
+  /**
+   * Make a waiting download the next one to start
+   */
+  static async moveDownloadToFront(downloadId: string): Promise<void> {
+    try {
+      await invoke('move_download_to_front', { downloadId });
+    } catch (error) {
+      console.error('[DownloadService] Failed to move download to front:', downloadId, error);
+      throw new Error(`Failed to move download to front: ${error}`);
+    }
+  }
+
+  /**
+   * Change the priority of a download
+   */
+  static async setDownloadPriority(downloadId: string, priority: DownloadPriority): Promise<void> {
+    try {
+      await invoke('set_download_priority', { downloadId, priority });
+    } catch (error) {
+      console.error('[DownloadService] Failed to set download priority:', downloadId, error);
+      throw new Error(`Failed to set download priority: ${error}`);
+    }
+  }
+
+  /**
+   * Pin a download ahead of unpinned ones, or return it to normal priority
+   */
+  static async pinDownload(downloadId: string, pinned: boolean): Promise<void> {
+    try {
+      await invoke('pin_download', { downloadId, pinned });
+    } catch (error) {
+      console.error('[DownloadService] Failed to pin download:', downloadId, error);
+      throw new Error(`Failed to pin download: ${error}`);
+    }
+  }
+
+  /**
+   * Check a download's size and expected checksum, repairing the size if possible
+   */
+  static async verifyAndRepairDownload(downloadId: string): Promise<string> {
+    console.log('[DownloadService] Verifying download:', downloadId);
+    try {
+      const result = await invoke<string>('verify_and_repair_download', { downloadId });
+      console.log('[DownloadService] Download verified:', downloadId, result);
+      return result;
+    } catch (error) {
+      console.error('[DownloadService] Failed to verify download:', downloadId, error);
+      throw new Error(`Failed to verify download: ${error}`);
+    }
+  }
+
   /**
    * Cancel a download
    */
@@ -168,6 +262,28 @@ export class DownloadService {
     }
   }
 
+  /**
+   * Get bytes per second over time, overall and per download (or only downloadId), with hourly and daily totals
+   */
+  static async getThroughputHistory(downloadId?: string): Promise<ThroughputHistory> {
+    try {
+      return await invoke<ThroughputHistory>('get_throughput_history', { downloadId });
+    } catch (error) {
+      throw new Error(`Failed to get throughput history: ${error}`);
+    }
+  }
+
+  /**
+   * Forget the recorded throughput
+   */
+  static async clearThroughputHistory(): Promise<void> {
+    try {
+      await invoke('clear_throughput_history');
+    } catch (error) {
+      throw new Error(`Failed to clear throughput history: ${error}`);
+    }
+  }
+
   /**
    * Open download location in file explorer
    */
@@ -182,9 +298,9 @@ export class DownloadService {
   /**
    * Search downloads by name
    */
-  static async searchDownloads(query: string): Promise<DownloadItem[]> {
+  static async searchDownloads(query: string, filter?: HistoryFilter): Promise<DownloadItem[]> {
     try {
-      const downloads = await invoke<DownloadItem[]>('search_downloads', { query });
+      const downloads = await invoke<DownloadItem[]>('search_downloads', { query, filter });
       return downloads;
     } catch (error) {
       throw new Error(`Failed to search downloads: ${error}`);
@@ -194,15 +310,125 @@ export class DownloadService {
   /**
    * Get downloads by status
    */
-  static async getDownloadsByStatus(status: string): Promise<DownloadItem[]> {
+  static async getDownloadsByStatus(status: string, filter?: HistoryFilter): Promise<DownloadItem[]> {
     try {
-      const downloads = await invoke<DownloadItem[]>('get_downloads_by_status', { status });
+      const downloads = await invoke<DownloadItem[]>('get_downloads_by_status', { status, filter });
       return downloads;
     } catch (error) {
       throw new Error(`Failed to get downloads by status: ${error}`);
     }
   }
 
+  /**
+   * Search finished downloads, one page at a time
+   */
+  static async searchDownloadHistory(filter: HistoryFilter): Promise<HistoryPage> {
+    try {
+      return await invoke<HistoryPage>('search_download_history', { filter });
+    } catch (error) {
+      throw new Error(`Failed to search download history: ${error}`);
+    }
+  }
+
+  /**
+   * Remove one finished download from the history; the file stays on disk
+   */
+  static async removeDownloadHistoryEntry(downloadId: string): Promise<void> {
+    try {
+      await invoke('remove_download_history_entry', { downloadId });
+    } catch (error) {
+      throw new Error(`Failed to remove history entry: ${error}`);
+    }
+  }
+
+  /**
+   * Clear the download history; files stay on disk
+   */
+  static async clearDownloadHistory(): Promise<void> {
+    try {
+      await invoke('clear_download_history');
+    } catch (error) {
+      throw new Error(`Failed to clear download history: ${error}`);
+    }
+  }
+
+  /**
+   * Queue every file of a Metalink document or aria2 list below targetFolder; the format is detected when omitted
+   */
+  static async importDownloadList(path: string, targetFolder: string, format?: DownloadListFormat): Promise<DownloadListImport> {
+    try {
+      return await invoke<DownloadListImport>('import_download_list', { path, targetFolder, format });
+    } catch (error) {
+      throw new Error(`Failed to import download list: ${error}`);
+    }
+  }
+
+  /**
+   * Write the unfinished downloads to a Metalink document or aria2 list; the format follows the extension when omitted
+   */
+  static async exportDownloadQueue(path: string, format?: DownloadListFormat): Promise<number> {
+    try {
+      return await invoke<number>('export_download_queue', { path, format });
+    } catch (error) {
+      throw new Error(`Failed to export download queue: ${error}`);
+    }
+  }
+
+  /**
+   * Run actions once a download or group has completed, replacing its previous actions; starts at once if already complete
+   */
+  static async setCompletionActions(target: PipelineTarget, actions: CompletionAction[]): Promise<CompletionPipeline> {
+    try {
+      return await invoke<CompletionPipeline>('set_completion_actions', { target, actions });
+    } catch (error) {
+      throw new Error(`Failed to set completion actions: ${error}`);
+    }
+  }
+
+  /**
+   * Get all completion pipelines with the status of each step
+   */
+  static async getCompletionPipelines(): Promise<CompletionPipeline[]> {
+    try {
+      return await invoke<CompletionPipeline[]>('get_completion_pipelines');
+    } catch (error) {
+      throw new Error(`Failed to get completion pipelines: ${error}`);
+    }
+  }
+
+  /**
+   * Run a failed completion pipeline again from the step that failed
+   */
+  static async retryCompletionPipeline(pipelineId: string): Promise<void> {
+    try {
+      await invoke('retry_completion_pipeline', { pipelineId });
+    } catch (error) {
+      throw new Error(`Failed to retry completion pipeline: ${error}`);
+    }
+  }
+
+  /**
+   * Stop a running completion pipeline; a retry resumes an interrupted extraction
+   */
+  static async cancelCompletionPipeline(pipelineId: string): Promise<void> {
+    try {
+      await invoke('cancel_completion_pipeline', { pipelineId });
+    } catch (error) {
+      throw new Error(`Failed to cancel completion pipeline: ${error}`);
+    }
+  }
+
+  /**
+   * Remove a completion pipeline that is not running
+   */
+  static async removeCompletionPipeline(pipelineId: string): Promise<void> {
+    try {
+      await invoke('remove_completion_pipeline', { pipelineId });
+    } catch (error) {
+      throw new Error(`Failed to remove completion pipeline: ${error}`);
+    }
+  }
+
   /**
    * Bulk pause downloads
    */
@@ -414,6 +640,41 @@ export class DownloadService {
     }
   }
 
+  /**
+   * Subscribe to throttled progress events pushed by the backend
+   */
+  static onDownloadProgress(handler: (event: DownloadProgressEvent) => void): Promise<UnlistenFn> {
+    return listen<DownloadProgressEvent>('download-progress', (event) => handler(event.payload));
+  }
+
+  /**
+   * Subscribe to download status transitions
+   */
+  static onDownloadStatus(handler: (event: DownloadStatusEvent) => void): Promise<UnlistenFn> {
+    return listen<DownloadStatusEvent>('download-status', (event) => handler(event.payload));
+  }
+
+  /**
+   * Subscribe to queue snapshots (added, removed, started, queued or held downloads)
+   */
+  static onDownloadQueue(handler: (event: DownloadQueueEvent) => void): Promise<UnlistenFn> {
+    return listen<DownloadQueueEvent>('download-queue', (event) => handler(event.payload));
+  }
+
+  /**
+   * Subscribe to completion pipeline changes (started, step finished, failed, completed)
+   */
+  static onCompletionPipeline(handler: (pipeline: CompletionPipeline) => void): Promise<UnlistenFn> {
+    return listen<CompletionPipeline>('download-pipeline', (event) => handler(event.payload));
+  }
+
+  /**
+   * Subscribe to game folders registered by a completion pipeline
+   */
+  static onGameInstallRegistered(handler: (registration: GameInstallRegistration) => void): Promise<UnlistenFn> {
+    return listen<GameInstallRegistration>('game-install-registered', (event) => handler(event.payload));
+  }
+
   /**
    * Get current max simultaneous downloads setting
    */
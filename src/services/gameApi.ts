@@ -1,4 +1,4 @@
-import { Game, GameEngine } from '../types';
+import { Game, GameEngine, PatchSessionInfo } from '../types';
 import { invoke } from '@tauri-apps/api/core';
 
 // Proxy management functions
@@ -54,6 +54,23 @@ export const startProxyWithSSLCheck = async (): Promise<{ success: boolean; mess
   }
 };
 
+// Patch crash-recovery functions
+export const getUnfinishedPatchSession = async (): Promise<PatchSessionInfo | null> => {
+  try {
+    return await invoke('get_unfinished_patch_session') as PatchSessionInfo | null;
+  } catch (error) {
+    throw new Error(`Failed to get unfinished patch session: ${error}`);
+  }
+};
+
+export const recoverPatchSession = async (): Promise<string> => {
+  try {
+    return await invoke('recover_patch_session') as string;
+  } catch (error) {
+    throw new Error(`Failed to recover patch session: ${error}`);
+  }
+};
+
 export class GameApiService {
   private static fetchPromise: Promise<Game[]> | null = null;
   
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { DownloadAuth } from '../types';
 
 export interface AppSettings {
   speedLimit: number;
@@ -6,6 +7,40 @@ export interface AppSettings {
   maxSimultaneousDownloads: number;
 }
 
+export type ScheduleWeekday = 'Mon' | 'Tue' | 'Wed' | 'Thu' | 'Fri' | 'Sat' | 'Sun';
+
+export interface ScheduleWindow {
+  days: ScheduleWeekday[]; // empty = every day
+  start: string; // "HH:MM", local time
+  end: string; // "HH:MM", at or before start = runs past midnight
+}
+
+export interface SpeedWindow extends ScheduleWindow {
+  speed_limit_mbps: number;
+}
+
+export interface DownloadSchedule {
+  enabled: boolean;
+  download_windows: ScheduleWindow[];
+  speed_windows: SpeedWindow[];
+}
+
+export interface DownloadScheduleStatus {
+  enabled: boolean;
+  downloadsAllowed: boolean;
+  speedLimitMbps: number;
+  nextDownloadChange: string | null;
+  nextSpeedChange: string | null;
+}
+
+// Headers added to every download from a host; '*.example.com' matches its subdomains
+export interface HostHeaderRule {
+  host: string;
+  headers?: Record<string, string>;
+  user_agent?: string;
+  auth?: DownloadAuth;
+}
+
 export class SettingsService {
   /**
    * Get all application settings
@@ -120,4 +155,66 @@ export class SettingsService {
     }
   }
 
-}
\ No newline at end of file
+  /**
+   * Get the weekly download schedule
+   */
+  static async getDownloadSchedule(): Promise<DownloadSchedule> {
+    try {
+      return await invoke<DownloadSchedule>('get_app_download_schedule');
+    } catch (error) {
+      console.error('[SettingsService] Failed to get download schedule:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Set the weekly download schedule
+   */
+  static async setDownloadSchedule(schedule: DownloadSchedule): Promise<void> {
+    try {
+      console.log('[SettingsService] Setting download schedule:', schedule);
+      await invoke('set_app_download_schedule', { schedule });
+      console.log('[SettingsService] Download schedule set successfully');
+    } catch (error) {
+      console.error('[SettingsService] Failed to set download schedule:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Get whether downloads may run right now and when that changes
+   */
+  static async getDownloadScheduleStatus(): Promise<DownloadScheduleStatus> {
+    try {
+      return await invoke<DownloadScheduleStatus>('get_download_schedule_status');
+    } catch (error) {
+      console.error('[SettingsService] Failed to get download schedule status:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Get the headers added to downloads from matching hosts
+   */
+  static async getDownloadHeaderRules(): Promise<HostHeaderRule[]> {
+    try {
+      return await invoke<HostHeaderRule[]>('get_app_download_header_rules');
+    } catch (error) {
+      console.error('[SettingsService] Failed to get download header rules:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * Set the headers added to downloads from matching hosts
+   */
+  static async setDownloadHeaderRules(rules: HostHeaderRule[]): Promise<void> {
+    try {
+      await invoke('set_app_download_header_rules', { rules });
+      console.log('[SettingsService] Download header rules set successfully:', rules.length);
+    } catch (error) {
+      console.error('[SettingsService] Failed to set download header rules:', error);
+      throw error;
+    }
+  }
+}
@@ -89,7 +89,7 @@ export interface DownloadItem {
   downloadedSize: number;
   progress: number;
   speed: number; // bytes per second
-  status: 'downloading' | 'paused' | 'completed' | 'error' | 'cancelled' | 'queued';
+  status: 'downloading' | 'paused' | 'completed' | 'error' | 'cancelled' | 'queued' | 'insufficient_space' | 'verifying';
   timeRemaining: number; // seconds
   url: string;
   filePath: string;
@@ -98,6 +98,141 @@ export interface DownloadItem {
   errorMessage?: string;
   userPaused?: boolean; // Track if pause was initiated by user
   resumeSupported?: boolean; // Whether the download supports resuming
+  mirrors?: string[]; // Alternative URLs for the same file, tried when `url` fails
+  activeUrl?: string; // Mirror currently used for the transfer
+  expectedMd5?: string; // Checked after the transfer, while the status is 'verifying'
+  expectedSha256?: string;
+  verifyAttempts?: number; // Checksum mismatches so far, each one downloads the file again
+  priority?: DownloadPriority;
+  queuePosition?: number; // Order within the priority, lower starts first
+  preallocate?: boolean; // File is grown to full size up front, so only downloadedSize tells progress
+  headers?: Record<string, string>; // Sent with every request for this download, after the host rules
+  auth?: DownloadAuth;
+  userAgent?: string;
+}
+
+// Credentials sent as the Authorization header
+export type DownloadAuth =
+  | { type: 'basic'; username: string; password: string }
+  | { type: 'bearer'; token: string };
+
+// Queued downloads start in priority order; 'pinned' is for small patch-related files
+export type DownloadPriority = 'low' | 'normal' | 'high' | 'pinned';
+
+// Optional settings accepted by start_download
+export interface DownloadOptions {
+  groupId?: string;
+  expectedSize?: number;
+  mirrors?: string[]; // Alternative URLs for the same file, tried when `url` fails
+  expectedMd5?: string;
+  expectedSha256?: string;
+  priority?: DownloadPriority;
+  preallocate?: boolean; // Reserve the whole file on disk as soon as its size is known
+  headers?: Record<string, string>; // e.g. Referer or an API key; Range and Host are set by the downloader
+  auth?: DownloadAuth;
+  userAgent?: string;
+}
+
+// Filter for searching current and finished downloads; dates are seconds since the epoch
+export interface HistoryFilter {
+  query?: string; // matched against file name and URL
+  statuses?: DownloadItem['status'][];
+  from?: number;
+  to?: number;
+  offset?: number;
+  limit?: number;
+}
+
+export interface HistoryPage {
+  items: DownloadItem[]; // newest first
+  total: number; // matches before pagination
+}
+
+// Metalink (RFC 5854) document or aria2 input list
+export type DownloadListFormat = 'metalink' | 'aria2';
+
+export interface DownloadListImport {
+  downloadIds: string[];
+  skipped: string[]; // why an entry was not added
+}
+
+// Follow-up actions run once a download or a whole group has completed
+export type CompletionAction =
+  | { type: 'verifyHash' }
+  | { type: 'extract'; targetFolder: string }
+  | { type: 'deleteArchives' } // only after an 'extract' action
+  | { type: 'registerGame'; gameId: number; channel: number; version: string; gameFolder?: string } // folder defaults to the extract target
+  | { type: 'openFolder'; folder?: string };
+
+export type PipelineTarget = { download: string } | { group: string };
+
+export interface PipelineStep {
+  action: CompletionAction;
+  status: 'pending' | 'running' | 'done' | 'skipped' | 'failed';
+  message?: string; // result of the step, or why it failed or was skipped
+  attempts: number;
+  startedAt?: number;
+  finishedAt?: number;
+}
+
+// Pushed as the 'download-pipeline' event whenever it changes
+export interface CompletionPipeline {
+  id: string;
+  target: PipelineTarget;
+  status: 'waiting' | 'running' | 'failed' | 'completed';
+  steps: PipelineStep[];
+  extractedArchives: string[];
+  createdAt: number;
+}
+
+// Pushed as the 'game-install-registered' event by a 'registerGame' step
+export interface GameInstallRegistration {
+  gameId: number;
+  channel: number;
+  version: string;
+  folder: string;
+}
+
+// Patches still applied after a crash or forced close of the launcher
+export interface PatchSessionInfo {
+  gameId: number;
+  version: string;
+  channel: number;
+  gameFolder: string;
+  startedAt: number; // seconds since epoch
+  files: string[];
+}
+
+// Events pushed by the download manager; `sequence` is shared by all three
+export interface DownloadProgressEvent {
+  sequence: number;
+  timestamp: number; // milliseconds since epoch
+  id: string;
+  downloadedSize: number;
+  totalSize: number;
+  progress: number;
+  speed: number;
+  timeRemaining: number;
+}
+
+export interface DownloadStatusEvent {
+  sequence: number;
+  timestamp: number;
+  id: string;
+  status: DownloadItem['status'];
+  previousStatus?: DownloadItem['status'];
+  errorMessage?: string;
+  download: DownloadItem;
+}
+
+export interface DownloadQueueEvent {
+  sequence: number;
+  timestamp: number;
+  reason: string;
+  downloading: string[];
+  queued: string[];
+  held: string[]; // waiting for disk space
+  totalDownloads: number;
 }
 
 export interface DownloadStats {
@@ -108,6 +243,34 @@ export interface DownloadStats {
   average_speed: number;
 }
 
+// Bytes moved during one second; seconds without a sample were idle
+export interface ThroughputSample {
+  timestamp: number; // seconds since epoch
+  bytesPerSecond: number;
+}
+
+// Totals for one UTC hour or day
+export interface ThroughputBucket {
+  start: number;
+  bytes: number;
+  activeSeconds: number; // bytes / activeSeconds is the average rate while transferring
+  peakBytesPerSecond: number;
+}
+
+export interface DownloadThroughput {
+  downloadId: string;
+  samples: ThroughputSample[];
+  totalBytes: number;
+  peakBytesPerSecond: number;
+}
+
+export interface ThroughputHistory {
+  samples: ThroughputSample[]; // all downloads together, up to the last hour of transfer
+  hourly: ThroughputBucket[];
+  daily: ThroughputBucket[];
+  downloads: DownloadThroughput[]; // most recently active last
+}
+
 export interface ActivityEntry {
   id: string;
   timestamp: string;